@@ -0,0 +1,74 @@
+use std::error::Error;
+use std::fmt::{self, Display};
+
+use crate::types::SnakeId;
+
+/// Reasons a wire game can't be converted in to a compact board
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ConversionError {
+    /// The board is larger than the board type, or doesn't have the dimensions it requires
+    BoardTooLarge {
+        /// width of the game's board
+        width: u32,
+        /// height of the game's board
+        height: u32,
+    },
+    /// The game has more snakes than the board type has room for
+    TooManySnakes(usize),
+    /// The game is played with a ruleset the board type doesn't simulate
+    WrongRuleset(String),
+    /// The body of the snake with this id can't be represented, e.g. it doesn't start at its
+    /// head or has a bad stack
+    InconsistentBody(String),
+    /// The snake with this id isn't in the snake id map
+    UnknownSnakeId(String),
+}
+
+impl Display for ConversionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConversionError::BoardTooLarge { width, height } => {
+                write!(f, "a {width}x{height} board doesn't fit this board type")
+            }
+            ConversionError::TooManySnakes(snakes) => {
+                write!(f, "{snakes} snakes don't fit this board type")
+            }
+            ConversionError::WrongRuleset(name) => {
+                write!(f, "the {name} ruleset isn't supported by this board type")
+            }
+            ConversionError::InconsistentBody(id) => write!(f, "snake {id} has a bad body"),
+            ConversionError::UnknownSnakeId(id) => write!(f, "snake {id} has no snake id"),
+        }
+    }
+}
+
+impl Error for ConversionError {}
+
+/// Reasons a board can't be simulated
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SimulationError {
+    /// The body of this alive snake doesn't lead from its tail to its head
+    InconsistentBody(SnakeId),
+    /// A move was given for a snake id the board has no room for
+    UnknownSnakeId(SnakeId),
+    /// Simulating the moves left a snake's body inconsistent
+    InconsistentResult,
+}
+
+impl Display for SimulationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SimulationError::InconsistentBody(sid) => {
+                write!(f, "snake {} is inconsistent", sid.as_usize())
+            }
+            SimulationError::UnknownSnakeId(sid) => {
+                write!(f, "snake {} isn't on this board", sid.as_usize())
+            }
+            SimulationError::InconsistentResult => {
+                write!(f, "the simulated board is inconsistent")
+            }
+        }
+    }
+}
+
+impl Error for SimulationError {}
@@ -5,16 +5,47 @@ use tracing::instrument;
 
 use crate::{
     compact_representation::{core::dimensions::Dimensions, CellNum},
-    types::{self, HeadGettableGame, Move, SnakeId, N_MOVES},
+    types::{self, DeathCause, HeadGettableGame, Move, SnakeId, TurnEvent, N_MOVES},
 };
 
-use super::{CellBoard, CellIndex};
+use super::{CellBoard, CellIndex, SimulationError};
 
 /// Which mode to evaluate in
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum EvaluateMode {
     Wrapped,
     Standard,
+    /// Snakes grow every turn once their starting stack has unfolded, and are kept at full health
+    Constrictor,
+    /// [`EvaluateMode::Constrictor`] on a wrapped board
+    WrappedConstrictor,
+}
+
+impl EvaluateMode {
+    /// This mode, switched to its constrictor counterpart if `constrictor` is set
+    pub fn with_constrictor(self, constrictor: bool) -> Self {
+        match (self, constrictor) {
+            (EvaluateMode::Standard, true) => EvaluateMode::Constrictor,
+            (EvaluateMode::Wrapped, true) => EvaluateMode::WrappedConstrictor,
+            (mode, _) => mode,
+        }
+    }
+
+    /// true if snakes leaving the board enter it again on the opposite edge
+    pub fn is_wrapped(self) -> bool {
+        matches!(
+            self,
+            EvaluateMode::Wrapped | EvaluateMode::WrappedConstrictor
+        )
+    }
+
+    /// true if snakes grow every turn and are kept at full health
+    pub fn is_constrictor(self) -> bool {
+        matches!(
+            self,
+            EvaluateMode::Constrictor | EvaluateMode::WrappedConstrictor
+        )
+    }
 }
 
 #[derive(Copy, Clone, Debug)]
@@ -33,8 +64,8 @@ pub struct AliveMoveResult<T: CellNum> {
     new_tail: CellIndex<T>,
     /// The new health of the snake
     new_health: u8,
-    /// True if the snake ate food
-    ate_food: bool,
+    /// True if the snake grows this turn, because it ate food or plays constrictor
+    grows: bool,
     /// The new length of the snake, after moving and potentially eating
     new_length: u16,
 }
@@ -47,9 +78,10 @@ pub struct AliveMoveResult<T: CellNum> {
 pub enum SinglePlayerMoveResult<T: CellNum> {
     /// Represents the given snake is alive after phase 1 of evaluation
     Alive(AliveMoveResult<T>),
-    /// Represents the snake died during phase 1. Cause it ran into a snake (including itself)
-    /// [excluding head to heads] or went out of bounds
-    Dead,
+    /// Represents the snake died during phase 1. Cause it went out of bounds, moved back onto
+    /// its neck or ran out of health. Moves that weren't simulated, e.g. because the snake is
+    /// already dead, have no cause
+    Dead(Option<DeathCause>),
 }
 
 impl<T: CellNum> SinglePlayerMoveResult<T> {
@@ -61,24 +93,29 @@ impl<T: CellNum> SinglePlayerMoveResult<T> {
     }
 
     pub fn is_dead(&self) -> bool {
-        matches!(self, SinglePlayerMoveResult::Dead)
+        matches!(self, SinglePlayerMoveResult::Dead(_))
     }
 }
 
 impl<T: CellNum, D: Dimensions, const BOARD_SIZE: usize, const MAX_SNAKES: usize>
     CellBoard<T, D, BOARD_SIZE, MAX_SNAKES>
 {
+    /// The result of every given move of every given snake. Fails if a move is given for a snake
+    /// the board has no room for, or an alive snake's body doesn't lead from its tail to its head
     pub fn generate_state<'a, S>(
         &self,
         moves: impl Iterator<Item = &'a (SnakeId, S)>,
         mode: EvaluateMode,
-    ) -> [[SinglePlayerMoveResult<T>; N_MOVES]; MAX_SNAKES]
+    ) -> Result<[[SinglePlayerMoveResult<T>; N_MOVES]; MAX_SNAKES], SimulationError>
     where
         S: 'a + Borrow<[Move]>,
     {
-        let mut new_heads = [[SinglePlayerMoveResult::Dead; 4]; MAX_SNAKES];
+        let mut new_heads = [[SinglePlayerMoveResult::Dead(None); 4]; MAX_SNAKES];
 
         for (id, mvs) in moves {
+            if id.as_usize() >= MAX_SNAKES {
+                return Err(SimulationError::UnknownSnakeId(*id));
+            }
             if self.healths[id.as_usize()] == 0 {
                 continue;
             }
@@ -87,25 +124,18 @@ impl<T: CellNum, D: Dimensions, const BOARD_SIZE: usize, const MAX_SNAKES: usize
                 let old_tail = self
                     .get_cell(old_head)
                     .get_tail_position(old_head)
-                    .unwrap_or_else(|| {
-                        panic!(
-                            "We came from a head so we should have a tail snake: {} health: {}",
-                            id.0,
-                            self.healths[id.as_usize()]
-                        )
-                    });
+                    .ok_or(SimulationError::InconsistentBody(*id))?;
 
                 let new_head_position =
                     old_head.into_position(Self::width()).add_vec(m.to_vector());
-                let new_head = match mode {
-                    EvaluateMode::Wrapped => self.as_wrapped_cell_index(new_head_position),
-                    EvaluateMode::Standard => {
-                        if self.off_board(new_head_position) {
-                            continue;
-                        } else {
-                            CellIndex::<T>::new(new_head_position, Self::width())
-                        }
-                    }
+                let new_head = if mode.is_wrapped() {
+                    self.as_wrapped_cell_index(new_head_position)
+                } else if self.off_board(new_head_position) {
+                    new_heads[id.as_usize()][m.as_index()] =
+                        SinglePlayerMoveResult::Dead(Some(DeathCause::Wall));
+                    continue;
+                } else {
+                    CellIndex::<T>::new(new_head_position, Self::width())
                 };
 
                 // TWe calculate the 'neck' so that we can avoid the 'instant death'
@@ -114,17 +144,23 @@ impl<T: CellNum, D: Dimensions, const BOARD_SIZE: usize, const MAX_SNAKES: usize
                     let mut curr = old_tail;
                     let mut prev = curr;
 
+                    // A consistent body reaches the head in fewer steps than there are cells
+                    let mut steps = 0;
                     while curr != old_head {
                         prev = curr;
-                        curr = self.get_cell(curr).get_next_index().unwrap_or_else(|| {
-                            eprintln!("{}", self);
-                            panic!("snake is inconsistent")
-                        });
+                        steps += 1;
+                        curr = self
+                            .get_cell(curr)
+                            .get_next_index()
+                            .filter(|_| steps < BOARD_SIZE)
+                            .ok_or(SimulationError::InconsistentBody(*id))?;
                     }
 
                     prev
                 };
                 if new_head == neck {
+                    new_heads[id.as_usize()][m.as_index()] =
+                        SinglePlayerMoveResult::Dead(Some(DeathCause::SelfCollision));
                     continue;
                 }
 
@@ -134,24 +170,38 @@ impl<T: CellNum, D: Dimensions, const BOARD_SIZE: usize, const MAX_SNAKES: usize
                 } else {
                     old_tail_cell
                         .get_next_index()
-                        .expect("We specifically went to a tail so this shouldn't fail")
+                        .ok_or(SimulationError::InconsistentBody(*id))?
                 };
 
                 let mut new_health = self.healths[id.as_usize()];
-                new_health = new_health.saturating_sub(1);
-                if self.get_cell(new_head).is_hazard() {
-                    new_health = new_health.saturating_sub(self.hazard_damage);
-                }
+                let mut starved = DeathCause::Starvation;
+                let grows = if mode.is_constrictor() {
+                    // There is no food in constrictor, every snake is kept at full health and
+                    // grows by one segment each turn, unless its tail is still stacked from the
+                    // start of the game
+                    new_health = 100;
+                    !old_tail_cell.is_triple_stacked_piece()
+                } else {
+                    new_health = new_health.saturating_sub(1);
+                    if self.get_cell(new_head).is_hazard() && new_health > 0 {
+                        starved = DeathCause::HazardDamage;
+                        new_health = new_health.saturating_sub(self.hazard_damage);
+                    }
+                    let ate_food = self.get_cell(new_head).is_food();
+                    if ate_food {
+                        new_health = 100;
+                    }
+                    ate_food
+                };
 
-                let ate_food = self.get_cell(new_head).is_food();
                 let mut new_length = self.lengths[id.as_usize()];
-
-                if ate_food {
-                    new_health = 100;
+                if grows {
                     new_length = new_length.saturating_add(1);
                 };
 
                 if new_health == 0 {
+                    new_heads[id.as_usize()][m.as_index()] =
+                        SinglePlayerMoveResult::Dead(Some(starved));
                     continue;
                 };
 
@@ -163,26 +213,30 @@ impl<T: CellNum, D: Dimensions, const BOARD_SIZE: usize, const MAX_SNAKES: usize
                         new_tail,
                         old_tail,
                         new_health,
-                        ate_food,
+                        grows,
                         new_length,
                     });
             }
         }
 
-        new_heads
+        Ok(new_heads)
     }
 
+    /// The board after the given moves, using the results of [`CellBoard::generate_state`]. What
+    /// happened in the turn is pushed to `events`, if given
     #[instrument(level = "trace", skip_all)]
     pub fn evaluate_moves_with_state<'a>(
         &self,
         moves: impl Iterator<Item = &'a (SnakeId, crate::types::Move)>,
         new_heads: &[[SinglePlayerMoveResult<T>; N_MOVES]; MAX_SNAKES],
+        mut events: Option<&mut Vec<TurnEvent>>,
     ) -> Self
     where
         <Self as types::SnakeIDGettableGame>::SnakeIDType: 'a,
     {
         let moves = moves.collect_vec();
         let mut new = *self;
+        let mut deaths: [Option<DeathCause>; MAX_SNAKES] = [None; MAX_SNAKES];
 
         for (id, m) in moves.iter() {
             let result = new_heads[id.as_usize()][m.as_index()];
@@ -191,13 +245,19 @@ impl<T: CellNum, D: Dimensions, const BOARD_SIZE: usize, const MAX_SNAKES: usize
                 SinglePlayerMoveResult::Alive(AliveMoveResult {
                     id,
                     old_head,
+                    new_head,
                     new_tail,
                     old_tail,
                     new_health,
-                    ate_food,
+                    grows,
                     new_length,
-                    ..
                 }) => {
+                    if let Some(events) = events.as_mut() {
+                        if self.get_cell(new_head).is_food() {
+                            events.push(TurnEvent::AteFood { sid: id });
+                        }
+                    }
+
                     // Step 1a is delayed and done later. This is to not run into issues with
                     // overriding someone elses tail which would break the representation and make it
                     // impossible to correctly remove the tail if the snake dies.
@@ -212,12 +272,12 @@ impl<T: CellNum, D: Dimensions, const BOARD_SIZE: usize, const MAX_SNAKES: usize
                     }
 
                     // Apply new health
-                    new.healths[id.as_usize()] = new_health;
+                    new.set_health(id, new_health);
                     new.lengths[id.as_usize()] = new_length;
 
                     // Step 2: Any Battlesnake that has found food will consume it
                     // Reset health to max if ate food
-                    if ate_food {
+                    if grows {
                         let new_tail_cell = new.get_cell(new_tail);
                         new.set_cell_double_stacked(new_tail, id, new_tail_cell.get_idx());
 
@@ -225,14 +285,16 @@ impl<T: CellNum, D: Dimensions, const BOARD_SIZE: usize, const MAX_SNAKES: usize
                         // happen later
                     }
                 }
-                SinglePlayerMoveResult::Dead => new.kill_and_remove(*id),
+                SinglePlayerMoveResult::Dead(cause) => {
+                    deaths[id.as_usize()] = cause;
+                    new.kill_and_remove(*id)
+                }
             }
         }
 
         // Step 3: Any new food spawning will be placed in empty squares on the board.
         // This step is ignored because we don't want to guess at food spawn locations as they are
         // random
-        let mut to_kill = [false; MAX_SNAKES];
 
         // Step 4c-d: Collision besides head to head
         for (id, m) in moves.iter() {
@@ -242,7 +304,11 @@ impl<T: CellNum, D: Dimensions, const BOARD_SIZE: usize, const MAX_SNAKES: usize
                 let new_head_cell = new.get_cell(new_head);
 
                 if new_head_cell.is_body_segment() || new_head_cell.is_head() {
-                    to_kill[id.as_usize()] = true;
+                    deaths[id.as_usize()] = Some(if new_head_cell.get_snake_id() == Some(id) {
+                        DeathCause::SelfCollision
+                    } else {
+                        DeathCause::BodyCollision
+                    });
                 }
             }
         }
@@ -312,7 +378,13 @@ impl<T: CellNum, D: Dimensions, const BOARD_SIZE: usize, const MAX_SNAKES: usize
                 .iter()
                 .filter(|x| Some(x.id) != winner.map(|x| x.id))
             {
-                to_kill[dead.as_usize()] = true;
+                deaths[dead.as_usize()].get_or_insert(DeathCause::LostHeadToHead);
+            }
+            if let Some(events) = events.as_mut() {
+                events.push(TurnEvent::HeadToHead {
+                    snakes: snake_ids.into_iter().sorted_by_key(|id| id.0).collect(),
+                    winner: winner.map(|x| x.id),
+                });
             }
 
             if winner.is_none() && !head_to_head_collision_on_another_snake {
@@ -332,7 +404,7 @@ impl<T: CellNum, D: Dimensions, const BOARD_SIZE: usize, const MAX_SNAKES: usize
                 ..
             }) = result
             {
-                if to_kill[id.as_usize()] {
+                if deaths[id.as_usize()].is_some() {
                     // Kill any player killed via collisions
                     new.kill_and_remove(id);
                 } else {
@@ -350,6 +422,12 @@ impl<T: CellNum, D: Dimensions, const BOARD_SIZE: usize, const MAX_SNAKES: usize
             }
         }
 
+        if let Some(events) = events {
+            events.extend(moves.iter().filter_map(|(sid, _)| {
+                deaths[sid.as_usize()].map(|cause| TurnEvent::Died { sid: *sid, cause })
+            }));
+        }
+
         new
     }
 }
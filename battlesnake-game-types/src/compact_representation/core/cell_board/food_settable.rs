@@ -0,0 +1,20 @@
+use crate::{
+    compact_representation::{core::dimensions::Dimensions, CellNum},
+    types::FoodSettableGame,
+};
+
+use super::CellBoard;
+
+impl<T: CellNum, D: Dimensions, const BOARD_SIZE: usize, const MAX_SNAKES: usize> FoodSettableGame
+    for CellBoard<T, D, BOARD_SIZE, MAX_SNAKES>
+{
+    fn set_food(&mut self, pos: Self::NativePositionType) {
+        self.set_cell_food(pos);
+    }
+
+    fn clear_food(&mut self, pos: Self::NativePositionType) {
+        if self.cell_is_food(pos) {
+            self.cell_remove(pos);
+        }
+    }
+}
@@ -9,10 +9,10 @@ impl<T: CellNum, D: Dimensions, const BOARD_SIZE: usize, const MAX_SNAKES: usize
     for CellBoard<T, D, BOARD_SIZE, MAX_SNAKES>
 {
     fn set_hazard(&mut self, pos: Self::NativePositionType) {
-        self.cells[pos.0.as_usize()].set_hazard();
+        self.set_cell_hazard(pos, true);
     }
 
     fn clear_hazard(&mut self, pos: Self::NativePositionType) {
-        self.cells[pos.0.as_usize()].clear_hazard();
+        self.set_cell_hazard(pos, false);
     }
 }
@@ -56,8 +56,6 @@ impl<T: CellNum, D: Dimensions, const BOARD_SIZE: usize, const MAX_SNAKES: usize
             }
             writeln!(f)?;
         }
-        let hash_repr = self.pack_as_hash();
-        writeln!(f, "{}", serde_json::to_string(&hash_repr).unwrap())?;
         Ok(())
     }
 }
@@ -1,5 +1,5 @@
 use std::collections::HashMap;
-use std::error::Error;
+use std::convert::TryFrom;
 use std::slice::Iter;
 
 use itertools::Itertools;
@@ -9,8 +9,8 @@ use crate::types::EmptyCellGettableGame;
 use crate::types::SnakeIDMap;
 use crate::types::SnakeId;
 use crate::types::StandardFoodPlaceableGame;
-use crate::wire_representation::Game;
 use crate::wire_representation::Position;
+use crate::wire_representation::{BattleSnake, Game};
 
 use super::dimensions::Dimensions;
 use super::Cell;
@@ -18,8 +18,15 @@ use super::CellIndex;
 use super::CellNum as CN;
 use super::{DOUBLE_STACK, TRIPLE_STACK};
 
+/// Food spawn chance of games without settings, in percent
+pub(crate) const DEFAULT_FOOD_SPAWN_CHANCE: u8 = 15;
+/// Minimum food of games without settings
+pub(crate) const DEFAULT_MINIMUM_FOOD: u8 = 1;
+
+mod error;
 mod eval;
 mod food_gettable;
+mod food_settable;
 mod hazard_queryable;
 mod hazard_settable;
 mod head_gettable;
@@ -27,13 +34,18 @@ mod health_gettable;
 mod length_gettable;
 mod neck_queryable;
 mod position_gettable;
+mod serialize;
 mod size_determinable;
 mod snake_body_gettable;
 mod snake_id_gettable;
+mod to_wire;
 mod victor_determinable;
 mod you_determinable;
+mod zobrist;
 
+pub use error::{ConversionError, SimulationError};
 pub use eval::EvaluateMode;
+pub use serialize::{DecodeError, BOARD_FORMAT_VERSION};
 
 /// A compact board representation that is significantly faster for simulation than
 /// `battlesnake_game_types::wire_representation::Game`.
@@ -45,25 +57,56 @@ pub struct CellBoard<
     const MAX_SNAKES: usize,
 > {
     hazard_damage: u8,
+    /// Played with the constrictor rules, see [`EvaluateMode::Constrictor`]
+    constrictor: bool,
+    /// Percent chance of a food spawning in a turn, see [`StandardFoodPlaceableGame`]
+    food_spawn_chance: u8,
+    /// Food is spawned until the board has at least this many
+    minimum_food: u8,
     cells: [Cell<T>; BOARD_SIZE],
     healths: [u8; MAX_SNAKES],
     heads: [CellIndex<T>; MAX_SNAKES],
     lengths: [u16; MAX_SNAKES],
     dimensions: DimensionsType,
+    /// Zobrist key of the cells and health buckets, kept up to date by every change to them
+    zobrist: u64,
 }
 
-#[allow(dead_code)]
 fn get_snake_id(
-    snake: &crate::wire_representation::BattleSnake,
+    snake: &BattleSnake,
     snake_ids: &SnakeIDMap,
-) -> Option<SnakeId> {
+) -> Result<Option<SnakeId>, ConversionError> {
     if snake.health == 0 {
-        None
+        Ok(None)
     } else {
-        Some(*snake_ids.get(&snake.id).unwrap())
+        match snake_ids.get(&snake.id) {
+            Some(sid) => Ok(Some(*sid)),
+            None => Err(ConversionError::UnknownSnakeId(snake.id.clone())),
+        }
     }
 }
 
+/// Whether the body of an alive snake fits in the cells: it starts at the head, stays within the
+/// cells of the board and only stacks two segments on its tail, or all of them on its head
+fn body_is_consistent(snake: &BattleSnake, width: u8, height: u8) -> bool {
+    let (Some(first), Some(tail)) = (snake.body.front(), snake.body.back()) else {
+        return false;
+    };
+    let cell_count = width as i64 * height as i64;
+    let counts = snake.body.iter().counts();
+    *first == snake.head
+        && snake.body.iter().all(|pos| {
+            let index = pos.y as i64 * width as i64 + pos.x as i64;
+            index >= 0 && index < cell_count
+        })
+        && counts.iter().all(|(pos, count)| match *count {
+            1 => true,
+            DOUBLE_STACK => *pos == tail && *pos != first,
+            // only a snake that hasn't moved yet stacks more
+            _ => counts.len() == 1,
+        })
+}
+
 impl<T: CN, D: Dimensions, const BOARD_SIZE: usize, const MAX_SNAKES: usize>
     CellBoard<T, D, BOARD_SIZE, MAX_SNAKES>
 {
@@ -71,6 +114,11 @@ impl<T: CN, D: Dimensions, const BOARD_SIZE: usize, const MAX_SNAKES: usize>
         self.healths.iter()
     }
 
+    /// true if this board is played with the constrictor rules
+    pub fn is_constrictor(&self) -> bool {
+        self.constrictor
+    }
+
     /// Asserts that all tails eventually loop back to a head and panics if the board is inconsistent
     pub fn assert_consistency(&self) -> bool {
         for i in 0..MAX_SNAKES {
@@ -103,11 +151,18 @@ impl<T: CN, D: Dimensions, const BOARD_SIZE: usize, const MAX_SNAKES: usize>
         true
     }
 
-    /// packs this as a hash. Doing this because getting serde to work
-    /// with const generics is hard
+    /// packs this as a hash, the legacy packed form kept so old debug output can still be
+    /// loaded with [`CellBoard::from_packed_hash`]. New boards should be stored with `to_bytes`
+    /// or serde JSON instead
     pub fn pack_as_hash(&self) -> HashMap<String, Vec<u32>> {
         let mut hash = HashMap::new();
         hash.insert("hazard_damage".to_string(), vec![self.hazard_damage as u32]);
+        hash.insert("constrictor".to_string(), vec![self.constrictor as u32]);
+        hash.insert(
+            "food_spawn_chance".to_string(),
+            vec![self.food_spawn_chance as u32],
+        );
+        hash.insert("minimum_food".to_string(), vec![self.minimum_food as u32]);
         hash.insert(
             "actual_width".to_string(),
             vec![self.get_actual_width() as u32],
@@ -131,49 +186,93 @@ impl<T: CN, D: Dimensions, const BOARD_SIZE: usize, const MAX_SNAKES: usize>
         hash
     }
 
-    /// unpacks a packed hash repr back in to a CellBoard
-    pub fn from_packed_hash(hash: &HashMap<String, Vec<u32>>) -> Self {
-        let hazard_damage = hash.get("hazard_damage").unwrap()[0] as u8;
-        let actual_width = hash.get("actual_width").unwrap()[0] as u8;
-        let actual_height = hash
-            .get("actual_height")
-            .map(|h| h[0] as u8)
-            .unwrap_or(actual_width);
+    /// unpacks a packed hash repr back in to a CellBoard. Fails on fields that are missing or out
+    /// of range and on boards that don't fit this board type, but keeps inconsistent snakes so
+    /// crashed boards can still be loaded
+    pub fn from_packed_hash(hash: &HashMap<String, Vec<u32>>) -> Result<Self, DecodeError> {
+        let list = |name: &'static str| hash.get(name).ok_or(DecodeError::MalformedField(name));
+        let byte = |name: &'static str| -> Result<Option<u8>, DecodeError> {
+            hash.get(name)
+                .map(|values| {
+                    values
+                        .first()
+                        .and_then(|v| u8::try_from(*v).ok())
+                        .ok_or(DecodeError::MalformedField(name))
+                })
+                .transpose()
+        };
+
+        let hazard_damage =
+            byte("hazard_damage")?.ok_or(DecodeError::MalformedField("hazard_damage"))?;
+        let constrictor = byte("constrictor")?.is_some_and(|c| c != 0);
+        let food_spawn_chance = byte("food_spawn_chance")?.unwrap_or(DEFAULT_FOOD_SPAWN_CHANCE);
+        let minimum_food = byte("minimum_food")?.unwrap_or(DEFAULT_MINIMUM_FOOD);
+        let actual_width =
+            byte("actual_width")?.ok_or(DecodeError::MalformedField("actual_width"))?;
+        let actual_height = byte("actual_height")?.unwrap_or(actual_width);
+        let dimensions = D::try_from_dimensions(actual_width, actual_height)
+            .filter(|_| actual_width as usize * actual_height as usize <= BOARD_SIZE)
+            .ok_or(DecodeError::DoesNotFit {
+                width: actual_width,
+                height: actual_height,
+            })?;
 
         let mut healths = [0; MAX_SNAKES];
-        let healths_iter = hash.get("healths").unwrap().iter().map(|x| *x as u8);
-        for (idx, health) in healths_iter.enumerate() {
-            healths[idx] = health;
+        let packed_healths = list("healths")?;
+        if packed_healths.len() > MAX_SNAKES {
+            return Err(DecodeError::TooManySnakes(packed_healths.len()));
+        }
+        for (health, packed) in healths.iter_mut().zip(packed_healths) {
+            *health = u8::try_from(*packed).map_err(|_| DecodeError::MalformedField("healths"))?;
         }
 
         let mut lengths = [0; MAX_SNAKES];
-        let lengths_iter = hash.get("lengths").unwrap().iter().map(|x| *x as u16);
-        for (idx, length) in lengths_iter.enumerate() {
-            lengths[idx] = length;
+        let packed_lengths = list("lengths")?;
+        if packed_lengths.len() > MAX_SNAKES {
+            return Err(DecodeError::TooManySnakes(packed_lengths.len()));
+        }
+        for (length, packed) in lengths.iter_mut().zip(packed_lengths) {
+            *length = u16::try_from(*packed).map_err(|_| DecodeError::MalformedField("lengths"))?;
         }
 
         let mut heads = [CellIndex::<T>::from_usize(0); MAX_SNAKES];
-        let heads_iter = hash.get("heads").unwrap().iter().map(|x| *x as usize);
-        for (idx, head) in heads_iter.enumerate() {
-            heads[idx] = CellIndex::<T>::from_usize(head);
+        let packed_heads = list("heads")?;
+        if packed_heads.len() > MAX_SNAKES {
+            return Err(DecodeError::TooManySnakes(packed_heads.len()));
+        }
+        for (sid, (head, packed)) in heads.iter_mut().zip(packed_heads).enumerate() {
+            if *packed as usize >= BOARD_SIZE {
+                return Err(DecodeError::InconsistentSnake(SnakeId(sid as u8)));
+            }
+            *head = CellIndex::<T>::from_usize(*packed as usize);
         }
 
         let mut cells = [Cell::<T>::empty(); BOARD_SIZE];
-        let cells_iter = hash.get("cells").unwrap().iter().cloned();
-        for (idx, cell) in cells_iter.enumerate() {
-            cells[idx] = Cell::<T>::from_u32(cell);
+        let packed_cells = list("cells")?;
+        if packed_cells.len() > BOARD_SIZE {
+            return Err(DecodeError::WrongCellCount {
+                expected: BOARD_SIZE,
+                actual: packed_cells.len(),
+            });
+        }
+        for (cell, packed) in cells.iter_mut().zip(packed_cells) {
+            *cell = Cell::<T>::from_u32(*packed);
         }
 
-        let dimensions = D::from_dimensions(actual_width, actual_height);
-
-        CellBoard {
+        let mut board = CellBoard {
             hazard_damage,
+            constrictor,
+            food_spawn_chance,
+            minimum_food,
             cells,
             healths,
             heads,
             lengths,
             dimensions,
-        }
+            zobrist: 0,
+        };
+        board.zobrist = board.compute_zobrist();
+        Ok(board)
     }
 
     pub fn as_wrapped_cell_index(&self, mut new_head_position: Position) -> CellIndex<T> {
@@ -224,7 +323,7 @@ impl<T: CN, D: Dimensions, const BOARD_SIZE: usize, const MAX_SNAKES: usize>
     }
 
     fn kill(&mut self, sid: SnakeId) {
-        self.healths[sid.0 as usize] = 0;
+        self.set_health(sid, 0);
         self.heads[sid.0 as usize] = CellIndex::from_i32(0);
         self.lengths[sid.0 as usize] = 0;
     }
@@ -245,25 +344,34 @@ impl<T: CN, D: Dimensions, const BOARD_SIZE: usize, const MAX_SNAKES: usize>
     }
 
     /// Builds a cellboard from a given game, will return an error if the game doesn't match
-    /// the provided BOARD_SIZE or MAX_SNAKES. You are encouraged to use `CellBoard4Snakes11x11`
+    /// the provided BOARD_SIZE or MAX_SNAKES, one of its snakes isn't in `snake_ids` or has a
+    /// body that can't be represented. You are encouraged to use `CellBoard4Snakes11x11`
     /// for the common game layout
-    pub fn convert_from_game(game: Game, snake_ids: &SnakeIDMap) -> Result<Self, Box<dyn Error>> {
-        if game.board.width * game.board.height > BOARD_SIZE as u32 {
-            return Err("game size doesn't fit in the given board size".into());
+    pub fn convert_from_game(game: Game, snake_ids: &SnakeIDMap) -> Result<Self, ConversionError> {
+        let too_large = || ConversionError::BoardTooLarge {
+            width: game.board.width,
+            height: game.board.height,
+        };
+        let (Ok(width), Ok(height)) = (
+            u8::try_from(game.board.width),
+            u8::try_from(game.board.height),
+        ) else {
+            return Err(too_large());
+        };
+        if width as usize * height as usize > BOARD_SIZE {
+            return Err(too_large());
         }
+        let dimensions = D::try_from_dimensions(width, height).ok_or_else(too_large)?;
 
         if game.board.snakes.len() > MAX_SNAKES {
-            return Err("too many snakes".into());
+            return Err(ConversionError::TooManySnakes(game.board.snakes.len()));
         }
 
         for snake in &game.board.snakes {
-            let counts = &snake.body.iter().counts();
-            if counts.values().any(|v| *v == TRIPLE_STACK) && counts.len() != 1 {
-                return Err(format!("snake {} has a bad body stack (3 segs on same square and more than one unique position)", snake.id).into());
+            if snake.health > 0 && !body_is_consistent(snake, width, height) {
+                return Err(ConversionError::InconsistentBody(snake.id.clone()));
             }
         }
-        let width = game.board.width as u8;
-        let height = game.board.height as u8;
 
         let mut cells = [Cell::empty(); BOARD_SIZE];
         let mut healths: [u8; MAX_SNAKES] = [0; MAX_SNAKES];
@@ -271,10 +379,13 @@ impl<T: CN, D: Dimensions, const BOARD_SIZE: usize, const MAX_SNAKES: usize>
         let mut lengths: [u16; MAX_SNAKES] = [0; MAX_SNAKES];
 
         for snake in &game.board.snakes {
-            let snake_id = match get_snake_id(snake, snake_ids) {
+            let snake_id = match get_snake_id(snake, snake_ids)? {
                 Some(value) => value,
                 None => continue,
             };
+            if snake_id.as_usize() >= MAX_SNAKES {
+                return Err(ConversionError::TooManySnakes(snake_id.as_usize() + 1));
+            }
 
             healths[snake_id.0 as usize] = snake.health as u8;
             if snake.health == 0 {
@@ -285,20 +396,14 @@ impl<T: CN, D: Dimensions, const BOARD_SIZE: usize, const MAX_SNAKES: usize>
             let counts = &snake.body.iter().counts();
 
             let head_idx = CellIndex::new(snake.head, width);
+            heads[snake_id.0 as usize] = head_idx;
             let mut next_index = head_idx;
-            for (idx, pos) in snake.body.iter().unique().enumerate() {
-                let cell_idx = CellIndex::new(*pos, width);
+            for pos in snake.body.iter().unique() {
+                let cell_idx: CellIndex<T> = CellIndex::new(*pos, width);
                 let count = counts.get(pos).unwrap();
-                if idx == 0 {
-                    assert!(cell_idx == head_idx);
-                    heads[snake_id.0 as usize] = head_idx;
-                }
                 cells[cell_idx.0.as_usize()] = if *count == TRIPLE_STACK {
                     Cell::make_triple_stacked_piece(snake_id)
                 } else if *pos == snake.head {
-                    // head can never be doubled, so let's assert it here, the cost of
-                    // one comparison is worth the saftey imo
-                    assert!(*count != DOUBLE_STACK);
                     let tail_index = CellIndex::new(*snake.body.back().unwrap(), width);
                     Cell::make_snake_head(snake_id, tail_index)
                 } else if *count == DOUBLE_STACK {
@@ -327,22 +432,27 @@ impl<T: CN, D: Dimensions, const BOARD_SIZE: usize, const MAX_SNAKES: usize>
             }
         }
 
-        let dimensions = D::from_dimensions(width, height);
+        let settings = game.game.ruleset.settings.as_ref();
 
-        Ok(CellBoard {
+        let mut board = CellBoard {
             cells,
             heads,
             healths,
             lengths,
             dimensions,
-            hazard_damage: game
-                .game
-                .ruleset
-                .settings
-                .as_ref()
-                .map(|s| s.hazard_damage_per_turn)
-                .unwrap_or(15) as u8,
-        })
+            constrictor: game.is_constrictor(),
+            // Settings out of range are clamped instead of wrapping around
+            food_spawn_chance: settings
+                .map(|s| s.food_spawn_chance.clamp(0, 100) as u8)
+                .unwrap_or(DEFAULT_FOOD_SPAWN_CHANCE),
+            minimum_food: settings
+                .map(|s| s.minimum_food.clamp(0, u8::MAX as i32) as u8)
+                .unwrap_or(DEFAULT_MINIMUM_FOOD),
+            hazard_damage: settings.map(|s| s.hazard_damage_per_turn).unwrap_or(15) as u8,
+            zobrist: 0,
+        };
+        board.zobrist = board.compute_zobrist();
+        Ok(board)
     }
     fn get_cell(&self, cell_index: CellIndex<T>) -> Cell<T> {
         self.cells[cell_index.0.as_usize()]
@@ -360,11 +470,45 @@ impl<T: CN, D: Dimensions, const BOARD_SIZE: usize, const MAX_SNAKES: usize>
     pub fn get_length(&self, snake_id: SnakeId) -> u16 {
         self.lengths[snake_id.0 as usize]
     }
+    /// Replace the cell at the given index, every change to the cells goes through here to
+    /// keep the zobrist key up to date
+    fn set_cell(&mut self, cell_index: CellIndex<T>, cell: Cell<T>) {
+        let index = cell_index.0.as_usize();
+        self.zobrist ^=
+            zobrist::cell_key(index, self.cells[index]) ^ zobrist::cell_key(index, cell);
+        self.cells[index] = cell;
+    }
+
+    /// Set the health of the given snake, keeping the zobrist key up to date
+    pub fn set_health(&mut self, sid: SnakeId, health: u8) {
+        let old_health = self.healths[sid.as_usize()];
+        self.zobrist ^= zobrist::health_key(sid, old_health) ^ zobrist::health_key(sid, health);
+        self.healths[sid.as_usize()] = health;
+    }
+
     /// Mutibaly call remove on the specified cell
     pub fn cell_remove(&mut self, cell_index: CellIndex<T>) {
         let mut old_cell = self.get_cell(cell_index);
         old_cell.remove();
-        self.cells[cell_index.0.as_usize()] = old_cell;
+        self.set_cell(cell_index, old_cell);
+    }
+
+    /// Place a food on the specified cell
+    pub fn set_cell_food(&mut self, cell_index: CellIndex<T>) {
+        let mut old_cell = self.get_cell(cell_index);
+        old_cell.set_food();
+        self.set_cell(cell_index, old_cell);
+    }
+
+    /// Set or clear the hazard of the specified cell
+    pub fn set_cell_hazard(&mut self, cell_index: CellIndex<T>, hazard: bool) {
+        let mut old_cell = self.get_cell(cell_index);
+        if hazard {
+            old_cell.set_hazard();
+        } else {
+            old_cell.clear_hazard();
+        }
+        self.set_cell(cell_index, old_cell);
     }
     /// Set the given index to a Snake Body Piece
     pub fn set_cell_body_piece(
@@ -375,7 +519,7 @@ impl<T: CN, D: Dimensions, const BOARD_SIZE: usize, const MAX_SNAKES: usize>
     ) {
         let mut old_cell = self.get_cell(cell_index);
         old_cell.set_body_piece(sid, next_id);
-        self.cells[cell_index.0.as_usize()] = old_cell;
+        self.set_cell(cell_index, old_cell);
     }
 
     /// Set the given index as a double stacked snake
@@ -387,7 +531,7 @@ impl<T: CN, D: Dimensions, const BOARD_SIZE: usize, const MAX_SNAKES: usize>
     ) {
         let mut old_cell = self.get_cell(cell_index);
         old_cell.set_double_stacked(sid, next_id);
-        self.cells[cell_index.0.as_usize()] = old_cell;
+        self.set_cell(cell_index, old_cell);
     }
 
     /// Set the given index as a snake head
@@ -399,7 +543,7 @@ impl<T: CN, D: Dimensions, const BOARD_SIZE: usize, const MAX_SNAKES: usize>
     ) {
         let mut old_cell = self.get_cell(old_head_index);
         old_cell.set_head(sid, next_id);
-        self.cells[old_head_index.0.as_usize()] = old_cell;
+        self.set_cell(old_head_index, old_cell);
     }
 
     /// gets the snake ID at a given index, returns None if the provided index is not a snake cell
@@ -469,16 +613,17 @@ impl<T: CN, D: Dimensions, const BOARD_SIZE: usize, const MAX_SNAKES: usize>
     StandardFoodPlaceableGame for CellBoard<T, D, BOARD_SIZE, MAX_SNAKES>
 {
     fn place_food(&mut self, rng: &mut impl rand::Rng) {
-        // TODO: Get these constants from the game
-        let min_food = 1;
-        let food_spawn_chance = 0.15;
-
-        // This is an optimization when min_food is 1. We know we don't need to spawn food if there if any of the board
-        // so we can short circuit on the first food we find
-        let food_to_add = if !self.cells.iter().any(|c| c.is_food()) {
-            min_food
+        // Snakes don't eat in constrictor, so no food spawns
+        if self.constrictor {
+            return;
+        }
+
+        let min_food = self.minimum_food as usize;
+        let food_count = self.cells.iter().filter(|c| c.is_food()).count();
+        let food_to_add = if food_count < min_food {
+            min_food - food_count
         } else {
-            usize::from(rng.random_bool(food_spawn_chance))
+            usize::from(rng.random_range(0..100) < self.food_spawn_chance)
         };
 
         if food_to_add == 0 {
@@ -488,7 +633,7 @@ impl<T: CN, D: Dimensions, const BOARD_SIZE: usize, const MAX_SNAKES: usize>
         let empty = self.get_empty_cells();
         let random = empty.choose_multiple(rng, food_to_add);
         for pos in random {
-            self.cells[pos.0.as_usize()].set_food();
+            self.set_cell_food(pos);
         }
     }
 }
@@ -502,7 +647,7 @@ mod tests {
     fn test_assert_consistent() {
         let inconsistent_fixture = include_str!("../../../../fixtures/inconsistent_fixture.json");
         let hm = serde_json::from_str(inconsistent_fixture).unwrap();
-        let game = CellBoard::<u8, Square, { 11 * 11 }, 4>::from_packed_hash(&hm);
+        let game = CellBoard::<u8, Square, { 11 * 11 }, 4>::from_packed_hash(&hm).unwrap();
         assert!(!game.assert_consistency());
     }
 }
@@ -0,0 +1,413 @@
+use std::error::Error;
+use std::fmt::{self, Display};
+
+use serde::{
+    de::{self, SeqAccess, Visitor},
+    Deserialize, Deserializer, Serialize, Serializer,
+};
+
+use crate::{
+    compact_representation::{
+        core::{dimensions::Dimensions, Cell, CellIndex},
+        CellNum,
+    },
+    types::SnakeId,
+};
+
+use super::CellBoard;
+
+/// The version of the serialized form of compact boards, bumped whenever the layout changes
+pub const BOARD_FORMAT_VERSION: u8 = 1;
+
+const WRAPPED_FLAG: u8 = 0x01;
+const CONSTRICTOR_FLAG: u8 = 0x02;
+
+/// Reasons a serialized board can't be loaded
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DecodeError {
+    /// The board was written by a version of the format we can't read
+    UnsupportedVersion(u8),
+    /// The binary form ended before the board was complete
+    Truncated,
+    /// The binary form has bytes left over after the board
+    TrailingBytes,
+    /// A wrapped board was loaded as a standard one or the other way around
+    WrongRuleset {
+        /// whether the serialized board is wrapped
+        wrapped: bool,
+    },
+    /// The board doesn't fit the dimensions or the board size of the type it's loaded into
+    DoesNotFit {
+        /// width of the serialized board
+        width: u8,
+        /// height of the serialized board
+        height: u8,
+    },
+    /// The board has more snakes than the type it's loaded into
+    TooManySnakes(usize),
+    /// The healths, lengths and heads don't describe the same number of snakes
+    MismatchedSnakes,
+    /// The number of cells doesn't match the width and height
+    WrongCellCount {
+        /// width times height
+        expected: usize,
+        /// cells in the serialized board
+        actual: usize,
+    },
+    /// The cell at this index isn't a valid cell
+    InvalidCell(usize),
+    /// The body of this alive snake doesn't lead from its tail to its head
+    InconsistentSnake(SnakeId),
+    /// This field of a packed hash is missing or out of range
+    MalformedField(&'static str),
+}
+
+impl Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DecodeError::UnsupportedVersion(version) => {
+                write!(f, "unsupported board format version {version}")
+            }
+            DecodeError::Truncated => write!(f, "board ended early"),
+            DecodeError::TrailingBytes => write!(f, "trailing bytes after the board"),
+            DecodeError::WrongRuleset { wrapped: true } => {
+                write!(f, "expected a standard board, got a wrapped one")
+            }
+            DecodeError::WrongRuleset { wrapped: false } => {
+                write!(f, "expected a wrapped board, got a standard one")
+            }
+            DecodeError::DoesNotFit { width, height } => {
+                write!(f, "a {width}x{height} board doesn't fit this board type")
+            }
+            DecodeError::TooManySnakes(snakes) => {
+                write!(f, "{snakes} snakes don't fit this board type")
+            }
+            DecodeError::MismatchedSnakes => {
+                write!(f, "healths, lengths and heads have different lengths")
+            }
+            DecodeError::WrongCellCount { expected, actual } => {
+                write!(f, "expected {expected} cells, got {actual}")
+            }
+            DecodeError::InvalidCell(index) => write!(f, "invalid cell at index {index}"),
+            DecodeError::InconsistentSnake(sid) => {
+                write!(f, "snake {} is inconsistent", sid.as_usize())
+            }
+            DecodeError::MalformedField(name) => {
+                write!(f, "{name} is missing or out of range")
+            }
+        }
+    }
+}
+
+impl Error for DecodeError {}
+
+/// The serialized form of a board. Cells are packed like [`Cell::pack_as_u32`] and only the
+/// `width * height` cells of the actual board are stored
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct BoardRepr {
+    version: u8,
+    wrapped: bool,
+    width: u8,
+    height: u8,
+    hazard_damage: u8,
+    constrictor: bool,
+    food_spawn_chance: u8,
+    minimum_food: u8,
+    healths: Vec<u8>,
+    lengths: Vec<u16>,
+    heads: Vec<u16>,
+    cells: Vec<u32>,
+}
+
+impl BoardRepr {
+    /// The binary form: a header of single bytes, then every snake and every cell, little endian
+    fn to_bytes(&self) -> Vec<u8> {
+        let mut flags = 0;
+        if self.wrapped {
+            flags |= WRAPPED_FLAG;
+        }
+        if self.constrictor {
+            flags |= CONSTRICTOR_FLAG;
+        }
+        let mut bytes = vec![
+            self.version,
+            flags,
+            self.width,
+            self.height,
+            self.hazard_damage,
+            self.food_spawn_chance,
+            self.minimum_food,
+            self.healths.len() as u8,
+        ];
+        for ((health, length), head) in self.healths.iter().zip(&self.lengths).zip(&self.heads) {
+            bytes.push(*health);
+            bytes.extend_from_slice(&length.to_le_bytes());
+            bytes.extend_from_slice(&head.to_le_bytes());
+        }
+        for cell in &self.cells {
+            bytes.extend_from_slice(&cell.to_le_bytes());
+        }
+        bytes
+    }
+
+    fn from_bytes(bytes: &[u8]) -> Result<Self, DecodeError> {
+        let mut reader = ByteReader(bytes);
+        let version = reader.u8()?;
+        if version != BOARD_FORMAT_VERSION {
+            return Err(DecodeError::UnsupportedVersion(version));
+        }
+        let flags = reader.u8()?;
+        let width = reader.u8()?;
+        let height = reader.u8()?;
+        let hazard_damage = reader.u8()?;
+        let food_spawn_chance = reader.u8()?;
+        let minimum_food = reader.u8()?;
+        let snakes = reader.u8()? as usize;
+        let mut healths = Vec::with_capacity(snakes);
+        let mut lengths = Vec::with_capacity(snakes);
+        let mut heads = Vec::with_capacity(snakes);
+        for _ in 0..snakes {
+            healths.push(reader.u8()?);
+            lengths.push(reader.u16()?);
+            heads.push(reader.u16()?);
+        }
+        let cells = (0..width as usize * height as usize)
+            .map(|_| reader.u32())
+            .collect::<Result<_, _>>()?;
+        if !reader.0.is_empty() {
+            return Err(DecodeError::TrailingBytes);
+        }
+        Ok(BoardRepr {
+            version,
+            wrapped: flags & WRAPPED_FLAG != 0,
+            width,
+            height,
+            hazard_damage,
+            constrictor: flags & CONSTRICTOR_FLAG != 0,
+            food_spawn_chance,
+            minimum_food,
+            healths,
+            lengths,
+            heads,
+            cells,
+        })
+    }
+}
+
+struct ByteReader<'a>(&'a [u8]);
+
+impl ByteReader<'_> {
+    fn take<const N: usize>(&mut self) -> Result<[u8; N], DecodeError> {
+        let (head, rest) = self
+            .0
+            .split_first_chunk::<N>()
+            .ok_or(DecodeError::Truncated)?;
+        self.0 = rest;
+        Ok(*head)
+    }
+
+    fn u8(&mut self) -> Result<u8, DecodeError> {
+        self.take::<1>().map(|[b]| b)
+    }
+
+    fn u16(&mut self) -> Result<u16, DecodeError> {
+        self.take().map(u16::from_le_bytes)
+    }
+
+    fn u32(&mut self) -> Result<u32, DecodeError> {
+        self.take().map(u32::from_le_bytes)
+    }
+}
+
+/// Reads the binary form from formats that aren't human readable, some of them hand out byte
+/// strings as sequences
+struct BytesVisitor;
+
+impl<'de> Visitor<'de> for BytesVisitor {
+    type Value = Vec<u8>;
+
+    fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "a serialized board")
+    }
+
+    fn visit_bytes<E: de::Error>(self, v: &[u8]) -> Result<Self::Value, E> {
+        Ok(v.to_vec())
+    }
+
+    fn visit_byte_buf<E: de::Error>(self, v: Vec<u8>) -> Result<Self::Value, E> {
+        Ok(v)
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+        let mut bytes = Vec::with_capacity(seq.size_hint().unwrap_or(0));
+        while let Some(byte) = seq.next_element()? {
+            bytes.push(byte);
+        }
+        Ok(bytes)
+    }
+}
+
+impl<T: CellNum, D: Dimensions, const BOARD_SIZE: usize, const MAX_SNAKES: usize>
+    CellBoard<T, D, BOARD_SIZE, MAX_SNAKES>
+{
+    fn repr(&self, wrapped: bool) -> BoardRepr {
+        let cells = self.get_actual_width() as usize * self.get_actual_height() as usize;
+        BoardRepr {
+            version: BOARD_FORMAT_VERSION,
+            wrapped,
+            width: self.get_actual_width(),
+            height: self.get_actual_height(),
+            hazard_damage: self.hazard_damage,
+            constrictor: self.constrictor,
+            food_spawn_chance: self.food_spawn_chance,
+            minimum_food: self.minimum_food,
+            healths: self.healths.to_vec(),
+            lengths: self.lengths.to_vec(),
+            heads: self.heads.iter().map(|h| h.as_usize() as u16).collect(),
+            cells: self.cells[..cells].iter().map(Cell::pack_as_u32).collect(),
+        }
+    }
+
+    fn from_repr(repr: BoardRepr, wrapped: bool) -> Result<Self, DecodeError> {
+        if repr.version != BOARD_FORMAT_VERSION {
+            return Err(DecodeError::UnsupportedVersion(repr.version));
+        }
+        if repr.wrapped != wrapped {
+            return Err(DecodeError::WrongRuleset {
+                wrapped: repr.wrapped,
+            });
+        }
+        let size = repr.width as usize * repr.height as usize;
+        let dimensions = D::try_from_dimensions(repr.width, repr.height)
+            .filter(|_| size <= BOARD_SIZE)
+            .ok_or(DecodeError::DoesNotFit {
+                width: repr.width,
+                height: repr.height,
+            })?;
+        let snakes = repr.healths.len();
+        if repr.lengths.len() != snakes || repr.heads.len() != snakes {
+            return Err(DecodeError::MismatchedSnakes);
+        }
+        if snakes > MAX_SNAKES {
+            return Err(DecodeError::TooManySnakes(snakes));
+        }
+        if repr.cells.len() != size {
+            return Err(DecodeError::WrongCellCount {
+                expected: size,
+                actual: repr.cells.len(),
+            });
+        }
+
+        let mut cells = [Cell::empty(); BOARD_SIZE];
+        for (index, (cell, packed)) in cells.iter_mut().zip(repr.cells).enumerate() {
+            *cell = Cell::from_u32(packed);
+            if !cell.is_valid(size, MAX_SNAKES) {
+                return Err(DecodeError::InvalidCell(index));
+            }
+        }
+        let mut healths = [0; MAX_SNAKES];
+        healths[..snakes].copy_from_slice(&repr.healths);
+        let mut lengths = [0; MAX_SNAKES];
+        lengths[..snakes].copy_from_slice(&repr.lengths);
+        let mut heads = [CellIndex::from_usize(0); MAX_SNAKES];
+        for (sid, (head, packed)) in heads.iter_mut().zip(repr.heads).enumerate() {
+            if packed as usize >= size.max(1) {
+                return Err(DecodeError::InconsistentSnake(SnakeId(sid as u8)));
+            }
+            *head = CellIndex::from_usize(packed as usize);
+        }
+
+        let mut board = CellBoard {
+            hazard_damage: repr.hazard_damage,
+            constrictor: repr.constrictor,
+            food_spawn_chance: repr.food_spawn_chance,
+            minimum_food: repr.minimum_food,
+            cells,
+            healths,
+            heads,
+            lengths,
+            dimensions,
+            zobrist: 0,
+        };
+        if let Some(sid) = (0..MAX_SNAKES)
+            .map(|sid| SnakeId(sid as u8))
+            .find(|sid| !board.snake_is_consistent(*sid))
+        {
+            return Err(DecodeError::InconsistentSnake(sid));
+        }
+        board.zobrist = board.compute_zobrist();
+        Ok(board)
+    }
+
+    /// Whether the head of `sid` is its own and its body leads from the tail to the head, dead
+    /// snakes are always consistent
+    fn snake_is_consistent(&self, sid: SnakeId) -> bool {
+        if self.healths[sid.as_usize()] == 0 {
+            return true;
+        }
+        let head = self.heads[sid.as_usize()];
+        let head_cell = self.get_cell(head);
+        if !head_cell.is_head() || head_cell.get_snake_id() != Some(sid) {
+            return false;
+        }
+        let mut index = match head_cell.get_tail_position(head) {
+            Some(tail) => tail,
+            None => return false,
+        };
+        // every step visits another cell, so a body that loops can't take more than this
+        for _ in 0..BOARD_SIZE {
+            if index == head {
+                return true;
+            }
+            let cell = self.get_cell(index);
+            if cell.get_snake_id() != Some(sid) {
+                return false;
+            }
+            index = match cell.get_next_index() {
+                Some(next) => next,
+                None => return false,
+            };
+        }
+        false
+    }
+
+    /// The compact binary form of this board, see [`CellBoard::decode`]
+    pub(crate) fn encode(&self, wrapped: bool) -> Vec<u8> {
+        self.repr(wrapped).to_bytes()
+    }
+
+    /// Loads a board from its compact binary form, rejecting boards that are malformed or don't
+    /// fit this board type
+    pub(crate) fn decode(bytes: &[u8], wrapped: bool) -> Result<Self, DecodeError> {
+        Self::from_repr(BoardRepr::from_bytes(bytes)?, wrapped)
+    }
+
+    /// Serializes this board as a JSON object for human readable formats and in the compact
+    /// binary form for all others
+    pub(crate) fn serialize_board<S: Serializer>(
+        &self,
+        wrapped: bool,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        if serializer.is_human_readable() {
+            self.repr(wrapped).serialize(serializer)
+        } else {
+            serializer.serialize_bytes(&self.encode(wrapped))
+        }
+    }
+
+    /// Reads a board written by [`CellBoard::serialize_board`]
+    pub(crate) fn deserialize_board<'de, De: Deserializer<'de>>(
+        wrapped: bool,
+        deserializer: De,
+    ) -> Result<Self, De::Error> {
+        let repr = if deserializer.is_human_readable() {
+            BoardRepr::deserialize(deserializer)?
+        } else {
+            let bytes = deserializer.deserialize_bytes(BytesVisitor)?;
+            BoardRepr::from_bytes(&bytes).map_err(de::Error::custom)?
+        };
+        Self::from_repr(repr, wrapped).map_err(de::Error::custom)
+    }
+}
@@ -0,0 +1,201 @@
+use crate::{
+    compact_representation::{
+        core::{dimensions::Dimensions, Cell, CellIndex},
+        CellNum,
+    },
+    types::{SnakeBodyGettableGame, SnakeIDMap, SnakeId},
+    wire_representation::{BattleSnake, Board, Game, NestedGame, Position, Ruleset, Settings},
+};
+
+use super::{CellBoard, ConversionError};
+
+impl<T: CellNum, D: Dimensions, const BOARD_SIZE: usize, const MAX_SNAKES: usize>
+    CellBoard<T, D, BOARD_SIZE, MAX_SNAKES>
+{
+    fn wire_snake(&self, id: &str, sid: SnakeId) -> BattleSnake {
+        let width = self.get_actual_width();
+        let health = self.healths[sid.as_usize()];
+        let body = if health > 0 {
+            self.get_snake_body_vec(&sid)
+                .into_iter()
+                .map(|ci| ci.into_position(width))
+                .collect()
+        } else {
+            Default::default()
+        };
+        BattleSnake {
+            id: id.to_string(),
+            name: id.to_string(),
+            head: self.heads[sid.as_usize()].into_position(width),
+            body,
+            health: health as i32,
+            shout: None,
+            actual_length: None,
+        }
+    }
+
+    /// converts this board back in to the wire representation, the inverse of
+    /// [`CellBoard::convert_from_game`]. Alive snakes are listed in the order of their ids, bodies
+    /// go from head to tail including stacked segments. The food and hazard settings of the
+    /// board are written in to the settings of `ruleset`, whose name has to match the rules of
+    /// this board and whether it's `wrapped`. `you` is the snake with id 0, it has an empty body
+    /// if it's dead
+    pub(crate) fn wire_game(
+        &self,
+        snake_ids: &SnakeIDMap,
+        turn: i32,
+        ruleset: Ruleset,
+        wrapped: bool,
+    ) -> Result<Game, ConversionError> {
+        let wrapped_ruleset = matches!(ruleset.name.as_str(), "wrapped" | "wrapped_constrictor");
+        let constrictor_ruleset =
+            matches!(ruleset.name.as_str(), "constrictor" | "wrapped_constrictor");
+        if wrapped_ruleset != wrapped || constrictor_ruleset != self.constrictor {
+            return Err(ConversionError::WrongRuleset(ruleset.name));
+        }
+
+        let mut ids: Vec<_> = snake_ids.iter().collect();
+        ids.sort_by_key(|(_, sid)| sid.as_usize());
+
+        let snakes = ids
+            .iter()
+            .filter(|(_, sid)| self.healths[sid.as_usize()] > 0)
+            .map(|(id, sid)| self.wire_snake(id, **sid))
+            .collect();
+        let you = ids
+            .iter()
+            .find(|(_, sid)| sid.as_usize() == 0)
+            .map(|(id, sid)| self.wire_snake(id, **sid))
+            .unwrap_or_else(|| self.wire_snake("", SnakeId(0)));
+
+        let width = self.get_actual_width();
+        let cells = (0..width as usize * self.get_actual_height() as usize)
+            .map(|index| (index, self.cells[index]));
+        let positions = |keep: fn(&Cell<T>) -> bool| -> Vec<Position> {
+            cells
+                .clone()
+                .filter(|(_, cell)| keep(cell))
+                .map(|(index, _)| CellIndex::<T>::from_usize(index).into_position(width))
+                .collect()
+        };
+
+        let settings = ruleset.settings.unwrap_or(Settings {
+            food_spawn_chance: 0,
+            minimum_food: 0,
+            hazard_damage_per_turn: 0,
+            hazard_map: None,
+            hazard_map_author: None,
+            royale: None,
+        });
+        Ok(Game {
+            you,
+            board: Board {
+                height: self.get_actual_height() as u32,
+                width: width as u32,
+                food: positions(|cell| cell.is_food()),
+                snakes,
+                hazards: positions(|cell| cell.is_hazard()),
+            },
+            turn,
+            game: NestedGame {
+                id: String::new(),
+                ruleset: Ruleset {
+                    settings: Some(Settings {
+                        food_spawn_chance: self.food_spawn_chance as i32,
+                        minimum_food: self.minimum_food as i32,
+                        hazard_damage_per_turn: self.hazard_damage as i32,
+                        ..settings
+                    }),
+                    ..ruleset
+                },
+                timeout: 500,
+                map: None,
+                source: None,
+            },
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{collections::HashSet, fs, path};
+
+    use crate::{
+        compact_representation::core::dimensions::Custom, types::build_snake_id_map,
+        wire_representation::Game,
+    };
+
+    use super::*;
+
+    type SillyCellBoard = CellBoard<u16, Custom, { 50 * 50 }, 16>;
+
+    #[test]
+    fn test_wire_game_round_trips_every_fixture() {
+        let mut dirs = vec![path::Path::new(env!("CARGO_MANIFEST_DIR")).join("fixtures")];
+        let mut checked = 0;
+        while let Some(dir) = dirs.pop() {
+            for entry in fs::read_dir(dir).unwrap() {
+                let path = entry.unwrap().path();
+                if path.is_dir() {
+                    dirs.push(path);
+                } else if check_fixture(&path) {
+                    checked += 1;
+                }
+            }
+        }
+        assert!(checked > 200);
+    }
+
+    fn check_fixture(path: &path::Path) -> bool {
+        // the packed hashes of old debug output aren't games
+        let Ok(game) = fs::read(path)
+            .map_err(|_| ())
+            .and_then(|bytes| serde_json::from_slice::<Game>(&bytes).map_err(|_| ()))
+        else {
+            return false;
+        };
+        let snake_ids = build_snake_id_map(&game);
+        let board = SillyCellBoard::convert_from_game(game.clone(), &snake_ids)
+            .unwrap_or_else(|e| panic!("{}: {}", path.display(), e));
+
+        let wire = board
+            .wire_game(
+                &snake_ids,
+                game.turn,
+                game.game.ruleset.clone(),
+                game.is_wrapped(),
+            )
+            .unwrap();
+        assert_eq!(
+            SillyCellBoard::convert_from_game(wire.clone(), &snake_ids).unwrap(),
+            board,
+            "{}",
+            path.display()
+        );
+
+        assert_eq!(wire.turn, game.turn);
+        assert_eq!(wire.game.ruleset.name, game.game.ruleset.name);
+        let alive: Vec<_> = game.board.snakes.iter().filter(|s| s.health > 0).collect();
+        assert_eq!(wire.board.snakes.len(), alive.len(), "{}", path.display());
+        // 8x8board.json has a snake and food outside of the board, which no cell can hold
+        let on_board = |p: &Position| {
+            p.x >= 0 && p.y >= 0 && p.x < game.board.width as i32 && p.y < game.board.height as i32
+        };
+        for snake in alive.into_iter().filter(|s| s.body.iter().all(on_board)) {
+            let converted = wire.board.snakes.iter().find(|s| s.id == snake.id).unwrap();
+            assert_eq!(converted.head, snake.head, "{}", path.display());
+            assert_eq!(converted.body, snake.body, "{}", path.display());
+            assert_eq!(converted.health, snake.health);
+        }
+        let as_set = |positions: &[Position]| {
+            positions
+                .iter()
+                .copied()
+                .filter(on_board)
+                .collect::<HashSet<_>>()
+        };
+        assert_eq!(as_set(&wire.board.food), as_set(&game.board.food));
+        assert_eq!(as_set(&wire.board.hazards), as_set(&game.board.hazards));
+        true
+    }
+}
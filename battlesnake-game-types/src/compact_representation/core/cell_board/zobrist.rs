@@ -0,0 +1,77 @@
+use crate::{
+    compact_representation::{
+        core::{dimensions::Dimensions, Cell},
+        CellNum,
+    },
+    types::{SnakeId, ZobristHashableGame},
+};
+
+use super::CellBoard;
+
+/// Healths within one bucket hash the same, so positions that only differ by a few points of
+/// health are treated as transpositions. Healths below the bucket size are close to starving
+/// and each hash on their own
+const HEALTH_BUCKET_SIZE: u8 = 10;
+
+const CELL_SALT: u64 = 0x2545_f491_4f6c_dd1d;
+const HEALTH_SALT: u64 = 0x9e37_79b9_7f4a_7c15;
+
+/// splitmix64 finalizer, the keys are computed instead of looked up in a table because
+/// cells of snakes also point at the next segment
+fn mix(mut z: u64) -> u64 {
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}
+
+/// the key of a cell at the given index, plain empty cells don't contribute to the hash
+pub(super) fn cell_key<T: CellNum>(index: usize, cell: Cell<T>) -> u64 {
+    if cell.is_empty() && !cell.is_hazard() {
+        return 0;
+    }
+    let packed = cell.pack_as_u32();
+    // food and empty cells don't own their snake id and index
+    let content = if cell.get_snake_id().is_some() {
+        packed
+    } else {
+        packed & 0xff
+    };
+    mix(CELL_SALT ^ ((index as u64) << 32) ^ content as u64)
+}
+
+/// the key of a snake with the given health
+pub(super) fn health_key(sid: SnakeId, health: u8) -> u64 {
+    let bucket = if health < HEALTH_BUCKET_SIZE {
+        health
+    } else {
+        HEALTH_BUCKET_SIZE + health.div_ceil(HEALTH_BUCKET_SIZE)
+    } as u64;
+    mix(HEALTH_SALT ^ ((sid.as_usize() as u64) << 8) ^ bucket)
+}
+
+impl<T: CellNum, D: Dimensions, const BOARD_SIZE: usize, const MAX_SNAKES: usize>
+    CellBoard<T, D, BOARD_SIZE, MAX_SNAKES>
+{
+    /// computes the zobrist key of this board from scratch
+    pub(crate) fn compute_zobrist(&self) -> u64 {
+        let cells = self
+            .cells
+            .iter()
+            .enumerate()
+            .fold(0, |key, (index, cell)| key ^ cell_key(index, *cell));
+        self.healths
+            .iter()
+            .enumerate()
+            .fold(cells, |key, (sid, health)| {
+                key ^ health_key(SnakeId(sid as u8), *health)
+            })
+    }
+}
+
+impl<T: CellNum, D: Dimensions, const BOARD_SIZE: usize, const MAX_SNAKES: usize>
+    ZobristHashableGame for CellBoard<T, D, BOARD_SIZE, MAX_SNAKES>
+{
+    fn zobrist_hash(&self) -> u64 {
+        self.zobrist
+    }
+}
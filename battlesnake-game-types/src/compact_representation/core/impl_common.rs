@@ -101,6 +101,26 @@ macro_rules! impl_common_board_traits {
             }
         }
 
+        impl<T: CN, D: Dimensions, const BOARD_SIZE: usize, const MAX_SNAKES: usize>
+            ZobristHashableGame for $type<T, D, BOARD_SIZE, MAX_SNAKES>
+        {
+            fn zobrist_hash(&self) -> u64 {
+                self.embedded.zobrist_hash()
+            }
+        }
+
+        impl<T: CN, D: Dimensions, const BOARD_SIZE: usize, const MAX_SNAKES: usize>
+            FoodSettableGame for $type<T, D, BOARD_SIZE, MAX_SNAKES>
+        {
+            fn set_food(&mut self, pos: Self::NativePositionType) {
+                self.embedded.set_food(pos)
+            }
+
+            fn clear_food(&mut self, pos: Self::NativePositionType) {
+                self.embedded.clear_food(pos)
+            }
+        }
+
         impl<T: CN, D: Dimensions, const BOARD_SIZE: usize, const MAX_SNAKES: usize>
             HazardQueryableGame for $type<T, D, BOARD_SIZE, MAX_SNAKES>
         {
@@ -163,7 +183,9 @@ macro_rules! impl_common_board_traits {
             for $type<T, D, BOARD_SIZE, MAX_SNAKES>
         {
             fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-                self.embedded.fmt(f)
+                self.embedded.fmt(f)?;
+                let json = serde_json::to_string(self).map_err(|_| std::fmt::Error)?;
+                writeln!(f, "{}", json)
             }
         }
         impl<T: CN, D: Dimensions, const BOARD_SIZE: usize, const MAX_SNAKES: usize>
@@ -201,9 +223,9 @@ macro_rules! impl_common_board_traits {
         impl<T: CN, D: Dimensions, const BOARD_SIZE: usize, const MAX_SNAKES: usize>
             std::convert::TryFrom<Game> for $type<T, D, BOARD_SIZE, MAX_SNAKES>
         {
-            type Error = Box<dyn Error>;
+            type Error = $crate::compact_representation::ConversionError;
 
-            fn try_from(game: Game) -> Result<Self, Box<dyn Error>> {
+            fn try_from(game: Game) -> Result<Self, Self::Error> {
                 let id_map = $crate::types::build_snake_id_map(&game);
 
                 $type::convert_from_game(game, &id_map)
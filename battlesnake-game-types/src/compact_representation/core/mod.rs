@@ -8,9 +8,12 @@ use crate::{
     wire_representation::Position,
 };
 
-pub use cell_board::{CellBoard, EvaluateMode};
+pub use cell_board::{
+    CellBoard, ConversionError, DecodeError, EvaluateMode, SimulationError, BOARD_FORMAT_VERSION,
+};
+pub(crate) use cell_board::{DEFAULT_FOOD_SPAWN_CHANCE, DEFAULT_MINIMUM_FOOD};
 pub use cell_num::CellNum;
-pub use simulate::simulate_with_moves;
+pub use simulate::{simulate_with_moves, simulate_with_moves_and_events, try_simulate_with_moves};
 
 /// wrapper type for an index in to the board
 #[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Ord, PartialOrd)]
@@ -109,6 +112,19 @@ impl<T: CellNum> Cell<T> {
         Self { flags, id, idx }
     }
 
+    /// whether this is a cell of a board with `size` cells and up to `max_snakes` snakes, snake
+    /// pieces have to belong to one of them and point at a cell of the board
+    pub fn is_valid(&self, size: usize, max_snakes: usize) -> bool {
+        let kind = self.flags & KIND_MASK;
+        if self.flags & !(KIND_MASK | IS_HAZARD) != 0 || kind == 0 || kind == KIND_MASK {
+            return false;
+        }
+        if kind == FOOD || kind == EMPTY {
+            return true;
+        }
+        self.id.as_usize() < max_snakes && self.idx.as_usize() < size
+    }
+
     pub fn is_empty(&self) -> bool {
         self.flags & KIND_MASK == EMPTY
     }
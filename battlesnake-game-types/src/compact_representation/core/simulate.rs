@@ -3,10 +3,18 @@ use std::{borrow::Borrow, time::Instant};
 use itertools::Itertools;
 use tracing::instrument;
 
-use crate::types::{Action, Move, SimulatorInstruments, SnakeId, N_MOVES};
+use crate::types::{
+    Action, Move, SimulatorInstruments, SnakeId, TurnEvent, ZobristHashableGame, N_MOVES,
+};
 
-use super::{cell_board::EvaluateMode, dimensions::Dimensions, CellBoard, CellNum};
+use super::{
+    cell_board::EvaluateMode, dimensions::Dimensions, CellBoard, CellNum, SimulationError,
+};
 
+/// [`try_simulate_with_moves`] for boards that are known to be consistent
+///
+/// # Panics
+/// if the board can't be simulated
 #[instrument(level = "trace", skip_all)]
 pub fn simulate_with_moves<
     'a,
@@ -25,18 +33,131 @@ pub fn simulate_with_moves<
 where
     S: Borrow<[Move]>,
 {
-    let start = Instant::now();
+    let results = try_simulate_with_moves(board, instruments, snake_ids_and_moves, evaluate_mode)
+        .unwrap_or_else(|e| panic!("{}: {}", e, board));
+    Box::new(results.map(move |result| result.unwrap_or_else(|e| panic!("{}: {}", e, board))))
+}
 
-    let mut snake_ids_we_are_simulating = [false; MAX_SNAKES];
-    for (snake_id, _) in snake_ids_and_moves.iter() {
-        snake_ids_we_are_simulating[snake_id.0.as_usize()] = true;
-    }
+/// Every board that can result from the given moves, along with the moves that lead to it.
+/// Fails if a move is given for a snake the board has no room for or a snake is inconsistent,
+/// and yields an error in place of any board the moves leave inconsistent
+#[allow(clippy::type_complexity)]
+#[instrument(level = "trace", skip_all)]
+pub fn try_simulate_with_moves<
+    'a,
+    S,
+    I: SimulatorInstruments,
+    T: CellNum,
+    D: Dimensions,
+    const BOARD_SIZE: usize,
+    const MAX_SNAKES: usize,
+>(
+    board: &'a CellBoard<T, D, BOARD_SIZE, MAX_SNAKES>,
+    instruments: &I,
+    snake_ids_and_moves: &[(SnakeId, S)],
+    evaluate_mode: EvaluateMode,
+) -> Result<
+    Box<
+        dyn Iterator<
+                Item = Result<
+                    (Action<MAX_SNAKES>, CellBoard<T, D, BOARD_SIZE, MAX_SNAKES>),
+                    SimulationError,
+                >,
+            > + 'a,
+    >,
+    SimulationError,
+>
+where
+    S: Borrow<[Move]>,
+{
+    let results = simulate_turns(
+        board,
+        instruments,
+        snake_ids_and_moves,
+        evaluate_mode,
+        false,
+    )?;
+    Ok(Box::new(results.map(|result| {
+        result.map(|(action, game, _)| (action, game))
+    })))
+}
+
+/// [`simulate_with_moves`] that also yields the events of the turn that led to each board
+///
+/// # Panics
+/// if the board can't be simulated
+#[allow(clippy::type_complexity)]
+#[instrument(level = "trace", skip_all)]
+pub fn simulate_with_moves_and_events<
+    'a,
+    S,
+    I: SimulatorInstruments,
+    T: CellNum,
+    D: Dimensions,
+    const BOARD_SIZE: usize,
+    const MAX_SNAKES: usize,
+>(
+    board: &'a CellBoard<T, D, BOARD_SIZE, MAX_SNAKES>,
+    instruments: &I,
+    snake_ids_and_moves: &[(SnakeId, S)],
+    evaluate_mode: EvaluateMode,
+) -> Box<
+    dyn Iterator<
+            Item = (
+                Action<MAX_SNAKES>,
+                CellBoard<T, D, BOARD_SIZE, MAX_SNAKES>,
+                Vec<TurnEvent>,
+            ),
+        > + 'a,
+>
+where
+    S: Borrow<[Move]>,
+{
+    let results = simulate_turns(board, instruments, snake_ids_and_moves, evaluate_mode, true)
+        .unwrap_or_else(|e| panic!("{}: {}", e, board));
+    Box::new(results.map(move |result| result.unwrap_or_else(|e| panic!("{}: {}", e, board))))
+}
+
+/// The boards of every combination of the given moves, with the events of their turn if
+/// `record_events` is set. A board the moves leave inconsistent is yielded as an error
+#[allow(clippy::type_complexity)]
+fn simulate_turns<
+    'a,
+    S,
+    I: SimulatorInstruments,
+    T: CellNum,
+    D: Dimensions,
+    const BOARD_SIZE: usize,
+    const MAX_SNAKES: usize,
+>(
+    board: &'a CellBoard<T, D, BOARD_SIZE, MAX_SNAKES>,
+    instruments: &I,
+    snake_ids_and_moves: &[(SnakeId, S)],
+    evaluate_mode: EvaluateMode,
+    record_events: bool,
+) -> Result<
+    impl Iterator<
+            Item = Result<
+                (
+                    Action<MAX_SNAKES>,
+                    CellBoard<T, D, BOARD_SIZE, MAX_SNAKES>,
+                    Vec<TurnEvent>,
+                ),
+                SimulationError,
+            >,
+        > + 'a,
+    SimulationError,
+>
+where
+    S: Borrow<[Move]>,
+{
+    let start = Instant::now();
 
     // [
     // sid major, move minor
     // [ some_reulst_struct, some_dead_struct ]
     // [ some_dead_struct, some_dead_struct ] // snake we didn't simulate
-    let states = board.generate_state(snake_ids_and_moves.iter(), evaluate_mode);
+    let states = board.generate_state(snake_ids_and_moves.iter(), evaluate_mode)?;
     let mut dead_snakes_table = [[false; N_MOVES]; MAX_SNAKES];
 
     for (sid, result_row) in states.iter().enumerate() {
@@ -65,17 +186,19 @@ where
     let results = ids_and_moves_product.into_iter().map(move |m| {
         let action = Action::collect_from(m.iter());
 
-        let game = board.evaluate_moves_with_state(m.iter(), &states);
+        let mut events = Vec::new();
+        let game = board.evaluate_moves_with_state(
+            m.iter(),
+            &states,
+            record_events.then_some(&mut events),
+        );
         if !game.assert_consistency() {
-            panic!(
-                "caught an inconsistent simulate, moves: {:?} orig: {}, new: {}",
-                m, board, game
-            );
+            return Err(SimulationError::InconsistentResult);
         }
-        (action, game)
+        debug_assert_eq!(game.zobrist_hash(), game.compute_zobrist());
+        Ok((action, game, events))
     });
-    let return_value = Box::new(results);
     let end = Instant::now();
     instruments.observe_simulation(end - start);
-    return_value
+    Ok(results)
 }
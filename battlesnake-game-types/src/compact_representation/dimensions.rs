@@ -12,6 +12,12 @@ pub trait Dimensions: Debug + Copy + Hash {
     /// Convert from a width and a height to this dimension
     fn from_dimensions(width: u8, height: u8) -> Self;
 
+    /// Like [Dimensions::from_dimensions], but None if this dimension can't hold a board of the
+    /// given width and height
+    fn try_from_dimensions(width: u8, height: u8) -> Option<Self> {
+        Some(Self::from_dimensions(width, height))
+    }
+
     /// Get the width of this dimension
     fn width(&self) -> u8;
 
@@ -41,6 +47,10 @@ impl Dimensions for Square {
 
         Self { width }
     }
+
+    fn try_from_dimensions(width: u8, height: u8) -> Option<Self> {
+        (width == height).then_some(Self { width })
+    }
 }
 
 #[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
@@ -62,6 +72,10 @@ impl<const W: u8, const H: u8> Dimensions for Fixed<W, H> {
 
         Self
     }
+
+    fn try_from_dimensions(width: u8, height: u8) -> Option<Self> {
+        (width == W && height == H).then_some(Self)
+    }
 }
 
 /// Alias for a [Fixed] board at the height and width for the ArcadeMaze map
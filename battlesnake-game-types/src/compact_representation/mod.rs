@@ -5,6 +5,8 @@ pub mod wrapped;
 
 pub use self::core::CellIndex;
 pub use self::core::CellNum;
+pub use self::core::{ConversionError, DecodeError, SimulationError, BOARD_FORMAT_VERSION};
+pub(crate) use self::core::{DEFAULT_FOOD_SPAWN_CHANCE, DEFAULT_MINIMUM_FOOD};
 
 use self::dimensions::Square;
 
@@ -5,12 +5,12 @@ use crate::types::*;
 /// you almost certainly want to use the `convert_from_game` method to
 /// cast from a json represention to a `CellBoard`
 use crate::types::{NeighborDeterminableGame, SnakeBodyGettableGame};
-use crate::wire_representation::Game;
+use crate::wire_representation::{Game, Ruleset};
 use itertools::Itertools;
 use rand::seq::IndexedRandom;
 use rand::Rng;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
 use std::borrow::Borrow;
-use std::error::Error;
 use std::fmt::Display;
 use tracing::instrument;
 
@@ -21,7 +21,10 @@ use crate::{
 
 use super::core::CellBoard as CCB;
 use super::core::CellIndex;
-use super::core::{simulate_with_moves, EvaluateMode};
+use super::core::{
+    simulate_with_moves, simulate_with_moves_and_events, try_simulate_with_moves, EvaluateMode,
+};
+use super::core::{ConversionError, DecodeError, SimulationError};
 use super::dimensions::{ArcadeMaze, Custom, Dimensions, Fixed, Square};
 
 /// A compact board representation that is significantly faster for simulation than
@@ -55,9 +58,9 @@ impl<T: CN, D: Dimensions, const BOARD_SIZE: usize, const MAX_SNAKES: usize>
     /// Builds a cellboard from a given game, will return an error if the game doesn't match
     /// the provided BOARD_SIZE or MAX_SNAKES. You are encouraged to use `CellBoard4Snakes11x11`
     /// for the common game layout
-    pub fn convert_from_game(game: Game, snake_ids: &SnakeIDMap) -> Result<Self, Box<dyn Error>> {
-        if game.game.ruleset.name == "wrapped" {
-            return Err("Wrapped games are not supported".into());
+    pub fn convert_from_game(game: Game, snake_ids: &SnakeIDMap) -> Result<Self, ConversionError> {
+        if game.is_wrapped() {
+            return Err(ConversionError::WrongRuleset(game.game.ruleset.name));
         }
 
         let embedded = CCB::convert_from_game(game, snake_ids)?;
@@ -75,6 +78,57 @@ impl<T: CN, D: Dimensions, const BOARD_SIZE: usize, const MAX_SNAKES: usize>
     pub fn get_all_empty(&self) -> impl Iterator<Item = CellIndex<T>> + '_ {
         self.embedded.get_empty_cells()
     }
+
+    /// Converts this board back in to the wire representation with the snake ids it was built
+    /// with, see [`CellBoard::convert_from_game`] for the other way. Fails if the name of
+    /// `ruleset` isn't one of the standard rulesets or disagrees with whether the board is played
+    /// with constrictor rules. Its food and hazard settings are taken from the board
+    pub fn to_wire_game(
+        &self,
+        snake_ids: &SnakeIDMap,
+        turn: i32,
+        ruleset: Ruleset,
+    ) -> Result<Game, ConversionError> {
+        self.embedded.wire_game(snake_ids, turn, ruleset, false)
+    }
+
+    /// The compact binary form of this board, the same serde uses for formats that aren't human
+    /// readable
+    pub fn to_bytes(&self) -> Vec<u8> {
+        self.embedded.encode(false)
+    }
+
+    /// Loads a standard board from its compact binary form, returns an error if the bytes are
+    /// malformed or the board doesn't fit this type
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, DecodeError> {
+        let embedded = CCB::decode(bytes, false)?;
+        Ok(CellBoard { embedded })
+    }
+}
+
+impl<T: CN, D: Dimensions, const BOARD_SIZE: usize, const MAX_SNAKES: usize> Serialize
+    for CellBoard<T, D, BOARD_SIZE, MAX_SNAKES>
+{
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.embedded.serialize_board(false, serializer)
+    }
+}
+
+impl<'de, T: CN, D: Dimensions, const BOARD_SIZE: usize, const MAX_SNAKES: usize> Deserialize<'de>
+    for CellBoard<T, D, BOARD_SIZE, MAX_SNAKES>
+{
+    fn deserialize<De: Deserializer<'de>>(deserializer: De) -> Result<Self, De::Error> {
+        let embedded = CCB::deserialize_board(false, deserializer)?;
+        Ok(CellBoard { embedded })
+    }
+}
+
+impl<T: CN, D: Dimensions, const BOARD_SIZE: usize, const MAX_SNAKES: usize> WrapDeterminableGame
+    for CellBoard<T, D, BOARD_SIZE, MAX_SNAKES>
+{
+    fn is_wrapped(&self) -> bool {
+        false
+    }
 }
 
 impl<T: CN, D: Dimensions, const BOARD_SIZE: usize, const MAX_SNAKES: usize>
@@ -148,7 +202,7 @@ impl<
                 &self.embedded,
                 instruments,
                 snake_ids_and_moves,
-                EvaluateMode::Standard,
+                EvaluateMode::Standard.with_constrictor(self.embedded.is_constrictor()),
             )
             .map(|v| {
                 let (action, board) = v;
@@ -158,6 +212,69 @@ impl<
     }
 }
 
+impl<
+        T: SimulatorInstruments,
+        D: Dimensions,
+        N: CN,
+        const BOARD_SIZE: usize,
+        const MAX_SNAKES: usize,
+    > FallibleSimulableGame<T, MAX_SNAKES> for CellBoard<N, D, BOARD_SIZE, MAX_SNAKES>
+{
+    type SimulationError = SimulationError;
+
+    #[allow(clippy::type_complexity)]
+    fn try_simulate_with_moves<S>(
+        &self,
+        instruments: &T,
+        snake_ids_and_moves: &[(Self::SnakeIDType, S)],
+    ) -> Result<
+        Box<dyn Iterator<Item = Result<(Action<MAX_SNAKES>, Self), SimulationError>> + '_>,
+        SimulationError,
+    >
+    where
+        S: Borrow<[Move]>,
+    {
+        let results = try_simulate_with_moves(
+            &self.embedded,
+            instruments,
+            snake_ids_and_moves,
+            EvaluateMode::Standard.with_constrictor(self.embedded.is_constrictor()),
+        )?;
+        Ok(Box::new(results.map(|result| {
+            result.map(|(action, board)| (action, Self { embedded: board }))
+        })))
+    }
+}
+
+impl<
+        T: SimulatorInstruments,
+        D: Dimensions,
+        N: CN,
+        const BOARD_SIZE: usize,
+        const MAX_SNAKES: usize,
+    > EventSimulableGame<T, MAX_SNAKES> for CellBoard<N, D, BOARD_SIZE, MAX_SNAKES>
+{
+    #[allow(clippy::type_complexity)]
+    fn simulate_with_moves_and_events<S>(
+        &self,
+        instruments: &T,
+        snake_ids_and_moves: &[(Self::SnakeIDType, S)],
+    ) -> Box<dyn Iterator<Item = (Action<MAX_SNAKES>, Self, Vec<TurnEvent>)> + '_>
+    where
+        S: Borrow<[Move]>,
+    {
+        Box::new(
+            simulate_with_moves_and_events(
+                &self.embedded,
+                instruments,
+                snake_ids_and_moves,
+                EvaluateMode::Standard.with_constrictor(self.embedded.is_constrictor()),
+            )
+            .map(|(action, board, events)| (action, Self { embedded: board }, events)),
+        )
+    }
+}
+
 impl<T: CN, D: Dimensions, const BOARD_SIZE: usize, const MAX_SNAKES: usize>
     NeighborDeterminableGame for CellBoard<T, D, BOARD_SIZE, MAX_SNAKES>
 {
@@ -227,50 +344,64 @@ pub enum BestCellBoard {
     Silly(Box<CellBoard16Snakes50x50>),
 }
 
-/// Trait to get the best sized cellboard for the given game. It returns the smallest Compact board
-/// that has enough room to fit the given Wire game. If the game can't fit in any of our Compact
-/// boards we panic. However the largest board available is MUCH larger than the biggest selectable
-/// board in the Battlesnake UI
-pub trait ToBestCellBoard {
-    #[allow(missing_docs)]
-    fn to_best_cell_board(self) -> Result<BestCellBoard, Box<dyn Error>>;
-}
-
-impl ToBestCellBoard for Game {
-    fn to_best_cell_board(self) -> Result<BestCellBoard, Box<dyn Error>> {
-        let width = self.board.width;
-        let height = self.board.height;
-        let num_snakes = self.board.snakes.len();
-        let id_map = build_snake_id_map(&self);
+impl BestCellBoard {
+    /// Builds the smallest Compact board that fits `game`, using the given snake ids. Games that
+    /// outlive some of their snakes should keep passing the ids they started with, so every snake
+    /// keeps its id and the board keeps room for all of them. Returns an error if the game doesn't
+    /// fit in any of our Compact boards
+    pub fn from_game(game: Game, snake_ids: &SnakeIDMap) -> Result<Self, ConversionError> {
+        let width = game.board.width;
+        let height = game.board.height;
+        let num_snakes = game.board.snakes.len().max(snake_ids.len());
 
         let best_board = if width == 7 && height == 7 && num_snakes <= 4 {
-            BestCellBoard::SmallExact(Box::new(CellBoard::convert_from_game(self, &id_map)?))
+            BestCellBoard::SmallExact(Box::new(CellBoard::convert_from_game(game, snake_ids)?))
         } else if width <= 7 && height <= 7 && num_snakes <= 4 {
-            BestCellBoard::Tiny(Box::new(CellBoard::convert_from_game(self, &id_map)?))
+            BestCellBoard::Tiny(Box::new(CellBoard::convert_from_game(game, snake_ids)?))
         } else if width == 11 && height == 11 && num_snakes <= 4 {
-            BestCellBoard::MediumExact(Box::new(CellBoard::convert_from_game(self, &id_map)?))
+            BestCellBoard::MediumExact(Box::new(CellBoard::convert_from_game(game, snake_ids)?))
         } else if width <= 11 && height <= 11 && num_snakes <= 4 {
-            BestCellBoard::Standard(Box::new(CellBoard::convert_from_game(self, &id_map)?))
+            BestCellBoard::Standard(Box::new(CellBoard::convert_from_game(game, snake_ids)?))
         } else if width <= 15 && height <= 15 && num_snakes <= 8 {
-            BestCellBoard::LargestU8(Box::new(CellBoard::convert_from_game(self, &id_map)?))
+            BestCellBoard::LargestU8(Box::new(CellBoard::convert_from_game(game, snake_ids)?))
         } else if width == 19 && height == 19 && num_snakes <= 4 {
-            BestCellBoard::LargeExact(Box::new(CellBoard::convert_from_game(self, &id_map)?))
+            BestCellBoard::LargeExact(Box::new(CellBoard::convert_from_game(game, snake_ids)?))
         } else if width == 19 && height == 21 && num_snakes <= 4 {
-            BestCellBoard::ArcadeMaze(Box::new(CellBoard::convert_from_game(self, &id_map)?))
+            BestCellBoard::ArcadeMaze(Box::new(CellBoard::convert_from_game(game, snake_ids)?))
         } else if width == 19 && height == 21 && num_snakes <= 8 {
-            BestCellBoard::ArcadeMaze8Snake(Box::new(CellBoard::convert_from_game(self, &id_map)?))
-        } else if width <= 25 && height < 25 && num_snakes <= 8 {
-            BestCellBoard::Large(Box::new(CellBoard::convert_from_game(self, &id_map)?))
+            BestCellBoard::ArcadeMaze8Snake(Box::new(CellBoard::convert_from_game(
+                game, snake_ids,
+            )?))
+        } else if width <= 25 && height <= 25 && num_snakes <= 8 {
+            BestCellBoard::Large(Box::new(CellBoard::convert_from_game(game, snake_ids)?))
         } else if width <= 50 && height <= 50 && num_snakes <= 16 {
-            BestCellBoard::Silly(Box::new(CellBoard::convert_from_game(self, &id_map)?))
+            BestCellBoard::Silly(Box::new(CellBoard::convert_from_game(game, snake_ids)?))
+        } else if width <= 50 && height <= 50 {
+            return Err(ConversionError::TooManySnakes(num_snakes));
         } else {
-            panic!("No board was big enough")
+            return Err(ConversionError::BoardTooLarge { width, height });
         };
 
         Ok(best_board)
     }
 }
 
+/// Trait to get the best sized cellboard for the given game. It returns the smallest Compact board
+/// that has enough room to fit the given Wire game. If the game can't fit in any of our Compact
+/// boards we return an error. However the largest board available is MUCH larger than the biggest
+/// selectable board in the Battlesnake UI
+pub trait ToBestCellBoard {
+    #[allow(missing_docs)]
+    fn to_best_cell_board(self) -> Result<BestCellBoard, ConversionError>;
+}
+
+impl ToBestCellBoard for Game {
+    fn to_best_cell_board(self) -> Result<BestCellBoard, ConversionError> {
+        let id_map = build_snake_id_map(&self);
+        BestCellBoard::from_game(self, &id_map)
+    }
+}
+
 #[cfg(test)]
 mod test {
 
@@ -278,8 +409,11 @@ mod test {
 
     use super::*;
     use crate::{
-        compact_representation::core::Cell, game_fixture, types::build_snake_id_map,
-        wire_representation::Game as DEGame,
+        compact_representation::core::Cell,
+        compact_representation::DecodeError,
+        game_fixture,
+        types::build_snake_id_map,
+        wire_representation::{BattleSnake, Game as DEGame},
     };
     #[derive(Debug)]
     struct Instruments;
@@ -319,6 +453,214 @@ mod test {
         }
     }
 
+    #[test]
+    fn test_best_cell_board_with_snake_ids() {
+        let mut game = game_fixture(include_str!("../../../fixtures/start_of_game.json"));
+        let id_map = build_snake_id_map(&game);
+        let last_snake = game.board.snakes.pop().unwrap();
+        let BestCellBoard::MediumExact(board) =
+            BestCellBoard::from_game(game.clone(), &id_map).expect("the game fits")
+        else {
+            panic!("expected an exactly 11x11 board");
+        };
+        // The snake that is gone keeps its id, so the others don't shift
+        let gone = id_map.get(&last_snake.id).unwrap();
+        assert!(!board.is_alive(gone));
+        for snake in &game.board.snakes {
+            assert!(board.is_alive(id_map.get(&snake.id).unwrap()));
+        }
+
+        game.board.width = 25;
+        game.board.height = 25;
+        assert!(matches!(
+            BestCellBoard::from_game(game.clone(), &id_map),
+            Ok(BestCellBoard::Large(_))
+        ));
+
+        game.board.width = 51;
+        assert_eq!(
+            BestCellBoard::from_game(game, &id_map).unwrap_err(),
+            ConversionError::BoardTooLarge {
+                width: 51,
+                height: 25
+            }
+        );
+    }
+
+    #[test]
+    fn test_conversion_errors() {
+        let g = game_fixture(include_str!("../../../fixtures/4_snake_game.json"));
+        let snake_ids = build_snake_id_map(&g);
+        let convert = |g: &DEGame| g.as_cell_board::<u8, Square, { 11 * 11 }, 4>(&snake_ids);
+        let id = g.board.snakes[1].id.clone();
+
+        let mut too_large = g.clone();
+        too_large.board.height = 12;
+        assert_eq!(
+            convert(&too_large).unwrap_err(),
+            ConversionError::BoardTooLarge {
+                width: 11,
+                height: 12
+            }
+        );
+        too_large.board.height = 1 << 30;
+        too_large.board.width = 1 << 30;
+        assert!(matches!(
+            convert(&too_large),
+            Err(ConversionError::BoardTooLarge { .. })
+        ));
+
+        let mut too_many = g.clone();
+        too_many.board.snakes.push(g.board.snakes[0].clone());
+        assert_eq!(
+            convert(&too_many).unwrap_err(),
+            ConversionError::TooManySnakes(5)
+        );
+
+        let mut wrapped = g.clone();
+        wrapped.game.ruleset.name = "wrapped".to_string();
+        assert_eq!(
+            convert(&wrapped).unwrap_err(),
+            ConversionError::WrongRuleset("wrapped".to_string())
+        );
+
+        let mut unknown = g.clone();
+        unknown.board.snakes[1].id = "someone else".to_string();
+        assert_eq!(
+            convert(&unknown).unwrap_err(),
+            ConversionError::UnknownSnakeId("someone else".to_string())
+        );
+
+        let inconsistent = |change: &dyn Fn(&mut BattleSnake)| {
+            let mut g = g.clone();
+            change(&mut g.board.snakes[1]);
+            convert(&g).unwrap_err()
+        };
+        let expected = ConversionError::InconsistentBody(id);
+        assert_eq!(inconsistent(&|s| s.body.clear()), expected);
+        assert_eq!(inconsistent(&|s| s.head.x += 1), expected);
+        assert_eq!(
+            inconsistent(&|s| s.body.push_front(Position { x: 20, y: 20 })),
+            expected
+        );
+        assert_eq!(
+            inconsistent(&|s| {
+                let head = s.head;
+                s.body.push_front(head)
+            }),
+            expected
+        );
+        assert_eq!(
+            inconsistent(&|s| {
+                let tail = *s.body.back().unwrap();
+                s.body.extend([tail, tail]);
+            }),
+            expected
+        );
+    }
+
+    #[test]
+    fn test_simulation_errors() {
+        let g = game_fixture(include_str!("../../../fixtures/4_snake_game.json"));
+        let board: CellBoard4Snakes11x11 = g.as_cell_board(&build_snake_id_map(&g)).unwrap();
+        assert!(board
+            .try_simulate_with_moves(&Instruments, &[(SnakeId(0), [Move::Up])])
+            .is_ok());
+        assert_eq!(
+            board
+                .try_simulate_with_moves(&Instruments, &[(SnakeId(4), [Move::Up])])
+                .err(),
+            Some(SimulationError::UnknownSnakeId(SnakeId(4)))
+        );
+    }
+
+    #[test]
+    fn test_simulate_with_moves_and_events() {
+        let g = game_fixture(include_str!("../../../fixtures/start_of_game.json"));
+        let snake_ids = build_snake_id_map(&g);
+        // body, health and move of a snake
+        type Placement<'a> = (&'a [(i32, i32)], i32, Move);
+        // Places the snakes, in the order of the fixture, and returns the events of their moves
+        let turn = |snakes: [Placement; 4], food: Vec<Position>| {
+            let mut g = g.clone();
+            let mut moves = vec![];
+            for (snake, (body, health, mv)) in g.board.snakes.iter_mut().zip(snakes) {
+                snake.body = body.iter().map(|&(x, y)| Position { x, y }).collect();
+                snake.head = snake.body[0];
+                snake.health = health;
+                moves.push((snake_ids[&snake.id], [mv]));
+            }
+            moves.sort_by_key(|(sid, _)| sid.0);
+            g.board.food = food;
+            g.board.hazards = vec![Position { x: 3, y: 1 }];
+            let board: CellBoard4Snakes11x11 = g.as_cell_board(&snake_ids).unwrap();
+
+            let mut results = board
+                .simulate_with_moves_and_events(&Instruments, &moves)
+                .collect_vec();
+            assert_eq!(results.len(), 1);
+            let (action, next, events) = results.pop().unwrap();
+            let expected = board
+                .simulate_with_moves(&Instruments, &moves)
+                .next()
+                .unwrap();
+            assert_eq!((action, next), expected);
+            events
+        };
+        let sid = |i: usize| snake_ids[&g.board.snakes[i].id];
+        let died = |i: usize, cause| TurnEvent::Died { sid: sid(i), cause };
+
+        let events = turn(
+            [
+                (&[(0, 5), (1, 5), (2, 5)], 90, Move::Left),
+                (&[(5, 8), (5, 9), (5, 10)], 1, Move::Down),
+                (&[(4, 1), (5, 1), (6, 1)], 10, Move::Left),
+                (&[(9, 0), (9, 1), (9, 2)], 1, Move::Right),
+            ],
+            vec![Position { x: 10, y: 0 }],
+        );
+        let mut expected = vec![
+            died(0, DeathCause::Wall),
+            died(1, DeathCause::Starvation),
+            died(2, DeathCause::HazardDamage),
+        ];
+        expected.sort_by_key(|event| match event {
+            TurnEvent::Died { sid, .. } => sid.0,
+            _ => unreachable!(),
+        });
+        expected.insert(0, TurnEvent::AteFood { sid: sid(3) });
+        assert_eq!(events, expected);
+
+        let events = turn(
+            [
+                (&[(3, 3), (3, 4), (3, 5)], 90, Move::Right),
+                (&[(5, 3), (5, 4), (5, 5), (5, 6)], 90, Move::Left),
+                (&[(7, 7), (8, 7), (8, 8), (7, 8), (6, 8)], 90, Move::Up),
+                (&[(2, 4), (1, 4), (0, 4)], 90, Move::Right),
+            ],
+            vec![],
+        );
+        let mut expected = vec![
+            died(0, DeathCause::LostHeadToHead),
+            died(2, DeathCause::SelfCollision),
+            died(3, DeathCause::BodyCollision),
+        ];
+        expected.sort_by_key(|event| match event {
+            TurnEvent::Died { sid, .. } => sid.0,
+            _ => unreachable!(),
+        });
+        let mut head_to_head = vec![sid(0), sid(1)];
+        head_to_head.sort_by_key(|sid| sid.0);
+        expected.insert(
+            0,
+            TurnEvent::HeadToHead {
+                snakes: head_to_head,
+                winner: Some(sid(1)),
+            },
+        );
+        assert_eq!(events, expected);
+    }
+
     #[test]
     fn test_head_gettable() {
         let game_fixture = include_str!("../../../fixtures/late_stage.json");
@@ -363,6 +705,92 @@ mod test {
         assert!(compact.get_health(&SnakeId(0)) > 0);
     }
 
+    #[test]
+    fn test_constrictor_snakes_grow_every_turn() {
+        let g = game_fixture(include_str!("../../../fixtures/constrictor.json"));
+        let snake_id_mapping = build_snake_id_map(&g);
+        let mut compact: CellBoard4Snakes11x11 = g.as_cell_board(&snake_id_mapping).unwrap();
+        assert!(compact.embedded.is_constrictor());
+
+        let instruments = Instruments;
+        for turn in 1..=3 {
+            let res = compact
+                .simulate_with_moves(
+                    &instruments,
+                    &[
+                        (SnakeId(0), [Move::Up].as_slice()),
+                        (SnakeId(1), [Move::Down].as_slice()),
+                    ],
+                )
+                .collect_vec();
+            compact = res[0].1;
+            for id in [SnakeId(0), SnakeId(1)] {
+                assert_eq!(compact.get_health(&id), 100);
+                assert_eq!(compact.get_length(&id), 4 + turn);
+            }
+        }
+        assert_eq!(
+            compact.get_head_as_position(&SnakeId(0)),
+            Position { x: 5, y: 8 }
+        );
+        let body = compact
+            .get_snake_body_vec(&SnakeId(0))
+            .into_iter()
+            .map(|c| c.into_position(11))
+            .collect_vec();
+        assert_eq!(
+            body,
+            [(5, 8), (5, 7), (5, 6), (5, 5), (5, 4), (5, 3), (5, 3)]
+                .iter()
+                .map(|&(x, y)| Position { x, y })
+                .collect_vec()
+        );
+    }
+
+    #[test]
+    fn test_constrictor_snakes_start_stacked() {
+        let mut g = game_fixture(include_str!("../../../fixtures/constrictor.json"));
+        g.turn = 0;
+        for snake in g.board.snakes.iter_mut() {
+            snake.body = std::iter::repeat_n(snake.body[0], 3).collect();
+            snake.head = snake.body[0];
+        }
+        let snake_id_mapping = build_snake_id_map(&g);
+        let mut compact: CellBoard4Snakes11x11 = g.as_cell_board(&snake_id_mapping).unwrap();
+
+        let instruments = Instruments;
+        // The tail of the start is still stacked after the first move, so nobody grows
+        for (turn, length) in [(1, 3), (2, 4), (3, 5)] {
+            let res = compact
+                .simulate_with_moves(
+                    &instruments,
+                    &[
+                        (SnakeId(0), [Move::Up].as_slice()),
+                        (SnakeId(1), [Move::Down].as_slice()),
+                    ],
+                )
+                .collect_vec();
+            compact = res[0].1;
+            for id in [SnakeId(0), SnakeId(1)] {
+                assert_eq!(compact.get_health(&id), 100, "turn {turn}");
+                assert_eq!(compact.get_length(&id), length, "turn {turn}");
+                assert_eq!(compact.get_snake_body_vec(&id).len(), length as usize);
+            }
+        }
+        let body = compact
+            .get_snake_body_vec(&SnakeId(0))
+            .into_iter()
+            .map(|c| c.into_position(11))
+            .collect_vec();
+        assert_eq!(
+            body,
+            [(5, 8), (5, 7), (5, 6), (5, 5), (5, 5)]
+                .iter()
+                .map(|&(x, y)| Position { x, y })
+                .collect_vec()
+        );
+    }
+
     #[test]
     fn test_set_hazard() {
         let mut c: Cell<u8> = Cell::empty();
@@ -403,6 +831,293 @@ mod test {
         assert!(c.get_snake_id().is_none());
         assert!(c.get_idx() == CellIndex(0));
     }
+    #[test]
+    fn test_place_food_follows_settings() {
+        use rand::{rngs::SmallRng, SeedableRng};
+
+        let mut rng = SmallRng::seed_from_u64(0);
+        let mut g = game_fixture(include_str!("../../../fixtures/start_of_game.json"));
+        g.board.food.clear();
+        let settings = |food_spawn_chance, minimum_food| {
+            Some(crate::wire_representation::Settings {
+                food_spawn_chance,
+                minimum_food,
+                hazard_damage_per_turn: 0,
+                hazard_map: None,
+                hazard_map_author: None,
+                royale: None,
+            })
+        };
+        let snake_id_mapping = build_snake_id_map(&g);
+
+        g.game.ruleset.settings = settings(0, 3);
+        let mut compact: CellBoard4Snakes11x11 = g.as_cell_board(&snake_id_mapping).unwrap();
+        for _ in 0..10 {
+            compact.place_food(&mut rng);
+            assert_eq!(compact.get_all_food_as_native_positions().len(), 3);
+        }
+
+        g.game.ruleset.settings = settings(100, 0);
+        let mut compact: CellBoard4Snakes11x11 = g.as_cell_board(&snake_id_mapping).unwrap();
+        for food in 1..=10 {
+            compact.place_food(&mut rng);
+            assert_eq!(compact.get_all_food_as_native_positions().len(), food);
+        }
+
+        // Settings out of range don't wrap around
+        g.game.ruleset.settings = settings(300, -253);
+        let mut compact: CellBoard4Snakes11x11 = g.as_cell_board(&snake_id_mapping).unwrap();
+        compact.place_food(&mut rng);
+        assert_eq!(compact.get_all_food_as_native_positions().len(), 1);
+        g.game.ruleset.settings = settings(-1, 0);
+        let mut compact: CellBoard4Snakes11x11 = g.as_cell_board(&snake_id_mapping).unwrap();
+        compact.place_food(&mut rng);
+        assert!(compact.get_all_food_as_native_positions().is_empty());
+
+        // Food has no use in constrictor, whatever the settings say
+        let mut g = game_fixture(include_str!("../../../fixtures/constrictor.json"));
+        g.game.ruleset.settings = settings(100, 3);
+        let mut compact: CellBoard4Snakes11x11 = g.as_cell_board(&build_snake_id_map(&g)).unwrap();
+        compact.place_food(&mut rng);
+        assert!(compact.get_all_food_as_native_positions().is_empty());
+    }
+
+    #[test]
+    fn test_zobrist_hash_is_maintained_incrementally() {
+        use rand::{rngs::SmallRng, SeedableRng};
+
+        let mut rng = SmallRng::seed_from_u64(4);
+        let g = game_fixture(include_str!("../../../fixtures/late_stage.json"));
+        let start: CellBoard4Snakes11x11 = g.as_cell_board(&build_snake_id_map(&g)).unwrap();
+        for _ in 0..20 {
+            let mut board = start;
+            while !board.is_over() {
+                let moves = board
+                    .random_reasonable_move_for_each_snake(&mut rng)
+                    .map(|(sid, mv)| (sid, [mv]))
+                    .collect_vec();
+                let next = board.simulate_with_moves(&Instruments, &moves).next();
+                board = next.unwrap().1;
+                board.place_food(&mut rng);
+                board.set_hazard(CellIndex(rng.random_range(0..121)));
+                assert_eq!(board.zobrist_hash(), board.embedded.compute_zobrist());
+            }
+        }
+    }
+
+    #[test]
+    fn test_zobrist_hash_identifies_positions() {
+        let g = game_fixture(include_str!("../../../fixtures/late_stage.json"));
+        let start: CellBoard4Snakes11x11 = g.as_cell_board(&build_snake_id_map(&g)).unwrap();
+        let moves = start
+            .reasonable_moves_for_each_snake()
+            .map(|(sid, mvs)| (sid, mvs.into_iter().collect_vec()))
+            .collect_vec();
+        let boards = start
+            .simulate_with_moves(&Instruments, &moves)
+            .map(|(_, board)| board)
+            .collect_vec();
+        assert!(boards.len() > 1);
+        for a in &boards {
+            for b in &boards {
+                assert_eq!(a == b, a.zobrist_hash() == b.zobrist_hash());
+            }
+        }
+
+        // a few points of health don't make a different position, unless the snake is close
+        // to starving
+        let sid = SnakeId(0);
+        let with_health = |health: u8| {
+            let mut board = start;
+            board.embedded.set_health(sid, health);
+            board.zobrist_hash()
+        };
+        assert_eq!(with_health(52), with_health(57));
+        assert_ne!(with_health(50), with_health(51));
+        let close_to_starving: std::collections::HashSet<_> = (0..=10).map(with_health).collect();
+        assert_eq!(close_to_starving.len(), 11);
+        assert!(!close_to_starving.contains(&with_health(11)));
+
+        // food and hazards are part of the position
+        let safe = (0..121)
+            .map(CellIndex)
+            .find(|pos| !start.is_hazard(pos))
+            .unwrap();
+        let mut with_hazard = start;
+        with_hazard.set_hazard(safe);
+        assert_ne!(with_hazard.zobrist_hash(), start.zobrist_hash());
+        with_hazard.clear_hazard(safe);
+        assert_eq!(with_hazard.zobrist_hash(), start.zobrist_hash());
+    }
+
+    #[test]
+    fn test_serde_round_trips() {
+        fn round_trip<B>(board: B)
+        where
+            B: Serialize + for<'de> Deserialize<'de> + PartialEq + std::fmt::Debug,
+        {
+            let json = serde_json::to_string(&board).unwrap();
+            assert_eq!(serde_json::from_str::<B>(&json).unwrap(), board);
+
+            let mut cbor = Vec::new();
+            ciborium::into_writer(&board, &mut cbor).unwrap();
+            assert_eq!(
+                ciborium::from_reader::<B, _>(cbor.as_slice()).unwrap(),
+                board
+            );
+        }
+
+        for fixture in [
+            include_str!("../../../fixtures/start_of_game.json"),
+            include_str!("../../../fixtures/late_stage.json"),
+            include_str!("../../../fixtures/constrictor.json"),
+            include_str!("../../../fixtures/cornered.json"),
+            include_str!("../../../fixtures/tail_chase.json"),
+        ] {
+            let g = game_fixture(fixture);
+            let board: CellBoard4Snakes11x11 = g.as_cell_board(&build_snake_id_map(&g)).unwrap();
+            assert_eq!(
+                CellBoard4Snakes11x11::from_bytes(&board.to_bytes()).unwrap(),
+                board
+            );
+            round_trip(board);
+            // boards the simulation left behind, with dead snakes and stacked tails
+            let moves = board
+                .reasonable_moves_for_each_snake()
+                .map(|(sid, mvs)| (sid, mvs.into_iter().collect_vec()))
+                .collect_vec();
+            for (_, child) in board.simulate_with_moves(&Instruments, &moves) {
+                round_trip(child);
+            }
+        }
+
+        // smaller games in a bigger board only store the cells they use
+        let g = game_fixture(include_str!("../../../fixtures/8x8board.json"));
+        let board: CellBoard8Snakes25x25 = g.as_cell_board(&build_snake_id_map(&g)).unwrap();
+        assert!(board.to_bytes().len() < 8 + 8 * 5 + 8 * 8 * 4 + 1);
+        round_trip(board);
+    }
+
+    #[test]
+    fn test_malformed_boards_are_rejected() {
+        let g = game_fixture(include_str!("../../../fixtures/late_stage.json"));
+        let board: CellBoard4Snakes11x11 = g.as_cell_board(&build_snake_id_map(&g)).unwrap();
+        let bytes = board.to_bytes();
+
+        let load = |bytes: &[u8]| CellBoard4Snakes11x11::from_bytes(bytes);
+        assert_eq!(load(&bytes[..bytes.len() - 1]), Err(DecodeError::Truncated));
+        assert_eq!(load(&[]), Err(DecodeError::Truncated));
+        let mut trailing = bytes.clone();
+        trailing.push(0);
+        assert_eq!(load(&trailing), Err(DecodeError::TrailingBytes));
+        let mut future = bytes.clone();
+        future[0] = 2;
+        assert_eq!(load(&future), Err(DecodeError::UnsupportedVersion(2)));
+        assert_eq!(
+            CellBoard4Snakes7x7::from_bytes(&bytes),
+            Err(DecodeError::DoesNotFit {
+                width: 11,
+                height: 11
+            })
+        );
+        let eight_snakes: CellBoard8Snakes15x15 = g.as_cell_board(&build_snake_id_map(&g)).unwrap();
+        assert_eq!(
+            load(&eight_snakes.to_bytes()),
+            Err(DecodeError::TooManySnakes(8))
+        );
+
+        let repr = serde_json::to_value(board).unwrap();
+        let load_json = |edit: &dyn Fn(&mut serde_json::Value)| {
+            let mut repr = repr.clone();
+            edit(&mut repr);
+            serde_json::from_value::<CellBoard4Snakes11x11>(repr)
+                .unwrap_err()
+                .to_string()
+        };
+        assert_eq!(
+            load_json(&|r| r["cells"][3] = 0x07.into()),
+            DecodeError::InvalidCell(3).to_string()
+        );
+        assert_eq!(
+            load_json(&|r| {
+                r["cells"].as_array_mut().unwrap().pop();
+            }),
+            DecodeError::WrongCellCount {
+                expected: 121,
+                actual: 120
+            }
+            .to_string()
+        );
+        assert_eq!(
+            load_json(&|r| {
+                r["lengths"].as_array_mut().unwrap().pop();
+            }),
+            DecodeError::MismatchedSnakes.to_string()
+        );
+        // the head of snake 0 is moved somewhere else
+        let head = board.get_head_as_native_position(&SnakeId(0)).0;
+        assert_eq!(
+            load_json(&|r| r["heads"][0] = ((head + 1) % 121).into()),
+            DecodeError::InconsistentSnake(SnakeId(0)).to_string()
+        );
+        assert!(load_json(&|r| r["color"] = "red".into()).contains("unknown field"));
+    }
+
+    #[test]
+    fn test_to_wire_game_round_trips_simulated_boards() {
+        use rand::{rngs::SmallRng, SeedableRng};
+
+        let g = game_fixture(include_str!("../../../fixtures/4_snake_game.json"));
+        let snake_ids = build_snake_id_map(&g);
+        let mut board: CellBoard4Snakes11x11 = g.as_cell_board(&snake_ids).unwrap();
+        let mut rng = SmallRng::seed_from_u64(7);
+        for turn in g.turn..g.turn + 40 {
+            let wire = board
+                .to_wire_game(&snake_ids, turn, g.game.ruleset.clone())
+                .unwrap();
+            assert_eq!(wire.turn, turn);
+            assert_eq!(
+                CellBoard4Snakes11x11::convert_from_game(wire, &snake_ids).unwrap(),
+                board
+            );
+            if board.is_over() {
+                break;
+            }
+            let moves = board
+                .random_reasonable_move_for_each_snake(&mut rng)
+                .map(|(sid, mv)| (sid, [mv]))
+                .collect_vec();
+            let next = board
+                .simulate_with_moves(&Instruments, &moves)
+                .next()
+                .unwrap()
+                .1;
+            board = next;
+            board.place_food(&mut rng);
+        }
+    }
+
+    #[test]
+    fn test_to_wire_game_rejects_other_rulesets() {
+        let g = game_fixture(include_str!("../../../fixtures/4_snake_game.json"));
+        let snake_ids = build_snake_id_map(&g);
+        let board: CellBoard4Snakes11x11 = g.as_cell_board(&snake_ids).unwrap();
+        let wire_game = |name: &str| {
+            let mut ruleset = g.game.ruleset.clone();
+            ruleset.name = name.to_string();
+            board.to_wire_game(&snake_ids, g.turn, ruleset)
+        };
+
+        assert!(wire_game("standard").is_ok());
+        assert!(wire_game("royale").is_ok());
+        for name in ["wrapped", "constrictor", "wrapped_constrictor"] {
+            assert_eq!(
+                wire_game(name).unwrap_err(),
+                ConversionError::WrongRuleset(name.to_string())
+            );
+        }
+    }
+
     #[test]
     fn test_set_food() {
         let mut c: Cell<u8> = Cell::empty();
@@ -5,13 +5,13 @@ use crate::types::*;
 /// you almost certainly want to use the `convert_from_game` method to
 /// cast from a json represention to a `CellBoard`
 use crate::types::{NeighborDeterminableGame, SnakeBodyGettableGame};
-use crate::wire_representation::Game;
+use crate::wire_representation::{Game, Ruleset};
 use itertools::Itertools;
 use rand::seq::IndexedRandom;
 use rand::Rng;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
 use std::borrow::Borrow;
 use std::collections::HashMap;
-use std::error::Error;
 use std::fmt::Display;
 
 use crate::{
@@ -19,8 +19,10 @@ use crate::{
     wire_representation::Position,
 };
 
-use super::core::{simulate_with_moves, EvaluateMode};
-use super::core::{CellBoard as CCB, CellIndex};
+use super::core::{
+    simulate_with_moves, simulate_with_moves_and_events, try_simulate_with_moves, EvaluateMode,
+};
+use super::core::{CellBoard as CCB, CellIndex, ConversionError, DecodeError, SimulationError};
 use super::dimensions::{ArcadeMaze, Custom, Dimensions, Fixed, Square};
 use super::CellNum as CN;
 
@@ -42,24 +44,68 @@ impl<T: CN, D: Dimensions, const BOARD_SIZE: usize, const MAX_SNAKES: usize>
     }
 
     /// creates a wrapped board from a Wire Representation game
-    pub fn convert_from_game(game: Game, snake_ids: &SnakeIDMap) -> Result<Self, Box<dyn Error>> {
-        if game.game.ruleset.name != "wrapped" {
-            return Err("only wrapped games are supported".into());
+    pub fn convert_from_game(game: Game, snake_ids: &SnakeIDMap) -> Result<Self, ConversionError> {
+        if !game.is_wrapped() {
+            return Err(ConversionError::WrongRuleset(game.game.ruleset.name));
         }
         let embedded = CCB::convert_from_game(game, snake_ids)?;
         Ok(CellBoard { embedded })
     }
 
-    /// for debugging, packs this board into a custom json representation
+    /// Converts this board back in to the wire representation with the snake ids it was built
+    /// with, see [`CellBoard::convert_from_game`] for the other way. Fails if the name of
+    /// `ruleset` isn't one of the wrapped rulesets or disagrees with whether the board is played
+    /// with constrictor rules. Its food and hazard settings are taken from the board
+    pub fn to_wire_game(
+        &self,
+        snake_ids: &SnakeIDMap,
+        turn: i32,
+        ruleset: Ruleset,
+    ) -> Result<Game, ConversionError> {
+        self.embedded.wire_game(snake_ids, turn, ruleset, true)
+    }
+
+    /// The compact binary form of this board, the same serde uses for formats that aren't human
+    /// readable
+    pub fn to_bytes(&self) -> Vec<u8> {
+        self.embedded.encode(true)
+    }
+
+    /// Loads a wrapped board from its compact binary form, returns an error if the bytes are
+    /// malformed or the board doesn't fit this type
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, DecodeError> {
+        let embedded = CCB::decode(bytes, true)?;
+        Ok(CellBoard { embedded })
+    }
+
+    /// for debugging, packs this board into the legacy json representation. New boards should
+    /// be stored with [`CellBoard::to_bytes`] or serde JSON instead
     pub fn pack_as_hash(&self) -> HashMap<String, Vec<u32>> {
         self.embedded.pack_as_hash()
     }
 
-    /// for debugging, unloads a board from a custom json representation
-    pub fn from_packed_hash(hash: &HashMap<String, Vec<u32>>) -> Self {
-        Self {
-            embedded: CCB::from_packed_hash(hash),
-        }
+    /// for debugging, unloads a board from a custom json representation. Fails on fields that
+    /// are missing or out of range, but keeps inconsistent snakes
+    pub fn from_packed_hash(hash: &HashMap<String, Vec<u32>>) -> Result<Self, DecodeError> {
+        let embedded = CCB::from_packed_hash(hash)?;
+        Ok(Self { embedded })
+    }
+}
+
+impl<T: CN, D: Dimensions, const BOARD_SIZE: usize, const MAX_SNAKES: usize> Serialize
+    for CellBoard<T, D, BOARD_SIZE, MAX_SNAKES>
+{
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.embedded.serialize_board(true, serializer)
+    }
+}
+
+impl<'de, T: CN, D: Dimensions, const BOARD_SIZE: usize, const MAX_SNAKES: usize> Deserialize<'de>
+    for CellBoard<T, D, BOARD_SIZE, MAX_SNAKES>
+{
+    fn deserialize<De: Deserializer<'de>>(deserializer: De) -> Result<Self, De::Error> {
+        let embedded = CCB::deserialize_board(true, deserializer)?;
+        Ok(CellBoard { embedded })
     }
 }
 
@@ -104,50 +150,72 @@ pub enum BestCellBoard {
     Silly(Box<CellBoard16SnakesSquare50x50>),
 }
 
-/// Trait to get the best sized cellboard for the given game. It returns the smallest Compact board
-/// that has enough room to fit the given Wire game. If the game can't fit in any of our Compact
-/// boards we panic. However the largest board available is MUCH larger than the biggest selectable
-/// board in the Battlesnake UI
-pub trait ToBestCellBoard {
-    #[allow(missing_docs)]
-    fn to_best_cell_board(self) -> Result<BestCellBoard, Box<dyn Error>>;
-}
-
-impl ToBestCellBoard for Game {
-    fn to_best_cell_board(self) -> Result<BestCellBoard, Box<dyn Error>> {
-        let width = self.board.width;
-        let height = self.board.height;
-        let num_snakes = self.board.snakes.len();
-        let id_map = build_snake_id_map(&self);
+impl BestCellBoard {
+    /// Builds the smallest wrapped Compact board that fits `game`, using the given snake ids.
+    /// Games that outlive some of their snakes should keep passing the ids they started with, so
+    /// every snake keeps its id and the board keeps room for all of them. Returns an error if the
+    /// game isn't wrapped or doesn't fit in any of our Compact boards
+    pub fn from_game(game: Game, snake_ids: &SnakeIDMap) -> Result<Self, ConversionError> {
+        let width = game.board.width;
+        let height = game.board.height;
+        let num_snakes = game.board.snakes.len().max(snake_ids.len());
 
         let best_board = if width == 7 && height == 7 && num_snakes <= 4 {
-            BestCellBoard::SmallExact(Box::new(CellBoard::convert_from_game(self, &id_map)?))
+            BestCellBoard::SmallExact(Box::new(CellBoard::convert_from_game(game, snake_ids)?))
         } else if width <= 7 && height <= 7 && num_snakes <= 4 {
-            BestCellBoard::Tiny(Box::new(CellBoard::convert_from_game(self, &id_map)?))
+            BestCellBoard::Tiny(Box::new(CellBoard::convert_from_game(game, snake_ids)?))
         } else if width == 11 && height == 11 && num_snakes <= 4 {
-            BestCellBoard::MediumExact(Box::new(CellBoard::convert_from_game(self, &id_map)?))
+            BestCellBoard::MediumExact(Box::new(CellBoard::convert_from_game(game, snake_ids)?))
         } else if width <= 11 && height <= 11 && num_snakes <= 4 {
-            BestCellBoard::Standard(Box::new(CellBoard::convert_from_game(self, &id_map)?))
+            BestCellBoard::Standard(Box::new(CellBoard::convert_from_game(game, snake_ids)?))
         } else if width <= 15 && height <= 15 && num_snakes <= 8 {
-            BestCellBoard::LargestU8(Box::new(CellBoard::convert_from_game(self, &id_map)?))
+            BestCellBoard::LargestU8(Box::new(CellBoard::convert_from_game(game, snake_ids)?))
         } else if width == 19 && height == 19 && num_snakes <= 4 {
-            BestCellBoard::LargeExact(Box::new(CellBoard::convert_from_game(self, &id_map)?))
+            BestCellBoard::LargeExact(Box::new(CellBoard::convert_from_game(game, snake_ids)?))
         } else if width == 19 && height == 21 && num_snakes <= 4 {
-            BestCellBoard::ArcadeMaze(Box::new(CellBoard::convert_from_game(self, &id_map)?))
+            BestCellBoard::ArcadeMaze(Box::new(CellBoard::convert_from_game(game, snake_ids)?))
         } else if width == 19 && height == 21 && num_snakes <= 8 {
-            BestCellBoard::ArcadeMaze8Snake(Box::new(CellBoard::convert_from_game(self, &id_map)?))
-        } else if width <= 25 && height < 25 && num_snakes <= 8 {
-            BestCellBoard::Large(Box::new(CellBoard::convert_from_game(self, &id_map)?))
+            BestCellBoard::ArcadeMaze8Snake(Box::new(CellBoard::convert_from_game(
+                game, snake_ids,
+            )?))
+        } else if width <= 25 && height <= 25 && num_snakes <= 8 {
+            BestCellBoard::Large(Box::new(CellBoard::convert_from_game(game, snake_ids)?))
         } else if width <= 50 && height <= 50 && num_snakes <= 16 {
-            BestCellBoard::Silly(Box::new(CellBoard::convert_from_game(self, &id_map)?))
+            BestCellBoard::Silly(Box::new(CellBoard::convert_from_game(game, snake_ids)?))
+        } else if width <= 50 && height <= 50 {
+            return Err(ConversionError::TooManySnakes(num_snakes));
         } else {
-            panic!("No board was big enough")
+            return Err(ConversionError::BoardTooLarge { width, height });
         };
 
         Ok(best_board)
     }
 }
 
+/// Trait to get the best sized cellboard for the given game. It returns the smallest Compact board
+/// that has enough room to fit the given Wire game. If the game can't fit in any of our Compact
+/// boards we return an error. However the largest board available is MUCH larger than the biggest
+/// selectable board in the Battlesnake UI
+pub trait ToBestCellBoard {
+    #[allow(missing_docs)]
+    fn to_best_cell_board(self) -> Result<BestCellBoard, ConversionError>;
+}
+
+impl ToBestCellBoard for Game {
+    fn to_best_cell_board(self) -> Result<BestCellBoard, ConversionError> {
+        let id_map = build_snake_id_map(&self);
+        BestCellBoard::from_game(self, &id_map)
+    }
+}
+
+impl<T: CN, D: Dimensions, const BOARD_SIZE: usize, const MAX_SNAKES: usize> WrapDeterminableGame
+    for CellBoard<T, D, BOARD_SIZE, MAX_SNAKES>
+{
+    fn is_wrapped(&self) -> bool {
+        true
+    }
+}
+
 impl<T: CN, D: Dimensions, const BOARD_SIZE: usize, const MAX_SNAKES: usize>
     RandomReasonableMovesGame for CellBoard<T, D, BOARD_SIZE, MAX_SNAKES>
 {
@@ -230,7 +298,7 @@ impl<
                 &self.embedded,
                 instruments,
                 snake_ids_and_moves,
-                EvaluateMode::Wrapped,
+                EvaluateMode::Wrapped.with_constrictor(self.embedded.is_constrictor()),
             )
             .map(|v| {
                 let (action, board) = v;
@@ -240,6 +308,69 @@ impl<
     }
 }
 
+impl<
+        T: SimulatorInstruments,
+        N: CN,
+        D: Dimensions,
+        const BOARD_SIZE: usize,
+        const MAX_SNAKES: usize,
+    > FallibleSimulableGame<T, MAX_SNAKES> for CellBoard<N, D, BOARD_SIZE, MAX_SNAKES>
+{
+    type SimulationError = SimulationError;
+
+    #[allow(clippy::type_complexity)]
+    fn try_simulate_with_moves<S>(
+        &self,
+        instruments: &T,
+        snake_ids_and_moves: &[(Self::SnakeIDType, S)],
+    ) -> Result<
+        Box<dyn Iterator<Item = Result<(Action<MAX_SNAKES>, Self), SimulationError>> + '_>,
+        SimulationError,
+    >
+    where
+        S: Borrow<[Move]>,
+    {
+        let results = try_simulate_with_moves(
+            &self.embedded,
+            instruments,
+            snake_ids_and_moves,
+            EvaluateMode::Wrapped.with_constrictor(self.embedded.is_constrictor()),
+        )?;
+        Ok(Box::new(results.map(|result| {
+            result.map(|(action, board)| (action, Self { embedded: board }))
+        })))
+    }
+}
+
+impl<
+        T: SimulatorInstruments,
+        D: Dimensions,
+        N: CN,
+        const BOARD_SIZE: usize,
+        const MAX_SNAKES: usize,
+    > EventSimulableGame<T, MAX_SNAKES> for CellBoard<N, D, BOARD_SIZE, MAX_SNAKES>
+{
+    #[allow(clippy::type_complexity)]
+    fn simulate_with_moves_and_events<S>(
+        &self,
+        instruments: &T,
+        snake_ids_and_moves: &[(Self::SnakeIDType, S)],
+    ) -> Box<dyn Iterator<Item = (Action<MAX_SNAKES>, Self, Vec<TurnEvent>)> + '_>
+    where
+        S: Borrow<[Move]>,
+    {
+        Box::new(
+            simulate_with_moves_and_events(
+                &self.embedded,
+                instruments,
+                snake_ids_and_moves,
+                EvaluateMode::Wrapped.with_constrictor(self.embedded.is_constrictor()),
+            )
+            .map(|(action, board, events)| (action, Self { embedded: board }, events)),
+        )
+    }
+}
+
 impl<T: CN, D: Dimensions, const BOARD_SIZE: usize, const MAX_SNAKES: usize>
     NeighborDeterminableGame for CellBoard<T, D, BOARD_SIZE, MAX_SNAKES>
 {
@@ -283,14 +414,17 @@ mod test {
         compact_representation::core::Cell,
         game_fixture,
         types::{
-            build_snake_id_map, HeadGettableGame, HealthGettableGame, Move,
+            build_snake_id_map, HeadGettableGame, HealthGettableGame, LengthGettableGame, Move,
             NeighborDeterminableGame, RandomReasonableMovesGame, ReasonableMovesGame,
-            SimulableGame, SimulatorInstruments, SnakeId,
+            SimulableGame, SimulatorInstruments, SnakeId, WrapDeterminableGame,
         },
         wire_representation::Position,
     };
 
-    use super::{CellBoard4SnakesSquare11x11, CellIndex};
+    use super::{BestCellBoard, CellBoard4SnakesSquare11x11, CellIndex, Square};
+    use crate::compact_representation::{
+        ConversionError, DecodeError, StandardCellBoard4Snakes11x11,
+    };
 
     #[derive(Debug)]
     struct Instruments {}
@@ -311,14 +445,174 @@ mod test {
         eprintln!(
             "{}",
             serde_json::to_string(
-                &CellBoard4SnakesSquare11x11::from_packed_hash(&hash).pack_as_hash()
+                &CellBoard4SnakesSquare11x11::from_packed_hash(&hash)
+                    .unwrap()
+                    .pack_as_hash()
             )
             .unwrap()
         );
         assert_eq!(
-            CellBoard4SnakesSquare11x11::from_packed_hash(&hash),
+            CellBoard4SnakesSquare11x11::from_packed_hash(&hash).unwrap(),
+            orig_wrapped_cell
+        );
+    }
+
+    #[test]
+    fn test_from_packed_hash_rejects_malformed_hashes() {
+        let g = game_fixture(include_str!("../../../fixtures/wrapped_fixture.json"));
+        let board: CellBoard4SnakesSquare11x11 =
+            g.as_wrapped_cell_board(&build_snake_id_map(&g)).unwrap();
+        let load = |field: &str, values: Option<Vec<u32>>| {
+            let mut hash = board.pack_as_hash();
+            match values {
+                Some(values) => hash.insert(field.to_string(), values),
+                None => hash.remove(field),
+            };
+            CellBoard4SnakesSquare11x11::from_packed_hash(&hash)
+        };
+
+        assert_eq!(
+            load("cells", None),
+            Err(DecodeError::MalformedField("cells"))
+        );
+        assert_eq!(
+            load("hazard_damage", Some(vec![])),
+            Err(DecodeError::MalformedField("hazard_damage"))
+        );
+        assert_eq!(
+            load("healths", Some(vec![300])),
+            Err(DecodeError::MalformedField("healths"))
+        );
+        assert_eq!(
+            load("actual_width", Some(vec![19])),
+            Err(DecodeError::DoesNotFit {
+                width: 19,
+                height: 19
+            })
+        );
+        assert_eq!(
+            load("lengths", Some(vec![3; 5])),
+            Err(DecodeError::TooManySnakes(5))
+        );
+        assert_eq!(
+            load("heads", Some(vec![121])),
+            Err(DecodeError::InconsistentSnake(SnakeId(0)))
+        );
+        assert_eq!(
+            load("cells", Some(vec![0; 122])),
+            Err(DecodeError::WrongCellCount {
+                expected: 121,
+                actual: 122
+            })
+        );
+        // fields newer than the hash fall back to their defaults
+        assert_eq!(load("minimum_food", None).unwrap(), board);
+    }
+
+    #[test]
+    fn test_serde_round_trips() {
+        let g = game_fixture(include_str!("../../../fixtures/wrapped_fixture.json"));
+        eprintln!("{}", g.board);
+        let snake_ids = build_snake_id_map(&g);
+        let orig_wrapped_cell: CellBoard4SnakesSquare11x11 =
+            g.as_wrapped_cell_board(&snake_ids).unwrap();
+        let json = serde_json::to_string(&orig_wrapped_cell).unwrap();
+        eprintln!("{}", json);
+        assert_eq!(
+            serde_json::from_str::<CellBoard4SnakesSquare11x11>(&json).unwrap(),
+            orig_wrapped_cell
+        );
+        assert_eq!(
+            CellBoard4SnakesSquare11x11::from_bytes(&orig_wrapped_cell.to_bytes()).unwrap(),
             orig_wrapped_cell
         );
+
+        // the ruleset is part of the board, a wrapped board isn't a standard one
+        assert_eq!(
+            StandardCellBoard4Snakes11x11::from_bytes(&orig_wrapped_cell.to_bytes()),
+            Err(DecodeError::WrongRuleset { wrapped: true })
+        );
+        assert!(serde_json::from_str::<StandardCellBoard4Snakes11x11>(&json).is_err());
+    }
+
+    #[test]
+    fn test_best_cell_board_from_game() {
+        let mut game = game_fixture(include_str!("../../../fixtures/wrapped_fixture.json"));
+        let id_map = build_snake_id_map(&game);
+        let Ok(BestCellBoard::MediumExact(board)) = BestCellBoard::from_game(game.clone(), &id_map)
+        else {
+            panic!("expected an exactly 11x11 board");
+        };
+        assert!(board.is_wrapped());
+
+        game.board.width = 25;
+        game.board.height = 25;
+        assert!(matches!(
+            BestCellBoard::from_game(game.clone(), &id_map),
+            Ok(BestCellBoard::Large(_))
+        ));
+
+        game.board.width = 11;
+        game.board.height = 11;
+        game.game.ruleset.name = "standard".to_string();
+        assert_eq!(
+            game.as_wrapped_cell_board::<u8, Square, { 11 * 11 }, 4>(&id_map)
+                .unwrap_err(),
+            ConversionError::WrongRuleset("standard".to_string())
+        );
+        assert!(matches!(
+            BestCellBoard::from_game(game, &id_map),
+            Err(ConversionError::WrongRuleset(_))
+        ));
+    }
+
+    #[test]
+    fn test_constrictor_wraps_and_grows() {
+        let g = game_fixture(include_str!("../../../fixtures/wrapped_constrictor.json"));
+        assert!(g.is_wrapped());
+        let snake_ids = build_snake_id_map(&g);
+        let wrapped: CellBoard4SnakesSquare11x11 = g.as_wrapped_cell_board(&snake_ids).unwrap();
+        assert!(wrapped.embedded.is_constrictor());
+        assert_eq!(
+            CellBoard4SnakesSquare11x11::from_packed_hash(&wrapped.pack_as_hash()).unwrap(),
+            wrapped
+        );
+        assert_eq!(
+            CellBoard4SnakesSquare11x11::from_bytes(&wrapped.to_bytes()).unwrap(),
+            wrapped
+        );
+        let wire_game = |name: &str| {
+            let mut ruleset = g.game.ruleset.clone();
+            ruleset.name = name.to_string();
+            wrapped.to_wire_game(&snake_ids, g.turn, ruleset)
+        };
+        assert!(wire_game("wrapped_constrictor").is_ok());
+        for name in ["wrapped", "constrictor", "standard"] {
+            assert_eq!(
+                wire_game(name).unwrap_err(),
+                ConversionError::WrongRuleset(name.to_string())
+            );
+        }
+
+        let instruments = Instruments {};
+        let res = wrapped
+            .simulate_with_moves(
+                &instruments,
+                &[
+                    (SnakeId(0), [Move::Right].as_slice()),
+                    (SnakeId(1), [Move::Up].as_slice()),
+                ],
+            )
+            .collect_vec();
+        let next = res[0].1;
+        assert_eq!(
+            next.get_head_as_position(&SnakeId(0)),
+            Position { x: 0, y: 5 }
+        );
+        for id in [SnakeId(0), SnakeId(1)] {
+            assert_eq!(next.get_health(&id), 100);
+            assert_eq!(next.get_length(&id), 5);
+        }
     }
 
     #[test]
@@ -459,7 +753,7 @@ mod test {
             // this json fixture is the frame at which we crashed, and it comes from a deep forward simulation of orig_crash_game
             let json_hash = include_str!("../../../fixtures/crash_json_hash.json");
             let hm = serde_json::from_str(json_hash).unwrap();
-            let game = super::CellBoard4SnakesSquare11x11::from_packed_hash(&hm);
+            let game = super::CellBoard4SnakesSquare11x11::from_packed_hash(&hm).unwrap();
             eprintln!("{}", orig_crash_game.board);
             dbg!(&compact_ids);
             let snakes_and_moves = compact_ids.iter().map(|id| (*id, vec![Move::Up]));
@@ -482,7 +776,7 @@ mod test {
             ];
             let json_hash = include_str!("../../../fixtures/another_wraped_panic_serialized.json");
             let hm = serde_json::from_str(json_hash).unwrap();
-            let game = super::CellBoard4SnakesSquare11x11::from_packed_hash(&hm);
+            let game = super::CellBoard4SnakesSquare11x11::from_packed_hash(&hm).unwrap();
             game.assert_consistency();
             eprintln!(
                 "!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!\n!!!!!!!!!!!!!!!!!!!!!!!!!!!!1\n{}",
@@ -507,7 +801,7 @@ mod test {
             ];
             let json_hash = include_str!("../../../fixtures/another_wrapped_panic.json");
             let hm = serde_json::from_str(json_hash).unwrap();
-            let game = super::CellBoard4SnakesSquare11x11::from_packed_hash(&hm);
+            let game = super::CellBoard4SnakesSquare11x11::from_packed_hash(&hm).unwrap();
             game.assert_consistency();
             eprintln!(
                 "!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!\n!!!!!!!!!!!!!!!!!!!!!!!!!!!!1\n{}",
@@ -1,8 +1,11 @@
 //! traits and structs for simulating hazard algorithms in battlesnake
-//! implements non-deterministic forecasting for randomized algorithms
+//! implements non-deterministic forecasting for randomized algorithms, and a registry
+//! ([HazardMap]) from the `hazard_map` names of the game settings to the algorithms
 
 use std::error::Error;
 
+use rand::{rngs::SmallRng, seq::IndexedRandom, seq::SliceRandom, Rng, SeedableRng};
+
 use crate::{
     types::Move,
     wire_representation::{Game, Position},
@@ -63,8 +66,12 @@ pub struct SpiralHazard {
     current_turn: u16,
     next_hazard_cell: Position,
     direction: Move,
+    seed: Option<u64>,
 }
 
+/// the turn the official spiral places its seed cell on
+const SPIRAL_FIRST_TURN: u16 = 3;
+
 impl SpiralHazard {
     /// Construct an unitialized spiral hazard algorithm
     pub fn new() -> Self {
@@ -75,6 +82,16 @@ impl SpiralHazard {
             current_turn: 0,
             next_hazard_cell: Position { x: 0, y: 0 },
             direction: Move::Up,
+            seed: None,
+        }
+    }
+
+    /// Construct an unitialized spiral hazard algorithm that draws the seed cell from
+    /// `seed` when it is observed before the seed cell is placed
+    pub fn with_seed(seed: u64) -> Self {
+        SpiralHazard {
+            seed: Some(seed),
+            ..Self::new()
         }
     }
 }
@@ -125,7 +142,8 @@ impl ForwardOnlyHazardAlgorithm<Position> for SpiralHazard {
     /// call this with game states until the seed cell has been observed
     /// which will usually be on turn 3, once you've seen the seed cell
     /// you should stop calling observe, and start calling inc_turn to
-    /// calculate forward hazard squares
+    /// calculate forward hazard squares. If constructed `with_seed` the seed
+    /// cell is drawn when observing a game before turn 3 instead
     fn observe(
         &mut self,
         game: &Game,
@@ -149,6 +167,19 @@ impl ForwardOnlyHazardAlgorithm<Position> for SpiralHazard {
                 self.next_hazard_cell = self.seed_cell.add_vec(Move::Up.to_vector());
                 self.direction = Move::Right;
                 return Ok(Box::new(Some(self.seed_cell).into_iter()));
+            } else if let Some(seed) = self.seed.filter(|_| game.turn < SPIRAL_FIRST_TURN as i32) {
+                let mut rng = SmallRng::seed_from_u64(seed);
+                let (width, height) = (game.board.width as i32, game.board.height as i32);
+                // keep the seed cell away from the walls on boards large enough to
+                self.seed_cell = Position {
+                    x: rng.random_range(2.min(width / 2)..(width - 2).max(width / 2 + 1)),
+                    y: rng.random_range(2.min(height / 2)..(height - 2).max(height / 2 + 1)),
+                };
+                self.hazard_every_turns = 3;
+                self.first_turn_seen = SPIRAL_FIRST_TURN;
+                self.current_turn = game.turn as u16;
+                self.next_hazard_cell = self.seed_cell.add_vec(Move::Up.to_vector());
+                self.direction = Move::Right;
             }
         }
         Ok(Box::new(None.into_iter()))
@@ -164,6 +195,12 @@ impl ForwardOnlyHazardAlgorithm<Position> for SpiralHazard {
 
     fn inc_turn(&mut self) -> Box<dyn Iterator<Item = Position>> {
         self.current_turn += 1;
+        // only seeded spirals are wound before their seed cell is placed
+        if self.current_turn < self.first_turn_seen {
+            return Box::new(None.into_iter());
+        } else if self.current_turn == self.first_turn_seen {
+            return Box::new(Some(self.seed_cell).into_iter());
+        }
         if self
             .current_turn
             .is_multiple_of(self.hazard_every_turns as u16)
@@ -209,16 +246,659 @@ impl ForwardOnlyHazardAlgorithm<Position> for SpiralHazard {
     }
 }
 
+/// Royale hazard algorithm, every `shrinkEveryNTurns` turns a randomly chosen side of the safe
+/// area shrinks by one row or column. The side of every shrink is drawn from the seed, so the
+/// same seed always shrinks the board the same way
+#[derive(Debug, Copy, Clone)]
+pub struct RoyaleHazard {
+    seed: u64,
+    shrink_every_n_turns: u16,
+    current_turn: u16,
+    width: i32,
+    height: i32,
+    min_x: i32,
+    max_x: i32,
+    min_y: i32,
+    max_y: i32,
+}
+
+impl RoyaleHazard {
+    /// Construct an unitialized royale hazard algorithm that shrinks with the given seed
+    pub fn new(seed: u64) -> Self {
+        RoyaleHazard {
+            seed,
+            shrink_every_n_turns: 0,
+            current_turn: 0,
+            width: 0,
+            height: 0,
+            min_x: 0,
+            max_x: 0,
+            min_y: 0,
+            max_y: 0,
+        }
+    }
+
+    /// the same algorithm, but the shrinks after the current turn are drawn from `seed`
+    pub fn with_seed(self, seed: u64) -> Self {
+        RoyaleHazard { seed, ..self }
+    }
+
+    /// Wind the turn forward by one without collecting the new hazards, returns true if
+    /// the safe area shrank on the wound turn
+    pub fn advance(&mut self) -> bool {
+        self.current_turn += 1;
+        if self.shrink_every_n_turns == 0
+            || !self.current_turn.is_multiple_of(self.shrink_every_n_turns)
+        {
+            return false;
+        }
+        let mut rng = SmallRng::seed_from_u64(self.seed ^ self.current_turn as u64);
+        match rng.random_range(0..4) {
+            0 if self.min_x < self.max_x => self.min_x += 1,
+            1 if self.min_x < self.max_x => self.max_x -= 1,
+            2 if self.min_y < self.max_y => self.min_y += 1,
+            3 if self.min_y < self.max_y => self.max_y -= 1,
+            _ => return false,
+        }
+        true
+    }
+
+    /// is this position outside of the safe area
+    pub fn is_hazard(&self, pos: &Position) -> bool {
+        pos.x < self.min_x || pos.x > self.max_x || pos.y < self.min_y || pos.y > self.max_y
+    }
+
+    /// every position of the board that is currently a hazard
+    pub fn hazards(&self) -> impl Iterator<Item = Position> {
+        let this = *self;
+        (0..this.height)
+            .flat_map(move |y| (0..this.width).map(move |x| Position { x, y }))
+            .filter(move |pos| this.is_hazard(pos))
+    }
+}
+
+impl ForwardOnlyHazardAlgorithm<Position> for RoyaleHazard {
+    /// call this once with the current game, the safe area is the smallest
+    /// rectangle that contains every cell that isn't a hazard. Errors if the
+    /// game isn't played with royale settings
+    fn observe(
+        &mut self,
+        game: &Game,
+    ) -> Result<Box<dyn Iterator<Item = Position>>, Box<dyn Error>> {
+        if self.is_ready_for_inc() {
+            return Err("already ready for inc".into());
+        }
+        let shrink_every_n_turns = game
+            .game
+            .ruleset
+            .settings
+            .as_ref()
+            .and_then(|settings| settings.royale)
+            .map(|royale| royale.shrink_every_n_turns)
+            .filter(|turns| *turns > 0)
+            .ok_or("game doesn't shrink with royale settings")?;
+
+        self.shrink_every_n_turns = shrink_every_n_turns as u16;
+        self.current_turn = game.turn as u16;
+        self.width = game.board.width as i32;
+        self.height = game.board.height as i32;
+        (self.min_x, self.max_x) = (self.width - 1, 0);
+        (self.min_y, self.max_y) = (self.height - 1, 0);
+        for y in 0..self.height {
+            for x in 0..self.width {
+                let pos = Position { x, y };
+                if !game.board.hazards.contains(&pos) {
+                    self.min_x = self.min_x.min(x);
+                    self.max_x = self.max_x.max(x);
+                    self.min_y = self.min_y.min(y);
+                    self.max_y = self.max_y.max(y);
+                }
+            }
+        }
+        Ok(Box::new(game.board.hazards.clone().into_iter()))
+    }
+
+    fn is_ready_for_inc(&self) -> bool {
+        self.shrink_every_n_turns != 0
+    }
+
+    fn current_turn(&self) -> usize {
+        self.current_turn as usize
+    }
+
+    fn inc_turn(&mut self) -> Box<dyn Iterator<Item = Position>> {
+        let before = *self;
+        if self.advance() {
+            let after = *self;
+            Box::new(after.hazards().filter(move |pos| !before.is_hazard(pos)))
+        } else {
+            Box::new(std::iter::empty())
+        }
+    }
+}
+
+/// Scatter hazard algorithm (`hz_scatter`), starting on turn 1 a single cell of the board
+/// turns into a hazard every other turn, in a shuffled order. The order is drawn from the
+/// seed, cells that were observed as hazards already are skipped. The schedule hasn't been
+/// checked against games of the official engine
+#[derive(Debug, Clone)]
+pub struct ScatterHazard {
+    seed: u64,
+    current_turn: u16,
+    ready: bool,
+    order: Vec<Position>,
+    next: usize,
+}
+
+const SCATTER_START_TURN: u16 = 1;
+const SCATTER_EVERY_N_TURNS: u16 = 2;
+
+impl ScatterHazard {
+    /// Construct an unitialized scatter hazard algorithm that shuffles with the given seed
+    pub fn new(seed: u64) -> Self {
+        ScatterHazard {
+            seed,
+            current_turn: 0,
+            ready: false,
+            order: Vec::new(),
+            next: 0,
+        }
+    }
+}
+
+impl ForwardOnlyHazardAlgorithm<Position> for ScatterHazard {
+    /// call this once with the current game, the hazards already on the board
+    /// keep their place and the remaining cells are shuffled with the seed
+    fn observe(
+        &mut self,
+        game: &Game,
+    ) -> Result<Box<dyn Iterator<Item = Position>>, Box<dyn Error>> {
+        if self.is_ready_for_inc() {
+            return Err("already ready for inc".into());
+        }
+        let mut order: Vec<Position> = Vec::new();
+        for pos in &game.board.hazards {
+            if !order.contains(pos) {
+                order.push(*pos);
+            }
+        }
+        let mut shuffled: Vec<Position> = (0..game.board.height as i32)
+            .flat_map(|y| (0..game.board.width as i32).map(move |x| Position { x, y }))
+            .collect();
+        shuffled.shuffle(&mut SmallRng::seed_from_u64(self.seed));
+        let remaining: Vec<Position> = shuffled
+            .into_iter()
+            .filter(|pos| !order.contains(pos))
+            .collect();
+
+        self.next = order.len();
+        order.extend(remaining);
+        self.order = order;
+        self.current_turn = game.turn as u16;
+        self.ready = true;
+        Ok(Box::new(game.board.hazards.clone().into_iter()))
+    }
+
+    fn is_ready_for_inc(&self) -> bool {
+        self.ready
+    }
+
+    fn current_turn(&self) -> usize {
+        self.current_turn as usize
+    }
+
+    fn inc_turn(&mut self) -> Box<dyn Iterator<Item = Position>> {
+        self.current_turn += 1;
+        if self.current_turn < SCATTER_START_TURN
+            || !(self.current_turn - SCATTER_START_TURN).is_multiple_of(SCATTER_EVERY_N_TURNS)
+        {
+            return Box::new(std::iter::empty());
+        }
+        let result = self.order.get(self.next).copied();
+        self.next += result.is_some() as usize;
+        Box::new(result.into_iter())
+    }
+}
+
+/// Expanding box hazard algorithm (`hz_expand_box`), a single cell turns into a hazard on
+/// turn 1 and the box around it grows by one cell in every direction every 20 turns. The
+/// center is drawn from the seed when it can't be told from the observed hazards. The schedule
+/// hasn't been checked against games of the official engine
+#[derive(Debug, Copy, Clone)]
+pub struct ExpandBoxHazard {
+    seed: u64,
+    current_turn: u16,
+    ready: bool,
+    width: i32,
+    height: i32,
+    center: Position,
+}
+
+const EXPAND_BOX_START_TURN: u16 = 1;
+const EXPAND_BOX_EVERY_N_TURNS: u16 = 20;
+
+impl ExpandBoxHazard {
+    /// Construct an unitialized expanding box hazard algorithm that picks its center with the given seed
+    pub fn new(seed: u64) -> Self {
+        ExpandBoxHazard {
+            seed,
+            current_turn: 0,
+            ready: false,
+            width: 0,
+            height: 0,
+            center: Position { x: 0, y: 0 },
+        }
+    }
+
+    /// the radius of the box on the given turn, None before the box appears
+    fn radius(turn: u16) -> Option<i32> {
+        turn.checked_sub(EXPAND_BOX_START_TURN)
+            .map(|turns| (turns / EXPAND_BOX_EVERY_N_TURNS) as i32)
+    }
+
+    fn distance(center: Position, pos: Position) -> i32 {
+        (pos.x - center.x).abs().max((pos.y - center.y).abs())
+    }
+
+    /// every cell of the board at most `radius` cells away from `center`
+    fn cells_within(&self, center: Position, radius: i32) -> impl Iterator<Item = Position> {
+        let (width, height) = (self.width, self.height);
+        (0..height)
+            .flat_map(move |y| (0..width).map(move |x| Position { x, y }))
+            .filter(move |pos| Self::distance(center, *pos) <= radius)
+    }
+}
+
+impl ForwardOnlyHazardAlgorithm<Position> for ExpandBoxHazard {
+    /// call this once with the current game. Before turn 1 the center is drawn
+    /// from the seed, afterwards it is one of the centers that produce the observed
+    /// box (there can be several once the box touches a wall). Errors if no center
+    /// produces the observed hazards
+    fn observe(
+        &mut self,
+        game: &Game,
+    ) -> Result<Box<dyn Iterator<Item = Position>>, Box<dyn Error>> {
+        if self.is_ready_for_inc() {
+            return Err("already ready for inc".into());
+        }
+        self.width = game.board.width as i32;
+        self.height = game.board.height as i32;
+        self.current_turn = game.turn as u16;
+        if self.width < 3 || self.height < 3 {
+            return Err("board is too small for an expanding box".into());
+        }
+        let mut rng = SmallRng::seed_from_u64(self.seed);
+        let centers: Vec<Position> = (1..self.height - 1)
+            .flat_map(|y| (1..self.width - 1).map(move |x| Position { x, y }))
+            .collect();
+        let centers: Vec<Position> = match Self::radius(self.current_turn) {
+            None => centers,
+            Some(radius) => centers
+                .into_iter()
+                .filter(|center| {
+                    let cells: Vec<_> = self.cells_within(*center, radius).collect();
+                    cells.len() == game.board.hazards.len()
+                        && cells.iter().all(|pos| game.board.hazards.contains(pos))
+                })
+                .collect(),
+        };
+        self.center = *centers
+            .choose(&mut rng)
+            .ok_or("hazards don't form an expanding box")?;
+        self.ready = true;
+        Ok(Box::new(game.board.hazards.clone().into_iter()))
+    }
+
+    fn is_ready_for_inc(&self) -> bool {
+        self.ready
+    }
+
+    fn current_turn(&self) -> usize {
+        self.current_turn as usize
+    }
+
+    fn inc_turn(&mut self) -> Box<dyn Iterator<Item = Position>> {
+        self.current_turn += 1;
+        match Self::radius(self.current_turn) {
+            Some(radius)
+                if (self.current_turn - EXPAND_BOX_START_TURN)
+                    .is_multiple_of(EXPAND_BOX_EVERY_N_TURNS) =>
+            {
+                let center = self.center;
+                Box::new(
+                    self.cells_within(center, radius)
+                        .filter(move |pos| Self::distance(center, *pos) == radius),
+                )
+            }
+            _ => Box::new(std::iter::empty()),
+        }
+    }
+}
+
+/// The hazard maps whose hazards never change over the course of a game. The layouts are
+/// approximations that haven't been checked against games of the official engine, they only
+/// lay out boards that start without their hazards
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum StaticLayout {
+    /// 3x3 islands separated by hazard channels, with a bridge in the middle of every channel
+    IslandsBridges,
+    /// square rings around the center of the board, every other ring is a hazard
+    Rings,
+    /// a hazard on every cell with both an odd x and an odd y
+    Columns,
+}
+
+impl StaticLayout {
+    /// is this position a hazard in the layout, independent of the board size for
+    /// everything but the rings which are centered on the board
+    pub fn is_hazard(&self, pos: &Position, width: i32, height: i32) -> bool {
+        match self {
+            StaticLayout::IslandsBridges => {
+                let (x, y) = (pos.x % 4, pos.y % 4);
+                let channel = x == 3 || y == 3;
+                let bridge = (x == 3 && y == 1) || (y == 3 && x == 1);
+                channel && !bridge
+            }
+            StaticLayout::Rings => {
+                let ring = (pos.x - width / 2).abs().max((pos.y - height / 2).abs());
+                ring > 0 && ring % 2 == 0
+            }
+            StaticLayout::Columns => pos.x % 2 == 1 && pos.y % 2 == 1,
+        }
+    }
+
+    /// every hazard of the layout on a board of the given size
+    pub fn hazards(self, width: i32, height: i32) -> impl Iterator<Item = Position> {
+        (0..height)
+            .flat_map(move |y| (0..width).map(move |x| Position { x, y }))
+            .filter(move |pos| self.is_hazard(pos, width, height))
+    }
+}
+
+/// Hazard algorithm for the maps in [StaticLayout], all hazards are placed when observing
+/// the game and winding forward never adds any. A game that already has hazards keeps them as
+/// its layout
+#[derive(Debug, Copy, Clone)]
+pub struct StaticHazard {
+    layout: StaticLayout,
+    current_turn: u16,
+    ready: bool,
+}
+
+impl StaticHazard {
+    /// Construct an unitialized hazard algorithm for the given layout
+    pub fn new(layout: StaticLayout) -> Self {
+        StaticHazard {
+            layout,
+            current_turn: 0,
+            ready: false,
+        }
+    }
+}
+
+impl ForwardOnlyHazardAlgorithm<Position> for StaticHazard {
+    /// call this once with the current game, the returned hazards are the hazards
+    /// of the game, or the layout for the size of the board if it has none
+    fn observe(
+        &mut self,
+        game: &Game,
+    ) -> Result<Box<dyn Iterator<Item = Position>>, Box<dyn Error>> {
+        if self.is_ready_for_inc() {
+            return Err("already ready for inc".into());
+        }
+        self.current_turn = game.turn as u16;
+        self.ready = true;
+        if !game.board.hazards.is_empty() {
+            return Ok(Box::new(game.board.hazards.clone().into_iter()));
+        }
+        Ok(Box::new(self.layout.hazards(
+            game.board.width as i32,
+            game.board.height as i32,
+        )))
+    }
+
+    fn is_ready_for_inc(&self) -> bool {
+        self.ready
+    }
+
+    fn current_turn(&self) -> usize {
+        self.current_turn as usize
+    }
+
+    fn inc_turn(&mut self) -> Box<dyn Iterator<Item = Position>> {
+        self.current_turn += 1;
+        Box::new(std::iter::empty())
+    }
+}
+
+/// Sinkholes hazard algorithm (`sinkholes`), a sinkhole opens in the center of the board on
+/// turn 1 and its radius grows by one every 10 turns until it reaches 4 cells. The schedule and
+/// shape haven't been checked against games of the official engine, so the hazards of an
+/// observed game are kept as they are and only the growth after it is forecast
+#[derive(Debug, Copy, Clone)]
+pub struct SinkholesHazard {
+    current_turn: u16,
+    ready: bool,
+    width: i32,
+    height: i32,
+}
+
+const SINKHOLES_START_TURN: u16 = 1;
+const SINKHOLES_EVERY_N_TURNS: u16 = 10;
+const SINKHOLES_MAX_RADIUS: i32 = 4;
+
+impl SinkholesHazard {
+    /// Construct an unitialized sinkholes hazard algorithm
+    pub fn new() -> Self {
+        SinkholesHazard {
+            current_turn: 0,
+            ready: false,
+            width: 0,
+            height: 0,
+        }
+    }
+
+    /// the radius of the sinkhole on the given turn, None before it opens
+    fn radius(turn: u16) -> Option<i32> {
+        turn.checked_sub(SINKHOLES_START_TURN)
+            .map(|turns| ((turns / SINKHOLES_EVERY_N_TURNS) as i32).min(SINKHOLES_MAX_RADIUS))
+    }
+
+    /// every cell of the sinkhole when it has the given radius
+    fn cells_within(&self, radius: i32) -> impl Iterator<Item = Position> {
+        let (width, height) = (self.width, self.height);
+        let center = Position {
+            x: width / 2,
+            y: height / 2,
+        };
+        (0..height)
+            .flat_map(move |y| (0..width).map(move |x| Position { x, y }))
+            .filter(move |pos| {
+                let (dx, dy) = (pos.x - center.x, pos.y - center.y);
+                dx * dx + dy * dy <= radius * radius
+            })
+    }
+}
+
+impl Default for SinkholesHazard {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ForwardOnlyHazardAlgorithm<Position> for SinkholesHazard {
+    /// call this once with the current game, the returned hazards are the hazards
+    /// of the game, or the sinkhole for the turn of the game if it has none
+    fn observe(
+        &mut self,
+        game: &Game,
+    ) -> Result<Box<dyn Iterator<Item = Position>>, Box<dyn Error>> {
+        if self.is_ready_for_inc() {
+            return Err("already ready for inc".into());
+        }
+        self.width = game.board.width as i32;
+        self.height = game.board.height as i32;
+        self.current_turn = game.turn as u16;
+        self.ready = true;
+        if !game.board.hazards.is_empty() {
+            return Ok(Box::new(game.board.hazards.clone().into_iter()));
+        }
+        match Self::radius(self.current_turn) {
+            Some(radius) => Ok(Box::new(self.cells_within(radius))),
+            None => Ok(Box::new(std::iter::empty())),
+        }
+    }
+
+    fn is_ready_for_inc(&self) -> bool {
+        self.ready
+    }
+
+    fn current_turn(&self) -> usize {
+        self.current_turn as usize
+    }
+
+    fn inc_turn(&mut self) -> Box<dyn Iterator<Item = Position>> {
+        let before = Self::radius(self.current_turn);
+        self.current_turn += 1;
+        let this = *self;
+        match (before, Self::radius(self.current_turn)) {
+            (None, Some(radius)) => Box::new(this.cells_within(radius)),
+            (Some(before), Some(radius)) if before != radius => Box::new(
+                this.cells_within(radius)
+                    .filter(move |pos| !this.cells_within(before).any(|p| p == *pos)),
+            ),
+            _ => Box::new(std::iter::empty()),
+        }
+    }
+}
+
+/// Registry of the hazard maps that can be named by `Settings.hazard_map`, or by
+/// `NestedGame.map` for the maps that come with their own hazards
+#[derive(Debug, Clone)]
+pub enum HazardMap {
+    /// `hz_spiral`
+    Spiral(SpiralHazard),
+    /// `hz_scatter`
+    Scatter(ScatterHazard),
+    /// `hz_expand_box`
+    ExpandBox(ExpandBoxHazard),
+    /// `hz_islands_bridges`, `hz_rings` and `hz_columns`
+    Static(StaticHazard),
+    /// `royale`
+    Royale(RoyaleHazard),
+    /// `sinkholes`
+    Sinkholes(SinkholesHazard),
+}
+
+impl HazardMap {
+    /// the names of every hazard map in the registry
+    pub const NAMES: [&'static str; 8] = [
+        "hz_spiral",
+        "hz_scatter",
+        "hz_islands_bridges",
+        "hz_rings",
+        "hz_columns",
+        "hz_expand_box",
+        "royale",
+        "sinkholes",
+    ];
+
+    /// Construct the unitialized hazard algorithm for the named map, randomized
+    /// maps draw from `seed`. Returns None for names that aren't in the registry
+    pub fn from_name(name: &str, seed: u64) -> Option<Self> {
+        Some(match name {
+            "hz_spiral" => HazardMap::Spiral(SpiralHazard::with_seed(seed)),
+            "hz_scatter" => HazardMap::Scatter(ScatterHazard::new(seed)),
+            "hz_expand_box" => HazardMap::ExpandBox(ExpandBoxHazard::new(seed)),
+            "hz_islands_bridges" => {
+                HazardMap::Static(StaticHazard::new(StaticLayout::IslandsBridges))
+            }
+            "hz_rings" => HazardMap::Static(StaticHazard::new(StaticLayout::Rings)),
+            "hz_columns" => HazardMap::Static(StaticHazard::new(StaticLayout::Columns)),
+            "royale" => HazardMap::Royale(RoyaleHazard::new(seed)),
+            "sinkholes" => HazardMap::Sinkholes(SinkholesHazard::new()),
+            _ => return None,
+        })
+    }
+
+    /// Construct the unitialized hazard algorithm for the map named in the
+    /// settings of the game, or else for the map of the game
+    pub fn from_game(game: &Game, seed: u64) -> Option<Self> {
+        let hazard_map = game
+            .game
+            .ruleset
+            .settings
+            .as_ref()
+            .and_then(|settings| settings.hazard_map.as_deref())
+            .filter(|name| !name.is_empty());
+        Self::from_name(hazard_map.or(game.game.map.as_deref())?, seed)
+    }
+}
+
+impl ForwardOnlyHazardAlgorithm<Position> for HazardMap {
+    /// see the notes on the algorithm of the map
+    fn observe(
+        &mut self,
+        game: &Game,
+    ) -> Result<Box<dyn Iterator<Item = Position>>, Box<dyn Error>> {
+        match self {
+            HazardMap::Spiral(alg) => alg.observe(game),
+            HazardMap::Scatter(alg) => alg.observe(game),
+            HazardMap::ExpandBox(alg) => alg.observe(game),
+            HazardMap::Static(alg) => alg.observe(game),
+            HazardMap::Royale(alg) => alg.observe(game),
+            HazardMap::Sinkholes(alg) => alg.observe(game),
+        }
+    }
+
+    fn is_ready_for_inc(&self) -> bool {
+        match self {
+            HazardMap::Spiral(alg) => alg.is_ready_for_inc(),
+            HazardMap::Scatter(alg) => alg.is_ready_for_inc(),
+            HazardMap::ExpandBox(alg) => alg.is_ready_for_inc(),
+            HazardMap::Static(alg) => alg.is_ready_for_inc(),
+            HazardMap::Royale(alg) => alg.is_ready_for_inc(),
+            HazardMap::Sinkholes(alg) => alg.is_ready_for_inc(),
+        }
+    }
+
+    fn current_turn(&self) -> usize {
+        match self {
+            HazardMap::Spiral(alg) => alg.current_turn(),
+            HazardMap::Scatter(alg) => alg.current_turn(),
+            HazardMap::ExpandBox(alg) => alg.current_turn(),
+            HazardMap::Static(alg) => alg.current_turn(),
+            HazardMap::Royale(alg) => alg.current_turn(),
+            HazardMap::Sinkholes(alg) => alg.current_turn(),
+        }
+    }
+
+    fn inc_turn(&mut self) -> Box<dyn Iterator<Item = Position>> {
+        match self {
+            HazardMap::Spiral(alg) => alg.inc_turn(),
+            HazardMap::Scatter(alg) => alg.inc_turn(),
+            HazardMap::ExpandBox(alg) => alg.inc_turn(),
+            HazardMap::Static(alg) => alg.inc_turn(),
+            HazardMap::Royale(alg) => alg.inc_turn(),
+            HazardMap::Sinkholes(alg) => alg.inc_turn(),
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use std::{collections::HashSet, fs, iter::FromIterator, path};
 
     use crate::{
+        game_fixture,
         types::Move,
         wire_representation::{Game, Position},
     };
 
-    use super::{ForwardOnlyHazardAlgorithm, SpiralHazard};
+    use super::{
+        ExpandBoxHazard, ForwardOnlyHazardAlgorithm, HazardMap, RoyaleHazard, ScatterHazard,
+        SinkholesHazard, SpiralHazard, StaticLayout,
+    };
 
     #[test]
     fn test_next_perfect_square() {
@@ -245,6 +925,7 @@ mod tests {
             current_turn: 3,
             next_hazard_cell: Position { x: 0, y: 1 },
             direction: Move::Right,
+            seed: None,
         };
         assert!(s.inc_turn().next().is_none());
         assert!(s.inc_turn().next().is_none());
@@ -349,4 +1030,254 @@ mod tests {
             }
         }
     }
+
+    #[test]
+    fn test_royale_observes_safe_area() {
+        let game = game_fixture(include_str!("../../fixtures/another_crash.json"));
+        let mut royale = RoyaleHazard::new(0);
+        assert!(!royale.is_ready_for_inc());
+        let observed: HashSet<_> = royale.observe(&game).unwrap().collect();
+        assert!(royale.is_ready_for_inc());
+        assert_eq!(royale.current_turn(), 50);
+        assert_eq!(observed, HashSet::from_iter(royale.hazards()));
+        assert!(royale.observe(&game).is_err());
+
+        let no_royale = game_fixture(include_str!("../../fixtures/wrapped_panic.json"));
+        assert!(RoyaleHazard::new(0).observe(&no_royale).is_err());
+    }
+
+    #[test]
+    fn test_royale_shrinks_one_side_every_n_turns() {
+        let game = game_fixture(include_str!("../../fixtures/another_crash.json"));
+        let mut royale = RoyaleHazard::new(7);
+        let mut maintained_hazards: HashSet<_> = royale.observe(&game).unwrap().collect();
+        for turn in 51..=150 {
+            let new_hazards: Vec<_> = royale.inc_turn().collect();
+            if turn % 25 == 0 {
+                // one whole row or column of the safe area turns into hazards
+                assert!(!new_hazards.is_empty());
+                let xs: HashSet<_> = new_hazards.iter().map(|p| p.x).collect();
+                let ys: HashSet<_> = new_hazards.iter().map(|p| p.y).collect();
+                assert!(xs.len() == 1 || ys.len() == 1);
+            } else {
+                assert!(new_hazards.is_empty());
+            }
+            assert!(new_hazards.iter().all(|p| !maintained_hazards.contains(p)));
+            maintained_hazards.extend(new_hazards);
+        }
+        assert_eq!(maintained_hazards, HashSet::from_iter(royale.hazards()));
+    }
+
+    #[test]
+    fn test_royale_is_seedable() {
+        let game = game_fixture(include_str!("../../fixtures/another_crash.json"));
+        let shrink = |seed: u64| {
+            let mut royale = RoyaleHazard::new(seed);
+            let _ = royale.observe(&game).unwrap();
+            for _ in 0..200 {
+                royale.advance();
+            }
+            let mut hazards: Vec<_> = royale.hazards().collect();
+            hazards.sort_by_key(|p| (p.x, p.y));
+            hazards
+        };
+        assert_eq!(shrink(42), shrink(42));
+        assert!((0..10).any(|seed| shrink(seed) != shrink(42)));
+    }
+
+    /// winds `alg` forward from the first frame that it can observe, and checks
+    /// that the maintained hazards match the hazards of every later frame
+    fn assert_matches_frames<A: ForwardOnlyHazardAlgorithm<Position>>(
+        mut alg: A,
+        frames: impl Iterator<Item = Game>,
+    ) {
+        let mut maintained_hazards = HashSet::new();
+        let mut checked = 0;
+        for game in frames {
+            if !alg.is_ready_for_inc() {
+                maintained_hazards.extend(alg.observe(&game).unwrap());
+            } else {
+                while alg.current_turn() < game.turn as usize {
+                    maintained_hazards.extend(alg.inc_turn());
+                }
+                let hazards_set = HashSet::from_iter(game.board.hazards);
+                assert_eq!(alg.current_turn(), game.turn as usize);
+                assert_eq!(hazards_set, maintained_hazards, "turn {}", game.turn);
+                checked += 1;
+            }
+        }
+        assert!(checked > 0);
+    }
+
+    /// frames of a game on the empty 11x11 fixture, with the hazards of every turn
+    /// given by `hazards_on`
+    fn frames(
+        turns: std::ops::RangeInclusive<i32>,
+        hazards_on: impl Fn(i32) -> Vec<Position>,
+    ) -> impl Iterator<Item = Game> {
+        let start = game_fixture(include_str!(
+            "../../fixtures/e80b70e7-a916-40ca-82d2-ad76e074efe1_0.json"
+        ));
+        turns.map(move |turn| {
+            let mut game = start.clone();
+            game.turn = turn;
+            game.board.hazards = hazards_on(turn);
+            game
+        })
+    }
+
+    #[test]
+    fn test_static_maps_keep_the_hazards_of_the_game() {
+        let self_file = path::Path::new(env!("CARGO_MANIFEST_DIR"));
+        let file_bytes = fs::read(self_file.join("fixtures/debug_wrapped/debug_game_100.json"));
+        let recorded = serde_json::from_slice::<Game>(&file_bytes.unwrap()).unwrap();
+        assert!(!recorded.board.hazards.is_empty());
+        for name in ["hz_islands_bridges", "hz_rings", "hz_columns"].iter() {
+            let hazards = recorded.board.hazards.clone();
+            let frames = frames(100..=150, move |_| hazards.clone());
+            assert_matches_frames(HazardMap::from_name(name, 0).unwrap(), frames);
+        }
+    }
+
+    #[test]
+    fn test_registry_knows_every_hazard_map() {
+        for name in HazardMap::NAMES.iter() {
+            assert!(HazardMap::from_name(name, 0).is_some(), "{}", name);
+        }
+        assert!(HazardMap::from_name("hz_unknown", 0).is_none());
+
+        let game = game_fixture(include_str!("../../fixtures/hazard_map_settings.json"));
+        assert!(matches!(
+            HazardMap::from_game(&game, 0),
+            Some(HazardMap::Spiral(_))
+        ));
+        let game = game_fixture(include_str!("../../fixtures/empty_str_hazard.json"));
+        assert!(HazardMap::from_game(&game, 0).is_none());
+    }
+
+    #[test]
+    fn test_registry_spiral_matches_frames_from_game() {
+        let self_file = path::Path::new(env!("CARGO_MANIFEST_DIR"));
+        let frames = (1..=193).map(|i| {
+            let file_name = self_file.join(format!("fixtures/debug_wrapped/debug_game_{}.json", i));
+            let file_bytes = fs::read(file_name).unwrap();
+            serde_json::from_slice::<Game>(&file_bytes).unwrap()
+        });
+        // the seeded spiral draws its own seed cell when observed before turn 3
+        let frames = frames.skip_while(|game| game.board.hazards.is_empty());
+        assert_matches_frames(HazardMap::from_name("hz_spiral", 0).unwrap(), frames);
+    }
+
+    #[test]
+    fn test_static_layouts() {
+        let count = |layout: StaticLayout| layout.hazards(11, 11).count();
+        // 5x5 odd cells
+        assert_eq!(count(StaticLayout::Columns), 25);
+        assert!(StaticLayout::Columns
+            .hazards(11, 11)
+            .all(|p| p.x % 2 == 1 && p.y % 2 == 1));
+        // rings 2 and 4 cells away from the center
+        assert_eq!(count(StaticLayout::Rings), 16 + 32);
+        assert!(!StaticLayout::Rings.is_hazard(&Position { x: 5, y: 5 }, 11, 11));
+        assert!(StaticLayout::Rings.is_hazard(&Position { x: 3, y: 7 }, 11, 11));
+        // two channels each way, without their three bridges each
+        assert_eq!(count(StaticLayout::IslandsBridges), 4 * 11 - 4 - 4 * 3);
+        assert!(!StaticLayout::IslandsBridges.is_hazard(&Position { x: 3, y: 5 }, 11, 11));
+        assert!(StaticLayout::IslandsBridges.is_hazard(&Position { x: 3, y: 3 }, 11, 11));
+
+        // a game without hazards gets the layout
+        let start = frames(0..=0, |_| vec![]).next().unwrap();
+        let mut alg = HazardMap::from_name("hz_columns", 0).unwrap();
+        assert_eq!(alg.observe(&start).unwrap().count(), 25);
+    }
+
+    /// every hazard of the scatter map with the given seed from turn 0 to `turns`
+    fn scatter_hazards(seed: u64, turns: i32) -> Vec<Vec<Position>> {
+        let start = frames(0..=0, |_| vec![]).next().unwrap();
+        let mut scatter = ScatterHazard::new(seed);
+        let mut hazards: Vec<Position> = scatter.observe(&start).unwrap().collect();
+        let mut result = vec![hazards.clone()];
+        for _ in 1..=turns {
+            hazards.extend(scatter.inc_turn());
+            result.push(hazards.clone());
+        }
+        result
+    }
+
+    #[test]
+    fn test_scatter_is_seedable() {
+        assert_eq!(scatter_hazards(42, 100), scatter_hazards(42, 100));
+        assert!((0..10).any(|seed| scatter_hazards(seed, 100) != scatter_hazards(42, 100)));
+    }
+
+    #[test]
+    fn test_expand_box_rejects_other_shapes() {
+        // two cells far apart can't be a box on turn 41
+        let mut game = frames(41..=41, |_| vec![]).next().unwrap();
+        game.board.hazards = vec![Position { x: 1, y: 1 }, Position { x: 9, y: 9 }];
+        assert!(ExpandBoxHazard::new(0).observe(&game).is_err());
+    }
+
+    #[test]
+    fn test_expand_box_is_seedable() {
+        let center = |seed: u64| {
+            let mut alg = HazardMap::from_name("hz_expand_box", seed).unwrap();
+            let start = frames(0..=0, |_| vec![]).next().unwrap();
+            assert_eq!(alg.observe(&start).unwrap().count(), 0);
+            let first: Vec<_> = alg.inc_turn().collect();
+            assert_eq!(first.len(), 1);
+            first[0]
+        };
+        assert_eq!(center(42), center(42));
+        assert!((0..10).any(|seed| center(seed) != center(42)));
+        assert!((0..10).all(|seed| (1..10).contains(&center(seed).x)));
+    }
+
+    #[test]
+    fn test_seeded_spiral_draws_the_seed_cell() {
+        let spiral = |seed: u64| {
+            let start = frames(0..=0, |_| vec![]).next().unwrap();
+            let mut alg = SpiralHazard::with_seed(seed);
+            assert_eq!(alg.observe(&start).unwrap().count(), 0);
+            let mut hazards = vec![];
+            let mut result = vec![hazards.clone()];
+            for _ in 1..=100 {
+                hazards.extend(alg.inc_turn());
+                result.push(hazards.clone());
+            }
+            result
+        };
+        let hazards = spiral(11);
+        assert!(hazards[2].is_empty());
+        assert_eq!(hazards[3].len(), 1);
+        assert_eq!(hazards, spiral(11));
+        assert!((0..10).any(|seed| spiral(seed)[3] != hazards[3]));
+
+        // the unseeded spiral forecasts the same spiral once it saw the seed cell
+        let frames = frames(0..=100, |turn| hazards[turn as usize].clone());
+        assert_matches_frames(SpiralHazard::new(), frames);
+    }
+
+    #[test]
+    fn test_sinkholes_keep_the_hazards_of_the_game() {
+        let self_file = path::Path::new(env!("CARGO_MANIFEST_DIR"));
+        let file_bytes = fs::read(self_file.join("fixtures/debug_wrapped/debug_game_100.json"));
+        let recorded = serde_json::from_slice::<Game>(&file_bytes.unwrap()).unwrap();
+        let hazards = recorded.board.hazards.clone();
+        assert!(!hazards.is_empty());
+        let recorded_frames = frames(100..=150, move |_| hazards.clone());
+        assert_matches_frames(
+            HazardMap::from_name("sinkholes", 0).unwrap(),
+            recorded_frames,
+        );
+
+        // a game that starts without hazards opens the sinkhole on turn 1
+        let start = frames(0..=0, |_| vec![]).next().unwrap();
+        let mut alg = SinkholesHazard::new();
+        assert_eq!(alg.observe(&start).unwrap().count(), 0);
+        assert_eq!(
+            alg.inc_turn().collect::<Vec<_>>(),
+            vec![Position { x: 5, y: 5 }]
+        );
+    }
 }
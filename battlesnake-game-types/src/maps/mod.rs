@@ -0,0 +1,508 @@
+//! The official maps a game can be played on, as named by `NestedGame.map`. A map decides
+//! the board a game starts on (its size, walls, where snakes spawn and the initial food)
+//! and how food and hazards appear on every following turn.
+
+use std::error::Error;
+
+use rand::{
+    seq::{IndexedRandom, IteratorRandom, SliceRandom},
+    Rng,
+};
+
+use crate::{
+    compact_representation::{DEFAULT_FOOD_SPAWN_CHANCE, DEFAULT_MINIMUM_FOOD},
+    hazard_algorithms::HazardMap,
+    types::{
+        EmptyCellGettableGame, FoodQueryableGame, FoodSettableGame, StandardFoodPlaceableGame,
+    },
+    wire_representation::{BattleSnake, Board, Game, Position, Settings},
+};
+
+/// A map a game can be played on
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum GameMap {
+    /// `standard`, snakes spawn on fixed points and food spawns following the standard rules
+    Standard,
+    /// `empty`, snakes spawn anywhere and no food is ever placed
+    Empty,
+    /// `arcade_maze`, a 19x21 maze of walls where food only spawns on fixed points
+    ArcadeMaze,
+    /// `royale`, the standard map with a safe area that shrinks
+    Royale,
+    /// `sinkholes`, the standard map with a sinkhole growing in the center
+    Sinkholes,
+    /// one of the `hz_*` maps of the [HazardMap] registry, played like the standard map
+    Hazards(&'static str),
+}
+
+/// The walls of the arcade maze, top row first
+const ARCADE_MAZE: [&str; 21] = [
+    "#.###############.#",
+    "#........#........#",
+    "#.##.###.#.###.##.#",
+    "#.................#",
+    "#.##.#.#####.#.##.#",
+    "#....#...#...#....#",
+    "#..#.###.#.###.#..#",
+    "#..#.#.......#.#..#",
+    "####.#.#.#.#.#.####",
+    ".......#...#.......",
+    "####.#.#.#.#.#.####",
+    "#..#.#.......#.#..#",
+    "#..#.#.#####.#.#..#",
+    "#........#........#",
+    "#.##.###.#.###.##.#",
+    "#..#...........#..#",
+    "##.#.#.#####.#.#.##",
+    "#....#...#...#....#",
+    "#.######.#.######.#",
+    "#.................#",
+    "#.###############.#",
+];
+
+const ARCADE_MAZE_SPAWN_POINTS: [(i32, i32); 8] = [
+    (4, 1),
+    (14, 1),
+    (4, 17),
+    (14, 17),
+    (1, 7),
+    (17, 7),
+    (1, 13),
+    (17, 13),
+];
+
+const ARCADE_MAZE_FOOD_POINTS: [(i32, i32); 9] = [
+    (1, 1),
+    (17, 1),
+    (1, 19),
+    (17, 19),
+    (3, 11),
+    (9, 11),
+    (15, 11),
+    (9, 5),
+    (9, 17),
+];
+
+fn positions(points: &[(i32, i32)]) -> Vec<Position> {
+    points.iter().map(|&(x, y)| Position { x, y }).collect()
+}
+
+impl GameMap {
+    /// The map with the given name, an empty name is the standard map. Returns None for
+    /// names that aren't known
+    pub fn from_name(name: &str) -> Option<Self> {
+        Some(match name {
+            "" | "standard" => GameMap::Standard,
+            "empty" => GameMap::Empty,
+            "arcade_maze" => GameMap::ArcadeMaze,
+            "royale" => GameMap::Royale,
+            "sinkholes" => GameMap::Sinkholes,
+            name => GameMap::Hazards(HazardMap::NAMES.iter().find(|known| **known == name)?),
+        })
+    }
+
+    /// The map of the game, games that don't name a map are played on the standard map
+    pub fn from_game(game: &Game) -> Option<Self> {
+        Self::from_name(game.game.map.as_deref().unwrap_or(""))
+    }
+
+    /// The name of the map as used in `NestedGame.map`
+    pub fn name(&self) -> &'static str {
+        match self {
+            GameMap::Standard => "standard",
+            GameMap::Empty => "empty",
+            GameMap::ArcadeMaze => "arcade_maze",
+            GameMap::Royale => "royale",
+            GameMap::Sinkholes => "sinkholes",
+            GameMap::Hazards(name) => name,
+        }
+    }
+
+    /// The width and height of maps that can only be played on a single board size
+    pub fn dimensions(&self) -> Option<(u32, u32)> {
+        match self {
+            GameMap::ArcadeMaze => Some((19, 21)),
+            _ => None,
+        }
+    }
+
+    /// The hazards the board starts with, which never change over the course of a game
+    pub fn walls(&self, width: u32, height: u32) -> Vec<Position> {
+        match self {
+            GameMap::ArcadeMaze => ARCADE_MAZE
+                .iter()
+                .enumerate()
+                .flat_map(|(row, line)| {
+                    line.chars()
+                        .enumerate()
+                        .filter(|(_, c)| *c == '#')
+                        .map(move |(x, _)| Position {
+                            x: x as i32,
+                            y: (ARCADE_MAZE.len() - 1 - row) as i32,
+                        })
+                })
+                .filter(|pos| pos.x < width as i32 && pos.y < height as i32)
+                .collect(),
+            _ => vec![],
+        }
+    }
+
+    /// The points snakes can spawn on, corners first for the standard maps. The empty map
+    /// lets snakes spawn on any cell
+    pub fn spawn_points(&self, width: u32, height: u32) -> Vec<Position> {
+        let (width, height) = (width as i32, height as i32);
+        match self {
+            GameMap::ArcadeMaze => positions(&ARCADE_MAZE_SPAWN_POINTS),
+            GameMap::Empty => (0..height)
+                .flat_map(|y| (0..width).map(move |x| Position { x, y }))
+                .collect(),
+            _ => {
+                let (min, max_x, max_y) = (1, width - 2, height - 2);
+                let (mid_x, mid_y) = ((width - 1) / 2, (height - 1) / 2);
+                positions(&[
+                    (min, min),
+                    (min, max_y),
+                    (max_x, min),
+                    (max_x, max_y),
+                    (min, mid_y),
+                    (mid_x, min),
+                    (mid_x, max_y),
+                    (max_x, mid_y),
+                ])
+            }
+        }
+    }
+
+    /// The only points food can spawn on, None if food can spawn on any empty cell
+    pub fn food_spawn_points(&self) -> Option<Vec<Position>> {
+        match self {
+            GameMap::ArcadeMaze => Some(positions(&ARCADE_MAZE_FOOD_POINTS)),
+            _ => None,
+        }
+    }
+
+    /// The unitialized hazard algorithm of maps whose hazards change every turn,
+    /// randomized hazards draw from `seed`
+    pub fn hazards(&self, seed: u64) -> Option<HazardMap> {
+        match self {
+            GameMap::Royale | GameMap::Sinkholes | GameMap::Hazards(_) => {
+                HazardMap::from_name(self.name(), seed)
+            }
+            _ => None,
+        }
+    }
+
+    /// Build the board a game on this map starts with. Every segment of the snakes is
+    /// moved onto the spawn point of the snake, the rest of the snakes is kept.
+    /// Errors if the map can't be played on a board of this size or with this many snakes
+    pub fn initial_board(
+        &self,
+        width: u32,
+        height: u32,
+        mut snakes: Vec<BattleSnake>,
+        rng: &mut impl Rng,
+    ) -> Result<Board, Box<dyn Error>> {
+        if let Some(dimensions) = self.dimensions() {
+            if dimensions != (width, height) {
+                return Err(format!(
+                    "{} is played on {}x{} boards",
+                    self.name(),
+                    dimensions.0,
+                    dimensions.1
+                )
+                .into());
+            }
+        } else if width < 7 || height < 7 {
+            return Err("board is too small for the spawn points".into());
+        }
+
+        let mut spawn_points = self.spawn_points(width, height);
+        if snakes.len() > spawn_points.len() {
+            return Err(format!("{} snakes don't fit on {}", snakes.len(), self.name()).into());
+        }
+        match self {
+            GameMap::Standard | GameMap::Royale | GameMap::Sinkholes | GameMap::Hazards(_) => {
+                // corners and sides are shuffled separately, either may be filled first
+                let (corners, sides) = spawn_points.split_at_mut(4);
+                corners.shuffle(rng);
+                sides.shuffle(rng);
+                if rng.random() {
+                    spawn_points.rotate_left(4);
+                }
+            }
+            GameMap::Empty | GameMap::ArcadeMaze => spawn_points.shuffle(rng),
+        }
+        for (snake, spawn) in snakes.iter_mut().zip(spawn_points) {
+            snake.head = spawn;
+            snake.body.iter_mut().for_each(|pos| *pos = spawn);
+        }
+
+        let food = self.initial_food(width, height, &snakes, rng);
+        Ok(Board {
+            height,
+            width,
+            food,
+            snakes,
+            hazards: self.walls(width, height),
+        })
+    }
+
+    /// One food next to every snake and one in the center, or on the food spawn points
+    fn initial_food(
+        &self,
+        width: u32,
+        height: u32,
+        snakes: &[BattleSnake],
+        rng: &mut impl Rng,
+    ) -> Vec<Position> {
+        let center = Position {
+            x: (width as i32 - 1) / 2,
+            y: (height as i32 - 1) / 2,
+        };
+        let occupied = |pos: &Position, food: &[Position]| {
+            food.contains(pos) || snakes.iter().any(|snake| snake.body.contains(pos))
+        };
+        let mut food = vec![];
+        match self {
+            GameMap::Empty => {}
+            GameMap::ArcadeMaze => {
+                let points = positions(&ARCADE_MAZE_FOOD_POINTS);
+                if let Some(pos) = points.iter().filter(|p| !occupied(p, &food)).choose(rng) {
+                    food.push(*pos);
+                }
+            }
+            _ => {
+                for snake in snakes {
+                    let head = snake.head;
+                    let distance =
+                        |pos: &Position| (pos.x - center.x).abs() + (pos.y - center.y).abs();
+                    let candidates: Vec<Position> = [(-1, -1), (-1, 1), (1, -1), (1, 1)]
+                        .iter()
+                        .map(|&(x, y)| Position {
+                            x: head.x + x,
+                            y: head.y + y,
+                        })
+                        .filter(|pos| distance(pos) >= distance(&head) && *pos != center)
+                        .filter(|pos| !occupied(pos, &food))
+                        .collect();
+                    if let Some(pos) = candidates.choose(rng) {
+                        food.push(*pos);
+                    }
+                }
+                if !occupied(&center, &food) {
+                    food.push(center);
+                }
+            }
+        }
+        food
+    }
+
+    /// Place the food of a turn on `board`. Maps with food spawn points follow the standard
+    /// rules with the `minimumFood` and `foodSpawnChance` of `settings`, but only spawn food on
+    /// their points, the empty map never places food and all other maps place it like
+    /// [StandardFoodPlaceableGame] does
+    pub fn place_food<B>(&self, board: &mut B, settings: Option<&Settings>, rng: &mut impl Rng)
+    where
+        B: StandardFoodPlaceableGame + FoodSettableGame + FoodQueryableGame + EmptyCellGettableGame,
+    {
+        let points = match (self, self.food_spawn_points()) {
+            (GameMap::Empty, _) => return,
+            (_, None) => return board.place_food(rng),
+            (_, Some(points)) => points,
+        };
+
+        // Settings out of range are clamped instead of wrapping around
+        let food_spawn_chance = settings
+            .map(|settings| settings.food_spawn_chance.clamp(0, 100) as u8)
+            .unwrap_or(DEFAULT_FOOD_SPAWN_CHANCE);
+        let minimum_food = settings
+            .map(|settings| settings.minimum_food.clamp(0, u8::MAX as i32) as usize)
+            .unwrap_or(DEFAULT_MINIMUM_FOOD as usize);
+        let natives: Vec<_> = points
+            .into_iter()
+            .map(|pos| board.native_from_position(pos))
+            .collect();
+        let food_count = natives.iter().filter(|pos| board.is_food(pos)).count();
+        let food_to_add = if food_count < minimum_food {
+            minimum_food - food_count
+        } else {
+            usize::from(rng.random_range(0..100) < food_spawn_chance)
+        };
+        if food_to_add == 0 {
+            return;
+        }
+
+        let empty: Vec<_> = board
+            .get_empty_cells()
+            .filter(|pos| natives.contains(pos))
+            .collect();
+        for pos in empty.choose_multiple(rng, food_to_add) {
+            board.set_food(pos.clone());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use rand::{rngs::SmallRng, SeedableRng};
+
+    use crate::{
+        compact_representation::{dimensions::ArcadeMaze, WrappedCellBoard},
+        game_fixture,
+        hazard_algorithms::{ForwardOnlyHazardAlgorithm, HazardMap},
+        types::{build_snake_id_map, FoodGettableGame, HazardQueryableGame, PositionGettableGame},
+        wire_representation::{BattleSnake, Game},
+    };
+
+    use super::GameMap;
+
+    fn snakes(n: usize) -> Vec<BattleSnake> {
+        let game = game_fixture(include_str!("../../fixtures/4_snake_game.json"));
+        (0..n)
+            .map(|i| BattleSnake {
+                id: format!("snake_{}", i),
+                ..game.board.snakes[0].clone()
+            })
+            .collect()
+    }
+
+    fn with_board(map: GameMap, width: u32, height: u32, n: usize) -> Game {
+        let mut game = game_fixture(include_str!("../../fixtures/arcade_maze_map.json"));
+        let mut rng = SmallRng::seed_from_u64(1);
+        game.board = map
+            .initial_board(width, height, snakes(n), &mut rng)
+            .unwrap();
+        game.you = game.board.snakes[0].clone();
+        game.turn = 0;
+        game.game.map = Some(map.name().to_string());
+        game
+    }
+
+    #[test]
+    fn test_maps_by_name() {
+        for name in [
+            "standard",
+            "empty",
+            "arcade_maze",
+            "royale",
+            "sinkholes",
+            "hz_rings",
+        ]
+        .iter()
+        {
+            assert_eq!(GameMap::from_name(name).unwrap().name(), *name);
+        }
+        assert_eq!(GameMap::from_name(""), Some(GameMap::Standard));
+        assert_eq!(GameMap::from_name("unknown"), None);
+        assert_eq!(GameMap::from_name("hz_unknown"), None);
+
+        let game = game_fixture(include_str!("../../fixtures/arcade_maze_map.json"));
+        assert_eq!(GameMap::from_game(&game), Some(GameMap::ArcadeMaze));
+        let game = game_fixture(include_str!("../../fixtures/start_of_game.json"));
+        assert_eq!(GameMap::from_game(&game), Some(GameMap::Standard));
+    }
+
+    #[test]
+    fn test_arcade_maze_walls_match_game() {
+        let game = game_fixture(include_str!("../../fixtures/arcade_maze_map.json"));
+        let walls: HashSet<_> = GameMap::ArcadeMaze.walls(19, 21).into_iter().collect();
+        let hazards: HashSet<_> = game.board.hazards.iter().copied().collect();
+        assert_eq!(walls, hazards);
+
+        let points = GameMap::ArcadeMaze.spawn_points(19, 21);
+        let food_points = GameMap::ArcadeMaze.food_spawn_points().unwrap();
+        assert!(points
+            .iter()
+            .chain(&food_points)
+            .all(|p| !walls.contains(p)));
+        assert!(game.board.food.iter().all(|p| food_points.contains(p)));
+    }
+
+    #[test]
+    fn test_initial_board() {
+        let game = with_board(GameMap::Standard, 11, 11, 4);
+        let heads: HashSet<_> = game.board.snakes.iter().map(|s| s.head).collect();
+        assert_eq!(heads.len(), 4);
+        let points = GameMap::Standard.spawn_points(11, 11);
+        for snake in &game.board.snakes {
+            assert!(points.contains(&snake.head));
+            assert!(snake.body.iter().all(|p| *p == snake.head));
+        }
+        // one food for every snake and the center
+        assert_eq!(game.board.food.len(), 5);
+        assert!(game.board.hazards.is_empty());
+
+        let game = with_board(GameMap::ArcadeMaze, 19, 21, 8);
+        assert_eq!(
+            game.board.hazards.len(),
+            GameMap::ArcadeMaze.walls(19, 21).len()
+        );
+        assert_eq!(game.board.food.len(), 1);
+
+        assert!(with_board(GameMap::Empty, 7, 7, 2).board.food.is_empty());
+
+        let mut rng = SmallRng::seed_from_u64(0);
+        assert!(GameMap::ArcadeMaze
+            .initial_board(11, 11, snakes(2), &mut rng)
+            .is_err());
+        assert!(GameMap::Standard
+            .initial_board(11, 11, snakes(9), &mut rng)
+            .is_err());
+    }
+
+    #[test]
+    fn test_arcade_maze_food_spawns_on_points() {
+        let game = with_board(GameMap::ArcadeMaze, 19, 21, 4);
+        let settings = game.game.ruleset.settings.clone();
+        let id_map = build_snake_id_map(&game);
+        let start =
+            WrappedCellBoard::<u16, ArcadeMaze, { 19 * 21 }, 4>::convert_from_game(game, &id_map)
+                .unwrap();
+        let mut board = start;
+        let mut rng = SmallRng::seed_from_u64(3);
+        for _ in 0..100 {
+            GameMap::ArcadeMaze.place_food(&mut board, settings.as_ref(), &mut rng);
+        }
+        let food = board.get_all_food_as_positions();
+        assert!(food.len() > 1);
+        let points = GameMap::ArcadeMaze.food_spawn_points().unwrap();
+        assert!(food.iter().all(|p| points.contains(p)));
+        // the walls are still hazards
+        for wall in GameMap::ArcadeMaze.walls(19, 21) {
+            assert!(board.is_hazard(&board.native_from_position(wall)));
+        }
+
+        // negative settings are clamped to never spawning food, instead of wrapping around
+        let mut settings = settings.unwrap();
+        settings.food_spawn_chance = -1;
+        settings.minimum_food = -1;
+        let mut board = start;
+        for _ in 0..100 {
+            GameMap::ArcadeMaze.place_food(&mut board, Some(&settings), &mut rng);
+        }
+        assert_eq!(
+            board.get_all_food_as_positions(),
+            start.get_all_food_as_positions()
+        );
+    }
+
+    #[test]
+    fn test_map_hazards() {
+        assert!(GameMap::Standard.hazards(0).is_none());
+        assert!(matches!(
+            GameMap::Sinkholes.hazards(0),
+            Some(HazardMap::Sinkholes(_))
+        ));
+
+        // a seeded spiral places its seed cell on turn 3 on a game started from the map
+        let game = with_board(GameMap::Hazards("hz_spiral"), 11, 11, 4);
+        let mut hazards = GameMap::from_game(&game).unwrap().hazards(5).unwrap();
+        assert_eq!(hazards.observe(&game).unwrap().count(), 0);
+        assert!(hazards.is_ready_for_inc());
+        assert_eq!(hazards.inc_turn().count(), 0);
+        assert_eq!(hazards.inc_turn().count(), 0);
+        assert_eq!(hazards.inc_turn().count(), 1);
+    }
+}
@@ -284,6 +284,103 @@ pub trait SimulableGame<T: SimulatorInstruments, const N_SNAKES: usize>:
         S: Borrow<[Move]>;
 }
 
+/// a game that can report why it can't be simulated, instead of panicking like
+/// [`SimulableGame::simulate_with_moves`] does on a malformed board
+pub trait FallibleSimulableGame<T: SimulatorInstruments, const N_SNAKES: usize>:
+    SimulableGame<T, N_SNAKES>
+{
+    /// why a board can't be simulated
+    type SimulationError: std::error::Error;
+
+    /// like [`SimulableGame::simulate_with_moves`], but fails instead of panicking if the board
+    /// can't be simulated, e.g. because one of its snakes is inconsistent. Each resulting board
+    /// is an error instead if the moves left it inconsistent
+    #[allow(clippy::type_complexity)]
+    fn try_simulate_with_moves<S>(
+        &self,
+        instruments: &T,
+        snake_ids_and_moves: &[(Self::SnakeIDType, S)],
+    ) -> Result<
+        Box<dyn Iterator<Item = Result<(Action<N_SNAKES>, Self), Self::SimulationError>> + '_>,
+        Self::SimulationError,
+    >
+    where
+        S: Borrow<[Move]>;
+}
+
+/// Why a snake was eliminated in a simulated turn
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DeathCause {
+    /// moved off the board
+    Wall,
+    /// moved into its own body
+    SelfCollision,
+    /// moved into the body of another snake
+    BodyCollision,
+    /// moved onto the same square as a snake that was as long or longer
+    LostHeadToHead,
+    /// ran out of health
+    Starvation,
+    /// had health left, but not enough for the damage of the hazard it moved into
+    HazardDamage,
+}
+
+impl fmt::Display for DeathCause {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DeathCause::Wall => write!(f, "wall"),
+            DeathCause::SelfCollision => write!(f, "self collision"),
+            DeathCause::BodyCollision => write!(f, "body collision"),
+            DeathCause::LostHeadToHead => write!(f, "lost head to head"),
+            DeathCause::Starvation => write!(f, "starvation"),
+            DeathCause::HazardDamage => write!(f, "hazard damage"),
+        }
+    }
+}
+
+/// Something that happened in a simulated turn
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum TurnEvent {
+    /// the snake was eliminated
+    Died {
+        /// the eliminated snake
+        sid: SnakeId,
+        /// why it was eliminated
+        cause: DeathCause,
+    },
+    /// the snake moved onto a food
+    AteFood {
+        /// the snake that ate
+        sid: SnakeId,
+    },
+    /// several snakes moved onto the same square
+    HeadToHead {
+        /// every snake that moved onto the square, ordered by id
+        snakes: Vec<SnakeId>,
+        /// the only longest snake, it survives. None if the longest snakes tied
+        winner: Option<SnakeId>,
+    },
+}
+
+/// a game that can also report what happened in every simulated turn, which is slower than only
+/// simulating it
+pub trait EventSimulableGame<T: SimulatorInstruments, const N_SNAKES: usize>:
+    SimulableGame<T, N_SNAKES>
+{
+    /// like [`SimulableGame::simulate_with_moves`], along with the events of the turn that led to
+    /// each state: deaths with their cause in the order of the snake ids, food that was eaten and
+    /// head to head collisions
+    #[allow(clippy::type_complexity)]
+    fn simulate_with_moves_and_events<S>(
+        &self,
+        instruments: &T,
+        snake_ids_and_moves: &[(Self::SnakeIDType, S)],
+    ) -> Box<dyn Iterator<Item = (Action<N_SNAKES>, Self, Vec<TurnEvent>)> + '_>
+    where
+        S: Borrow<[Move]>;
+}
+
 /// A game where positions can be checked for hazards
 pub trait HazardQueryableGame: PositionGettableGame {
     /// Is this position a hazard?
@@ -317,6 +414,15 @@ pub trait HazardSettableGame: PositionGettableGame {
     fn clear_hazard(&mut self, pos: Self::NativePositionType);
 }
 
+/// A game where positions can have food placed and removed
+pub trait FoodSettableGame: PositionGettableGame {
+    /// place a food on this position
+    fn set_food(&mut self, pos: Self::NativePositionType);
+
+    /// remove the food from this position, if there is one
+    fn clear_food(&mut self, pos: Self::NativePositionType);
+}
+
 /// A game for which board positions can be identified and returned
 pub trait PositionGettableGame {
     /// the native position type for this board
@@ -435,6 +541,12 @@ pub trait SizeDeterminableGame {
     fn get_height(&self) -> u32;
 }
 
+/// a game for which it's determinable whether the edges of the board wrap around
+pub trait WrapDeterminableGame {
+    /// true if a snake leaving the board on one edge enters it again on the opposite edge
+    fn is_wrapped(&self) -> bool;
+}
+
 /// a game for which the current turn is determinable
 pub trait TurnDeterminableGame {
     #[allow(missing_docs)]
@@ -465,10 +577,21 @@ pub trait EmptyCellGettableGame: PositionGettableGame {
     fn get_empty_cells(&self) -> Box<dyn Iterator<Item = Self::NativePositionType> + '_>;
 }
 
-/// A game that can place food following the standard rules
+/// A game with a 64-bit Zobrist key of its position, which is updated incrementally as the
+/// game is simulated instead of being recomputed from the whole board
+///
+/// Equal positions always have equal keys. Positions that only differ by a few points of health
+/// have equal keys as well, so they are treated as transpositions
+pub trait ZobristHashableGame {
+    /// get the Zobrist key of this position
+    fn zobrist_hash(&self) -> u64;
+}
+
+/// A game that can place food following the standard rules, with the `minimumFood` and
+/// `foodSpawnChance` of the game settings
 ///
 /// - If the number of Food on the board is less than the minimum spawn enough food to reach the miniumum.
-/// - Otherwise there is a 15% chance of spawning a single food
+/// - Otherwise there is a `foodSpawnChance` percent chance of spawning a single food
 /// - Otherwise no food spawns
 ///
 /// - When food spawns place it randomly on the empty cells of the board
@@ -4,13 +4,13 @@
 use crate::compact_representation;
 use crate::compact_representation::dimensions::Dimensions;
 use crate::compact_representation::CellNum;
+use crate::compact_representation::ConversionError;
 use crate::compact_representation::StandardCellBoard;
 use crate::types::*;
 use rand::prelude::IteratorRandom;
 use serde::{Deserialize, Serialize};
 use std::collections::VecDeque;
 use std::convert::TryInto;
-use std::error::Error;
 use std::fmt::{self, Display};
 
 /// Struct that matches the `battlesnake` object from the wire representation
@@ -189,7 +189,7 @@ impl Game {
     >(
         &self,
         snake_ids: &SnakeIDMap,
-    ) -> Result<StandardCellBoard<T, D, BOARD_SIZE, MAX_SNAKES>, Box<dyn Error>> {
+    ) -> Result<StandardCellBoard<T, D, BOARD_SIZE, MAX_SNAKES>, ConversionError> {
         StandardCellBoard::convert_from_game(self.clone(), snake_ids)
     }
 
@@ -203,13 +203,9 @@ impl Game {
         snake_ids: &SnakeIDMap,
     ) -> Result<
         compact_representation::wrapped::CellBoard<T, D, BOARD_SIZE, MAX_SNAKES>,
-        Box<dyn Error>,
+        ConversionError,
     > {
-        if self.is_wrapped() {
-            compact_representation::wrapped::CellBoard::convert_from_game(self.clone(), snake_ids)
-        } else {
-            panic!("Cannot convert a non-wrapped game to a wrapped game")
-        }
+        compact_representation::wrapped::CellBoard::convert_from_game(self.clone(), snake_ids)
     }
 
     pub fn off_board(&self, position: Position) -> bool {
@@ -227,9 +223,27 @@ impl Game {
             .collect::<Vec<_>>()
     }
 
-    /// Returns a boolean indicating whether this game is using the wrapped ruleset
+    /// Returns a boolean indicating whether this game is using the wrapped ruleset, including
+    /// wrapped constrictor
     pub fn is_wrapped(&self) -> bool {
-        self.game.ruleset.name == "wrapped"
+        matches!(
+            self.game.ruleset.name.as_str(),
+            "wrapped" | "wrapped_constrictor"
+        )
+    }
+
+    /// Returns a boolean indicating whether this game is using the constrictor ruleset, including
+    /// wrapped constrictor
+    pub fn is_constrictor(&self) -> bool {
+        matches!(
+            self.game.ruleset.name.as_str(),
+            "constrictor" | "wrapped_constrictor"
+        )
+    }
+
+    /// Returns a boolean indicating whether this game is using the royale ruleset
+    pub fn is_royale(&self) -> bool {
+        self.game.ruleset.name == "royale"
     }
 
     pub fn is_arcade_maze_map(&self) -> bool {
@@ -528,6 +542,18 @@ impl HazardSettableGame for Game {
     }
 }
 
+impl FoodSettableGame for Game {
+    fn set_food(&mut self, pos: Self::NativePositionType) {
+        if !self.board.food.contains(&pos) {
+            self.board.food.push(pos);
+        }
+    }
+
+    fn clear_food(&mut self, pos: Self::NativePositionType) {
+        self.board.food.retain(|p| p != &pos);
+    }
+}
+
 impl NeighborDeterminableGame for Game {
     fn neighbors<'a>(
         &'a self,
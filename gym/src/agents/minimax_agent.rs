@@ -1,69 +1,62 @@
-use battlesnake_game_types::{
-    compact_representation::standard::CellBoard4Snakes11x11,
-    types::{
-        FoodGettableGame, HeadGettableGame, HealthGettableGame, LengthGettableGame, Move,
-        NeighborDeterminableGame, ReasonableMovesGame, SimulableGame, SimulatorInstruments,
-        SnakeId, VictorDeterminableGame,
-    },
-};
-
-use lib::Agent;
-
-#[derive(Debug)]
-struct Instr;
-impl SimulatorInstruments for Instr {
-    fn observe_simulation(&self, _: std::time::Duration) {}
-}
+use battlesnake_game_types::types::{Move, SimulableGame, SnakeId};
+
+use lib::eval::DefaultEvaluator;
+use lib::game::{Instruments, SearchableGame};
+use lib::{Agent, Evaluator};
 
-/// A minimax agent with alpha-beta pruning.
-pub struct MinimaxAgent {
+/// A minimax agent with alpha-beta pruning, the leaves are scored with its [`Evaluator`].
+pub struct MinimaxAgent<B, const N_SNAKES: usize> {
     name: String,
     depth: u32,
+    evaluator: Box<dyn Evaluator<B>>,
 }
 
-impl MinimaxAgent {
+impl<B, const N_SNAKES: usize> MinimaxAgent<B, N_SNAKES>
+where
+    B: SearchableGame + SimulableGame<Instruments, N_SNAKES>,
+{
     pub fn new(depth: u32) -> Self {
-        Self {
-            name: "Minimax".to_string(),
-            depth,
-        }
+        Self::with_name("Minimax", depth)
     }
 
     pub fn with_name(name: impl Into<String>, depth: u32) -> Self {
         Self {
             name: name.into(),
             depth,
+            evaluator: Box::new(DefaultEvaluator),
         }
     }
 
-    fn evaluate(&self, board: &CellBoard4Snakes11x11, you: SnakeId) -> i32 {
-        // Terminal state check
+    pub fn with_evaluator(
+        mut self,
+        evaluator: impl Evaluator<B> + 'static,
+    ) -> Self {
+        self.evaluator = Box::new(evaluator);
+        self
+    }
+
+    fn evaluate(&self, board: &B, you: SnakeId) -> f32 {
+        // Terminal state check, outside of the [0, 1] range of any evaluation
         if board.is_over() {
             return if board.get_winner() == Some(you) {
-                10000
+                2.0
             } else {
-                -10000
+                -1.0
             };
         }
 
-        let health = board.get_health(&you) as i32;
-        let length = board.get_length(&you) as i32;
-        let head = board.get_head_as_native_position(&you);
-        let moves = board.possible_moves(&head).count() as i32;
-
-        // Basic evaluation: health + length * 10 + mobility * 5
-        health + length * 10 + moves * 5
+        self.evaluator.evaluate(board, &you)
     }
 
     fn minimax(
         &self,
-        board: &CellBoard4Snakes11x11,
+        board: &B,
         you: SnakeId,
         depth: u32,
-        mut alpha: i32,
-        mut beta: i32,
+        mut alpha: f32,
+        mut beta: f32,
         maximizing: bool,
-    ) -> i32 {
+    ) -> f32 {
         if depth == 0 || board.is_over() {
             return self.evaluate(board, you);
         }
@@ -83,11 +76,11 @@ impl MinimaxAgent {
         }
 
         if maximizing {
-            let mut max_eval = i32::MIN;
+            let mut max_eval = f32::NEG_INFINITY;
             for moves in combinations {
                 let moves_for_sim: Vec<_> = moves.iter().map(|(sid, mv)| (*sid, [*mv])).collect();
 
-                if let Some((_, next_board)) = board.simulate_with_moves(&Instr, &moves_for_sim).next() {
+                if let Some((_, next_board)) = board.simulate_with_moves(&Instruments, &moves_for_sim).next() {
                     let eval = self.minimax(&next_board, you, depth - 1, alpha, beta, false);
                     max_eval = max_eval.max(eval);
                     alpha = alpha.max(eval);
@@ -98,11 +91,11 @@ impl MinimaxAgent {
             }
             max_eval
         } else {
-            let mut min_eval = i32::MAX;
+            let mut min_eval = f32::INFINITY;
             for moves in combinations {
                 let moves_for_sim: Vec<_> = moves.iter().map(|(sid, mv)| (*sid, [*mv])).collect();
 
-                if let Some((_, next_board)) = board.simulate_with_moves(&Instr, &moves_for_sim).next() {
+                if let Some((_, next_board)) = board.simulate_with_moves(&Instruments, &moves_for_sim).next() {
                     let eval = self.minimax(&next_board, you, depth - 1, alpha, beta, true);
                     min_eval = min_eval.min(eval);
                     beta = beta.min(eval);
@@ -141,18 +134,24 @@ impl MinimaxAgent {
     }
 }
 
-impl Default for MinimaxAgent {
+impl<B, const N_SNAKES: usize> Default for MinimaxAgent<B, N_SNAKES>
+where
+    B: SearchableGame + SimulableGame<Instruments, N_SNAKES>,
+{
     fn default() -> Self {
         Self::new(3)
     }
 }
 
-impl Agent for MinimaxAgent {
+impl<B, const N_SNAKES: usize> Agent<B> for MinimaxAgent<B, N_SNAKES>
+where
+    B: SearchableGame + SimulableGame<Instruments, N_SNAKES>,
+{
     fn name(&self) -> &str {
         &self.name
     }
 
-    fn choose_move(&self, board: &CellBoard4Snakes11x11, you: SnakeId) -> Move {
+    fn choose_move(&self, board: &B, you: SnakeId) -> Move {
         let my_moves: Vec<Move> = board
             .reasonable_moves_for_each_snake()
             .find(|(sid, _)| *sid == you)
@@ -160,7 +159,7 @@ impl Agent for MinimaxAgent {
             .unwrap_or_else(|| vec![Move::Up, Move::Down, Move::Left, Move::Right]);
 
         let mut best_move = my_moves.first().copied().unwrap_or(Move::Up);
-        let mut best_score = i32::MIN;
+        let mut best_score = f32::NEG_INFINITY;
 
         for mv in my_moves {
             // Create move combination with our move and assume others pick first valid
@@ -176,8 +175,8 @@ impl Agent for MinimaxAgent {
                 })
                 .collect();
 
-            if let Some((_, next_board)) = board.simulate_with_moves(&Instr, &moves_for_sim).next() {
-                let score = self.minimax(&next_board, you, self.depth - 1, i32::MIN, i32::MAX, false);
+            if let Some((_, next_board)) = board.simulate_with_moves(&Instruments, &moves_for_sim).next() {
+                let score = self.minimax(&next_board, you, self.depth - 1, f32::NEG_INFINITY, f32::INFINITY, false);
                 if score > best_score {
                     best_score = score;
                     best_move = mv;
@@ -1,9 +1,7 @@
-use battlesnake_game_types::{
-    compact_representation::standard::CellBoard4Snakes11x11,
-    types::{Move, RandomReasonableMovesGame, ReasonableMovesGame, SnakeId},
-};
+use battlesnake_game_types::types::{Move, SnakeId};
 use rand::Rng;
 
+use lib::game::SearchableGame;
 use lib::Agent;
 
 /// A simple agent that picks a random valid move each turn.
@@ -30,12 +28,12 @@ impl Default for RandomAgent {
     }
 }
 
-impl Agent for RandomAgent {
+impl<B: SearchableGame> Agent<B> for RandomAgent {
     fn name(&self) -> &str {
         &self.name
     }
 
-    fn choose_move(&self, board: &CellBoard4Snakes11x11, you: SnakeId) -> Move {
+    fn choose_move(&self, board: &B, you: SnakeId) -> Move {
         let mut rng = rand::rng();
 
         // Try to get a random reasonable move
@@ -1,6 +1,17 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
 use std::time::Duration;
 
-use clap::{Parser, Subcommand, ValueEnum};
+use battlesnake_game_types::compact_representation::dimensions::ArcadeMaze;
+use battlesnake_game_types::compact_representation::standard::{self, CellBoard4Snakes11x11};
+use battlesnake_game_types::compact_representation::wrapped::{self, CellBoard4SnakesSquare11x11};
+use battlesnake_game_types::maps::GameMap;
+use battlesnake_game_types::types::{
+    build_snake_id_map, EventSimulableGame, FallibleSimulableGame, SnakeId,
+};
+
+use clap::error::ErrorKind;
+use clap::{Args, CommandFactory, Parser, Subcommand, ValueEnum};
 use colored::Colorize;
 use indicatif::{ProgressBar, ProgressStyle};
 
@@ -8,9 +19,15 @@ mod agents;
 mod runner;
 mod stats;
 
-use lib::Agent;
+use lib::config::{ParallelMode, RolloutPolicy};
+use lib::mcts::{mcts_search, Node, SearchMode};
+use lib::reward::{OpponentModel, RewardKind};
+use lib::selection::FinalMovePolicy;
+use lib::eval::{DefaultEvaluator, WeightedEvaluator};
+use lib::game::{FromWireGame, Instruments, SearchableGame};
+use lib::{Agent, Evaluator, MctsConfig};
 use agents::{HeuristicAgent, MctsAgent, MinimaxAgent, RandomAgent};
-use runner::{run_game, GameConfig};
+use runner::{generate_random_game, run_game, GameConfig};
 use stats::{HeadToHeadStats, TournamentStats};
 
 #[derive(Parser)]
@@ -38,14 +55,41 @@ enum Commands {
         #[arg(long, default_value = "50")]
         mcts_time: u64,
 
+        #[command(flatten)]
+        mcts: MctsArgs,
+
         /// Minimax search depth
         #[arg(long, default_value = "3")]
         minimax_depth: u32,
 
+        /// Board evaluation of the MCTS, minimax and heuristic agents
+        #[arg(long, default_value = "default")]
+        evaluator: EvaluatorType,
+
         /// Maximum turns per game
         #[arg(long, default_value = "500")]
         max_turns: u32,
 
+        /// Play with the wrapped ruleset
+        #[arg(long)]
+        wrapped: bool,
+
+        /// Play with the constrictor ruleset
+        #[arg(long)]
+        constrictor: bool,
+
+        /// Play with the royale ruleset, shrinking the board every this many turns
+        #[arg(long, value_name = "TURNS", conflicts_with_all = ["wrapped", "constrictor"])]
+        royale: Option<i32>,
+
+        /// Spawn food during the game with the food settings of the ruleset
+        #[arg(long)]
+        spawn_food: bool,
+
+        /// Map to play on, e.g. arcade_maze, sinkholes or hz_spiral
+        #[arg(long, default_value = "standard", value_parser = parse_map)]
+        map: GameMap,
+
         /// Run games in parallel
         #[arg(short, long)]
         parallel: bool,
@@ -73,14 +117,41 @@ enum Commands {
         #[arg(long, default_value = "50")]
         mcts_time: u64,
 
+        #[command(flatten)]
+        mcts: MctsArgs,
+
         /// Minimax search depth
         #[arg(long, default_value = "3")]
         minimax_depth: u32,
 
+        /// Board evaluation of the MCTS, minimax and heuristic agents
+        #[arg(long, default_value = "default")]
+        evaluator: EvaluatorType,
+
         /// Maximum turns per game
         #[arg(long, default_value = "500")]
         max_turns: u32,
 
+        /// Play with the wrapped ruleset
+        #[arg(long)]
+        wrapped: bool,
+
+        /// Play with the constrictor ruleset
+        #[arg(long)]
+        constrictor: bool,
+
+        /// Play with the royale ruleset, shrinking the board every this many turns
+        #[arg(long, value_name = "TURNS", conflicts_with_all = ["wrapped", "constrictor"])]
+        royale: Option<i32>,
+
+        /// Spawn food during the game with the food settings of the ruleset
+        #[arg(long)]
+        spawn_food: bool,
+
+        /// Map to play on, e.g. arcade_maze, sinkholes or hz_spiral
+        #[arg(long, default_value = "standard", value_parser = parse_map)]
+        map: GameMap,
+
         /// Run games in parallel
         #[arg(short, long)]
         parallel: bool,
@@ -103,28 +174,215 @@ enum Commands {
         /// Run games in parallel
         #[arg(short, long)]
         parallel: bool,
+
+        #[command(flatten)]
+        mcts: MctsArgs,
+    },
+
+    /// Compare the tree-parallel MCTS at different thread counts
+    Threads {
+        /// Number of positions searched and games played per thread count
+        #[arg(short, long, default_value = "10")]
+        games: usize,
+
+        /// Thread counts to test, each one plays against a single threaded MCTS
+        #[arg(long, value_delimiter = ',', default_value = "1,2,4,8")]
+        thread_counts: Vec<usize>,
+
+        /// MCTS think time in milliseconds
+        #[arg(long, default_value = "50")]
+        mcts_time: u64,
+
+        #[command(flatten)]
+        mcts: MctsArgs,
     },
 }
 
+/// Search parameters of the MCTS agents, see [`MctsConfig`]
+#[derive(Args, Clone, Debug)]
+struct MctsArgs {
+    /// UCB1 exploration constant used while descending the tree
+    #[arg(long, default_value_t = MctsConfig::default().exploration_constant)]
+    exploration_constant: f32,
+
+    /// Maximum number of turns simulated per rollout
+    #[arg(long, default_value_t = MctsConfig::default().rollout_depth)]
+    rollout_depth: u32,
+
+    /// How moves are picked during rollouts (random, epsilon_greedy, cutoff)
+    #[arg(long, default_value = "random")]
+    rollout_policy: RolloutPolicy,
+
+    /// Chance of a random move in epsilon_greedy rollouts
+    #[arg(long, default_value_t = MctsConfig::default().rollout_epsilon)]
+    rollout_epsilon: f32,
+
+    /// Turns played before the board is evaluated in cutoff rollouts
+    #[arg(long, default_value_t = MctsConfig::default().rollout_cutoff)]
+    rollout_cutoff: u32,
+
+    /// How the played move is picked (robust_child, max_child, max_robust)
+    #[arg(long, default_value = "robust_child")]
+    final_move_policy: FinalMovePolicy,
+
+    /// How rollouts are scored (win_loss, placement, evaluation)
+    #[arg(long, default_value = "win_loss")]
+    reward_kind: RewardKind,
+
+    /// Whose reward the opponents maximize (max_n, paranoid)
+    #[arg(long, default_value = "max_n")]
+    opponent_model: OpponentModel,
+
+    /// Number of threads searching the tree of each MCTS agent
+    #[arg(long, default_value_t = MctsConfig::default().threads)]
+    mcts_threads: usize,
+
+    /// How the MCTS threads share the work (tree, root)
+    #[arg(long, default_value = "tree")]
+    parallel_mode: ParallelMode,
+
+    /// Virtual losses added to the path of a running search so threads spread out, 0 disables it
+    #[arg(long, default_value_t = MctsConfig::default().virtual_loss)]
+    virtual_loss: u32,
+
+    /// Maximum number of nodes added to the tree per move
+    #[arg(long)]
+    node_budget: Option<usize>,
+
+    /// Buckets of the transposition table that shares statistics between transpositions, off if not given
+    #[arg(long)]
+    transposition_table: Option<usize>,
+
+    /// Shrink the board of royale games during rollouts
+    #[arg(long)]
+    royale_shrinking: bool,
+
+    /// Spawn food during the search with the food settings of the game
+    #[arg(long)]
+    mcts_spawn_food: bool,
+}
+
+impl MctsArgs {
+    fn config(&self) -> MctsConfig {
+        MctsConfig {
+            exploration_constant: self.exploration_constant,
+            rollout_depth: self.rollout_depth,
+            rollout_policy: self.rollout_policy,
+            rollout_epsilon: self.rollout_epsilon,
+            rollout_cutoff: self.rollout_cutoff,
+            final_move_policy: self.final_move_policy,
+            reward_kind: self.reward_kind,
+            opponent_model: self.opponent_model,
+            threads: self.mcts_threads,
+            parallel_mode: self.parallel_mode,
+            virtual_loss: self.virtual_loss,
+            node_budget: self.node_budget,
+            transposition_table: self.transposition_table,
+            royale_shrinking: self.royale_shrinking,
+            spawn_food: self.mcts_spawn_food,
+            ..MctsConfig::default()
+        }
+    }
+}
+
 #[derive(Clone, Copy, ValueEnum, Debug, PartialEq)]
 enum AgentType {
     Mcts,
+    /// MCTS using decoupled UCT, every snake selects its move independently
+    MctsDecoupled,
+    /// MCTS where every thread searches its own tree and the trees vote on the move
+    MctsRootParallel,
     Random,
     Heuristic,
     Minimax,
 }
 
+/// The board evaluations of `lib::eval`
+#[derive(Clone, Copy, ValueEnum, Debug, PartialEq)]
+enum EvaluatorType {
+    Default,
+    /// Weighted board features with the default weights
+    Weighted,
+}
+
+impl EvaluatorType {
+    fn evaluator<B: SearchableGame>(&self) -> Box<dyn Evaluator<B>> {
+        match self {
+            EvaluatorType::Default => Box::new(DefaultEvaluator),
+            EvaluatorType::Weighted => Box::new(WeightedEvaluator::default()),
+        }
+    }
+}
+
 impl AgentType {
-    fn create_agent(&self, mcts_time_ms: u64, minimax_depth: u32) -> Box<dyn Agent> {
+    fn create_agent<B, const N_SNAKES: usize>(
+        &self,
+        mcts_time_ms: u64,
+        minimax_depth: u32,
+        mcts_config: &MctsConfig,
+        evaluator: EvaluatorType,
+    ) -> Box<dyn Agent<B>>
+    where
+        B: SearchableGame + FallibleSimulableGame<Instruments, N_SNAKES>,
+    {
         match self {
-            AgentType::Mcts => Box::new(MctsAgent::new(Duration::from_millis(mcts_time_ms))),
+            AgentType::Mcts => Box::new(
+                MctsAgent::<B, N_SNAKES>::new(Duration::from_millis(mcts_time_ms))
+                    .with_config(*mcts_config)
+                    .with_evaluator(evaluator.evaluator()),
+            ),
+            AgentType::MctsDecoupled => Box::new(
+                MctsAgent::<B, N_SNAKES>::with_name("MCTS-Decoupled", Duration::from_millis(mcts_time_ms))
+                    .with_config(*mcts_config)
+                    .with_search_mode(SearchMode::Decoupled)
+                    .with_evaluator(evaluator.evaluator()),
+            ),
+            AgentType::MctsRootParallel => Box::new(
+                MctsAgent::<B, N_SNAKES>::with_name("MCTS-RootParallel", Duration::from_millis(mcts_time_ms))
+                    .with_config(MctsConfig {
+                        parallel_mode: ParallelMode::Root,
+                        ..*mcts_config
+                    })
+                    .with_evaluator(evaluator.evaluator()),
+            ),
             AgentType::Random => Box::new(RandomAgent::new()),
-            AgentType::Heuristic => Box::new(HeuristicAgent::new()),
-            AgentType::Minimax => Box::new(MinimaxAgent::new(minimax_depth)),
+            AgentType::Heuristic => {
+                Box::new(HeuristicAgent::<B, N_SNAKES>::new().with_evaluator(evaluator.evaluator()))
+            }
+            AgentType::Minimax => {
+                Box::new(MinimaxAgent::<B, N_SNAKES>::new(minimax_depth).with_evaluator(evaluator.evaluator()))
+            }
         }
     }
 }
 
+/// Board of the arcade maze map with the standard ruleset
+type StandardArcadeMaze = standard::CellBoard<u16, ArcadeMaze, { 19 * 21 }, 4>;
+/// Board of the arcade maze map with the wrapped ruleset
+type WrappedArcadeMaze = wrapped::CellBoard<u16, ArcadeMaze, { 19 * 21 }, 4>;
+
+fn parse_map(name: &str) -> Result<GameMap, String> {
+    GameMap::from_name(name).ok_or_else(|| format!("unknown map {}", name))
+}
+
+/// The royale map shrinks every 25 turns unless told otherwise
+fn shrink_every_n_turns(royale: Option<i32>, map: GameMap) -> Option<i32> {
+    royale.or(if map == GameMap::Royale { Some(25) } else { None })
+}
+
+/// Royale games are played with the royale ruleset, which `--royale` already keeps apart from
+/// the other rulesets, but the royale map turns it on as well
+fn check_rulesets(wrapped: bool, constrictor: bool, map: GameMap) {
+    if map == GameMap::Royale && (wrapped || constrictor) {
+        Cli::command()
+            .error(
+                ErrorKind::ArgumentConflict,
+                "the royale map can't be played with --wrapped or --constrictor",
+            )
+            .exit();
+    }
+}
+
 fn main() {
     let cli = Cli::parse();
 
@@ -133,69 +391,130 @@ fn main() {
             games,
             agents,
             mcts_time,
+            mcts,
             minimax_depth,
+            evaluator,
             max_turns,
+            wrapped,
+            constrictor,
+            royale,
+            spawn_food,
+            map,
             parallel,
             json,
         } => {
-            run_tournament_cmd(games, &agents, mcts_time, minimax_depth, max_turns, parallel, json);
+            check_rulesets(wrapped, constrictor, map);
+            let config = GameConfig {
+                num_snakes: agents.len().min(4),
+                max_turns,
+                wrapped,
+                constrictor,
+                shrink_every_n_turns: shrink_every_n_turns(royale, map),
+                spawn_food,
+                map,
+                ..GameConfig::default()
+            };
+            match (wrapped, map) {
+                (true, GameMap::ArcadeMaze) => run_tournament_cmd::<WrappedArcadeMaze, 4>(games, &agents, mcts_time, &mcts.config(), minimax_depth, evaluator, &config, parallel, json),
+                (false, GameMap::ArcadeMaze) => run_tournament_cmd::<StandardArcadeMaze, 4>(games, &agents, mcts_time, &mcts.config(), minimax_depth, evaluator, &config, parallel, json),
+                (true, _) => run_tournament_cmd::<CellBoard4SnakesSquare11x11, 4>(games, &agents, mcts_time, &mcts.config(), minimax_depth, evaluator, &config, parallel, json),
+                (false, _) => run_tournament_cmd::<CellBoard4Snakes11x11, 4>(games, &agents, mcts_time, &mcts.config(), minimax_depth, evaluator, &config, parallel, json),
+            }
         }
         Commands::Duel {
             agent1,
             agent2,
             games,
             mcts_time,
+            mcts,
             minimax_depth,
+            evaluator,
             max_turns,
+            wrapped,
+            constrictor,
+            royale,
+            spawn_food,
+            map,
             parallel,
             json,
         } => {
-            run_duel_cmd(agent1, agent2, games, mcts_time, minimax_depth, max_turns, parallel, json);
+            check_rulesets(wrapped, constrictor, map);
+            let config = GameConfig {
+                wrapped,
+                constrictor,
+                shrink_every_n_turns: shrink_every_n_turns(royale, map),
+                spawn_food,
+                map,
+                ..GameConfig::duel().with_max_turns(max_turns)
+            };
+            match (wrapped, map) {
+                (true, GameMap::ArcadeMaze) => run_duel_cmd::<WrappedArcadeMaze, 4>(agent1, agent2, games, mcts_time, &mcts.config(), minimax_depth, evaluator, &config, parallel, json),
+                (false, GameMap::ArcadeMaze) => run_duel_cmd::<StandardArcadeMaze, 4>(agent1, agent2, games, mcts_time, &mcts.config(), minimax_depth, evaluator, &config, parallel, json),
+                (true, _) => run_duel_cmd::<CellBoard4SnakesSquare11x11, 4>(agent1, agent2, games, mcts_time, &mcts.config(), minimax_depth, evaluator, &config, parallel, json),
+                (false, _) => run_duel_cmd::<CellBoard4Snakes11x11, 4>(agent1, agent2, games, mcts_time, &mcts.config(), minimax_depth, evaluator, &config, parallel, json),
+            }
         }
         Commands::Benchmark {
             games,
             mcts_times,
             parallel,
+            mcts,
+        } => {
+            run_benchmark_cmd(games, &mcts_times, parallel, &mcts.config());
+        }
+        Commands::Threads {
+            games,
+            thread_counts,
+            mcts_time,
+            mcts,
         } => {
-            run_benchmark_cmd(games, &mcts_times, parallel);
+            run_threads_cmd(games, &thread_counts, mcts_time, &mcts.config());
         }
     }
 }
 
-fn run_tournament_cmd(
+#[allow(clippy::too_many_arguments)]
+fn run_tournament_cmd<B, const N_SNAKES: usize>(
     num_games: usize,
     agent_types: &[AgentType],
     mcts_time: u64,
+    mcts_config: &MctsConfig,
     minimax_depth: u32,
-    max_turns: u32,
+    evaluator: EvaluatorType,
+    config: &GameConfig,
     parallel: bool,
     json_output: bool,
-) {
+) where
+    B: SearchableGame
+        + FromWireGame
+        + EventSimulableGame<Instruments, N_SNAKES>
+        + FallibleSimulableGame<Instruments, N_SNAKES>,
+{
     if !json_output {
         println!(
             "\n{}",
             "=== Snake Gym Tournament ===".green().bold()
         );
-        println!("Games: {} | Max turns: {}", num_games, max_turns);
+        println!("Games: {} | Max turns: {} | Wrapped: {} | Constrictor: {}", num_games, config.max_turns, config.wrapped, config.constrictor);
+        if let Some(turns) = config.shrink_every_n_turns {
+            println!("Royale: shrinking every {} turns", turns);
+        }
+        if config.map != GameMap::Standard {
+            println!("Map: {}", config.map.name());
+        }
         println!("Parallel: {} | MCTS time: {}ms", parallel, mcts_time);
         println!();
     }
 
     // Create agents
-    let agents: Vec<Box<dyn Agent>> = agent_types
+    let agents: Vec<Box<dyn Agent<B>>> = agent_types
         .iter()
-        .map(|t| t.create_agent(mcts_time, minimax_depth))
+        .map(|t| t.create_agent(mcts_time, minimax_depth, mcts_config, evaluator))
         .collect();
 
-    let agent_refs: Vec<&dyn Agent> = agents.iter().map(|a| a.as_ref()).collect();
+    let agent_refs: Vec<&dyn Agent<B>> = agents.iter().map(|a| a.as_ref()).collect();
     let agent_names: Vec<String> = agents.iter().map(|a| a.name().to_string()).collect();
 
-    let config = GameConfig {
-        num_snakes: agents.len().min(4),
-        max_turns,
-        ..GameConfig::default()
-    };
-
     // Progress bar
     let pb = if !json_output {
         let pb = ProgressBar::new(num_games as u64);
@@ -216,7 +535,7 @@ fn run_tournament_cmd(
         (0..num_games)
             .into_par_iter()
             .map(|_| {
-                let result = run_game(&agent_refs, &config);
+                let result = run_game(&agent_refs, config);
                 if let Some(ref pb) = pb {
                     pb.inc(1);
                 }
@@ -226,7 +545,7 @@ fn run_tournament_cmd(
     } else {
         (0..num_games)
             .map(|_| {
-                let result = run_game(&agent_refs, &config);
+                let result = run_game(&agent_refs, config);
                 if let Some(ref pb) = pb {
                     pb.inc(1);
                 }
@@ -249,32 +568,44 @@ fn run_tournament_cmd(
     }
 }
 
-fn run_duel_cmd(
+#[allow(clippy::too_many_arguments)]
+fn run_duel_cmd<B, const N_SNAKES: usize>(
     agent1_type: AgentType,
     agent2_type: AgentType,
     num_games: usize,
     mcts_time: u64,
+    mcts_config: &MctsConfig,
     minimax_depth: u32,
-    max_turns: u32,
+    evaluator: EvaluatorType,
+    config: &GameConfig,
     parallel: bool,
     json_output: bool,
-) {
+) where
+    B: SearchableGame
+        + FromWireGame
+        + EventSimulableGame<Instruments, N_SNAKES>
+        + FallibleSimulableGame<Instruments, N_SNAKES>,
+{
     if !json_output {
         println!("\n{}", "=== Snake Gym Duel ===".green().bold());
         println!(
             "{:?} vs {:?}",
             agent1_type, agent2_type
         );
-        println!("Games: {} | Max turns: {}", num_games, max_turns);
+        println!("Games: {} | Max turns: {} | Wrapped: {} | Constrictor: {}", num_games, config.max_turns, config.wrapped, config.constrictor);
+        if let Some(turns) = config.shrink_every_n_turns {
+            println!("Royale: shrinking every {} turns", turns);
+        }
+        if config.map != GameMap::Standard {
+            println!("Map: {}", config.map.name());
+        }
         println!();
     }
 
     // Create agents
-    let agent1 = agent1_type.create_agent(mcts_time, minimax_depth);
-    let agent2 = agent2_type.create_agent(mcts_time, minimax_depth);
-    let agents: Vec<&dyn Agent> = vec![agent1.as_ref(), agent2.as_ref()];
-
-    let config = GameConfig::duel().with_max_turns(max_turns);
+    let agent1 = agent1_type.create_agent(mcts_time, minimax_depth, mcts_config, evaluator);
+    let agent2 = agent2_type.create_agent(mcts_time, minimax_depth, mcts_config, evaluator);
+    let agents: Vec<&dyn Agent<B>> = vec![agent1.as_ref(), agent2.as_ref()];
 
     // Progress bar
     let pb = if !json_output {
@@ -296,7 +627,7 @@ fn run_duel_cmd(
         (0..num_games)
             .into_par_iter()
             .map(|_| {
-                let result = run_game(&agents, &config);
+                let result = run_game(&agents, config);
                 if let Some(ref pb) = pb {
                     pb.inc(1);
                 }
@@ -306,7 +637,7 @@ fn run_duel_cmd(
     } else {
         (0..num_games)
             .map(|_| {
-                let result = run_game(&agents, &config);
+                let result = run_game(&agents, config);
                 if let Some(ref pb) = pb {
                     pb.inc(1);
                 }
@@ -344,7 +675,12 @@ fn run_duel_cmd(
     }
 }
 
-fn run_benchmark_cmd(games_per_config: usize, mcts_times: &[u64], parallel: bool) {
+fn run_benchmark_cmd(
+    games_per_config: usize,
+    mcts_times: &[u64],
+    parallel: bool,
+    mcts_config: &MctsConfig,
+) {
     println!("\n{}", "=== Snake Gym Benchmark ===".green().bold());
     println!(
         "Testing MCTS at different think times against Random baseline"
@@ -355,9 +691,10 @@ fn run_benchmark_cmd(games_per_config: usize, mcts_times: &[u64], parallel: bool
     let random_agent = RandomAgent::new();
 
     for &time_ms in mcts_times {
-        let mcts_agent = MctsAgent::with_name(format!("MCTS-{}ms", time_ms), Duration::from_millis(time_ms));
+        let mcts_agent = MctsAgent::with_name(format!("MCTS-{}ms", time_ms), Duration::from_millis(time_ms))
+            .with_config(*mcts_config);
 
-        let agents: Vec<&dyn Agent> = vec![&mcts_agent, &random_agent];
+        let agents: Vec<&dyn Agent<CellBoard4Snakes11x11>> = vec![&mcts_agent, &random_agent];
         let config = GameConfig::duel();
 
         let pb = ProgressBar::new(games_per_config as u64);
@@ -393,7 +730,7 @@ fn run_benchmark_cmd(games_per_config: usize, mcts_times: &[u64], parallel: bool
 
         pb.finish();
 
-        let h2h = HeadToHeadStats::from_results(&results, mcts_agent.name(), random_agent.name());
+        let h2h = HeadToHeadStats::from_results(&results, agents[0].name(), agents[1].name());
 
         let win_rate = h2h.agent1_wins as f64 / (h2h.agent1_wins + h2h.agent2_wins + h2h.draws) as f64 * 100.0;
 
@@ -410,6 +747,94 @@ fn run_benchmark_cmd(games_per_config: usize, mcts_times: &[u64], parallel: bool
     println!();
 }
 
+fn run_threads_cmd(
+    games_per_config: usize,
+    thread_counts: &[usize],
+    mcts_time: u64,
+    mcts_config: &MctsConfig,
+) {
+    println!("\n{}", "=== Snake Gym Thread Scaling ===".green().bold());
+    println!("Testing tree-parallel MCTS at {}ms against a single threaded MCTS", mcts_time);
+    println!("Games per config: {}", games_per_config);
+    println!();
+
+    let think_time = Duration::from_millis(mcts_time);
+    let config = GameConfig::duel();
+    let baseline_agent = MctsAgent::with_name("MCTS-1t", think_time)
+        .with_config(MctsConfig { threads: 1, ..*mcts_config });
+
+    for &threads in thread_counts {
+        let threaded_config = MctsConfig { threads, ..*mcts_config };
+        let playouts_per_second = measure_playouts_per_second(games_per_config, think_time, &threaded_config);
+
+        let mcts_agent = MctsAgent::with_name(format!("MCTS-{}t", threads), think_time)
+            .with_config(threaded_config);
+        let agents: Vec<&dyn Agent<CellBoard4Snakes11x11>> = vec![&mcts_agent, &baseline_agent];
+
+        let pb = ProgressBar::new(games_per_config as u64);
+        pb.set_style(
+            ProgressStyle::default_bar()
+                .template(&format!(
+                    "{{spinner:.green}} MCTS {} threads [{{bar:30.cyan/blue}}] {{pos}}/{{len}}",
+                    threads
+                ))
+                .unwrap()
+                .progress_chars("#>-"),
+        );
+
+        // Games run one after another so the threads of the searches don't compete with each other
+        let results: Vec<_> = (0..games_per_config)
+            .map(|_| {
+                let result = run_game(&agents, &config);
+                pb.inc(1);
+                result
+            })
+            .collect();
+
+        pb.finish();
+
+        let h2h = HeadToHeadStats::from_results(&results, mcts_agent.name(), baseline_agent.name());
+
+        let win_rate = h2h.agent1_wins as f64 / (h2h.agent1_wins + h2h.agent2_wins + h2h.draws) as f64 * 100.0;
+
+        println!(
+            "  MCTS {} threads: {:.0} playouts/s, {:.1}% win rate vs 1 thread ({} wins / {} losses / {} draws)",
+            threads,
+            playouts_per_second,
+            win_rate,
+            h2h.agent1_wins.to_string().green(),
+            h2h.agent2_wins.to_string().red(),
+            h2h.draws.to_string().yellow()
+        );
+    }
+
+    println!();
+}
+
+/// Searches `positions` random starting positions for `think_time` each and returns the average
+/// number of playouts per second
+fn measure_playouts_per_second(positions: usize, think_time: Duration, mcts_config: &MctsConfig) -> f64 {
+    let config = GameConfig::duel();
+    let playouts: u32 = (0..positions)
+        .map(|_| {
+            let game = generate_random_game(&config);
+            let snake_id_map = build_snake_id_map(&game);
+            let board: CellBoard4Snakes11x11 = game
+                .as_cell_board(&snake_id_map)
+                .expect("Failed to create cell board");
+            let root_node = Arc::new(Node::new_root_with_config(board, mcts_config));
+            let stop = Arc::new(AtomicBool::new(false));
+            std::thread::scope(|s| {
+                s.spawn(|| mcts_search(root_node.clone(), &SnakeId(0), stop.clone(), mcts_config, &DefaultEvaluator));
+                std::thread::sleep(think_time);
+                stop.store(true, Ordering::Relaxed);
+            });
+            root_node.visits()
+        })
+        .sum();
+    playouts as f64 / (think_time.as_secs_f64() * positions as f64)
+}
+
 // Extension trait for GameConfig
 impl GameConfig {
     fn with_max_turns(mut self, max_turns: u32) -> Self {
@@ -1,25 +1,20 @@
 use std::collections::VecDeque;
 
 use battlesnake_game_types::{
-    compact_representation::standard::CellBoard4Snakes11x11,
-    types::{
-        build_snake_id_map, ReasonableMovesGame, SimulableGame, SimulatorInstruments,
-        SnakeId, VictorDeterminableGame,
+    hazard_algorithms::{ForwardOnlyHazardAlgorithm, HazardMap, RoyaleHazard},
+    maps::GameMap,
+    types::{build_snake_id_map, EventSimulableGame, SnakeId, TurnEvent},
+    wire_representation::{
+        BattleSnake, Board, Game, NestedGame, Position, RoyaleSettings, Ruleset, Settings,
     },
-    wire_representation::{BattleSnake, Board, Game, NestedGame, Position, Ruleset},
 };
 use rand::seq::SliceRandom;
 use rand::Rng;
 
+use lib::game::{FromWireGame, Instruments, SearchableGame};
 use lib::Agent;
 use crate::stats::GameResult;
 
-#[derive(Debug)]
-struct Instr;
-impl SimulatorInstruments for Instr {
-    fn observe_simulation(&self, _: std::time::Duration) {}
-}
-
 /// Configuration for game generation
 #[derive(Clone, Debug)]
 pub struct GameConfig {
@@ -30,6 +25,18 @@ pub struct GameConfig {
     pub initial_length: usize,
     pub num_food: usize,
     pub max_turns: u32,
+    /// Play with the wrapped ruleset, where snakes leaving the board enter it on the other side
+    pub wrapped: bool,
+    /// Play with the constrictor ruleset, where snakes grow every turn and never starve
+    pub constrictor: bool,
+    /// Play with the royale ruleset, where the safe area shrinks every this many turns
+    pub shrink_every_n_turns: Option<i32>,
+    /// Spawn food after every turn like the standard rules do, otherwise only the initial food
+    /// is on the board
+    pub spawn_food: bool,
+    /// The map to play on, every map but the standard one decides the size of the board, where
+    /// snakes start and the initial food itself
+    pub map: GameMap,
 }
 
 impl Default for GameConfig {
@@ -42,6 +49,11 @@ impl Default for GameConfig {
             initial_length: 3,
             num_food: 5,
             max_turns: 500,
+            wrapped: false,
+            constrictor: false,
+            shrink_every_n_turns: None,
+            spawn_food: false,
+            map: GameMap::Standard,
         }
     }
 }
@@ -105,11 +117,80 @@ pub fn generate_random_game(config: &GameConfig) -> Game {
         })
         .collect();
 
+    let board = if config.map != GameMap::Standard {
+        let (width, height) = config.map.dimensions().unwrap_or((config.width, config.height));
+        let mut board = config
+            .map
+            .initial_board(width, height, snakes, &mut rng)
+            .expect("Map doesn't fit the game config");
+        if config.constrictor {
+            board.food.clear();
+        }
+        board
+    } else {
+        standard_board(config, snakes, &snake_positions, &mut rng)
+    };
+
+    Game {
+        you: board.snakes[0].clone(),
+        board,
+        turn: 0,
+        game: NestedGame {
+            id: "gym-game".to_string(),
+            ruleset: Ruleset {
+                name: match (config.wrapped, config.constrictor) {
+                    _ if config.shrink_every_n_turns.is_some() => "royale",
+                    (false, false) => "standard",
+                    (true, false) => "wrapped",
+                    (false, true) => "constrictor",
+                    (true, true) => "wrapped_constrictor",
+                }
+                .to_string(),
+                version: "v1.0.0".to_string(),
+                settings: settings(config),
+            },
+            timeout: 500,
+            map: Some(config.map.name().to_string()),
+            source: None,
+        },
+    }
+}
+
+/// The settings of the ruleset, games on the standard map without royale use the defaults
+fn settings(config: &GameConfig) -> Option<Settings> {
+    if config.shrink_every_n_turns.is_none() && config.map == GameMap::Standard {
+        return None;
+    }
+    Some(Settings {
+        food_spawn_chance: 15,
+        minimum_food: 1,
+        // the walls of the arcade maze kill
+        hazard_damage_per_turn: if config.map == GameMap::ArcadeMaze { 100 } else { 14 },
+        hazard_map: match config.map {
+            GameMap::Hazards(name) => Some(name.to_string()),
+            _ => None,
+        },
+        hazard_map_author: None,
+        royale: config.shrink_every_n_turns.map(|shrink_every_n_turns| RoyaleSettings {
+            shrink_every_n_turns,
+        }),
+    })
+}
+
+/// The board of a game on the standard map, with randomly placed food
+fn standard_board(
+    config: &GameConfig,
+    snakes: Vec<BattleSnake>,
+    snake_positions: &[Position],
+    rng: &mut impl Rng,
+) -> Board {
     // Generate food positions (avoid snake positions)
     let mut food = Vec::new();
     let occupied: std::collections::HashSet<_> = snake_positions.iter().collect();
 
-    while food.len() < config.num_food {
+    // Constrictor games are played without food
+    let num_food = if config.constrictor { 0 } else { config.num_food };
+    while food.len() < num_food {
         let pos = Position::new(
             rng.random_range(0..config.width as i32),
             rng.random_range(0..config.height as i32),
@@ -119,37 +200,23 @@ pub fn generate_random_game(config: &GameConfig) -> Game {
         }
     }
 
-    let board = Board {
+    Board {
         height: config.height,
         width: config.width,
         food,
-        snakes: snakes.clone(),
+        snakes,
         hazards: vec![],
-    };
-
-    Game {
-        you: snakes[0].clone(),
-        board,
-        turn: 0,
-        game: NestedGame {
-            id: "gym-game".to_string(),
-            ruleset: Ruleset {
-                name: "standard".to_string(),
-                version: "v1.0.0".to_string(),
-                settings: None,
-            },
-            timeout: 500,
-            map: None,
-            source: None,
-        },
     }
 }
 
-/// Runs a single game with the given agents
-pub fn run_game(
-    agents: &[&dyn Agent],
+/// Runs a single game with the given agents on a board of type `B`, which has to fit the game
+pub fn run_game<B, const N_SNAKES: usize>(
+    agents: &[&dyn Agent<B>],
     config: &GameConfig,
-) -> GameResult {
+) -> GameResult
+where
+    B: SearchableGame + FromWireGame + EventSimulableGame<Instruments, N_SNAKES>,
+{
     assert!(
         agents.len() >= config.num_snakes,
         "Need at least {} agents for {} snakes",
@@ -160,11 +227,32 @@ pub fn run_game(
     // Generate starting position
     let game = generate_random_game(config);
     let snake_id_map = build_snake_id_map(&game);
-    let mut board: CellBoard4Snakes11x11 = game
-        .as_cell_board(&snake_id_map)
-        .expect("Failed to create cell board");
+    let settings = game.game.ruleset.settings.clone();
+    let mut rng = rand::rng();
 
+    // Royale games shrink on top of whatever map they are played on, which keeps its own
+    // hazards and food
+    let mut hazards = config
+        .map
+        .hazards(rng.random())
+        .filter(|hazards| !matches!(hazards, HazardMap::Royale(_)));
+    let mut royale = config.shrink_every_n_turns.map(|_| RoyaleHazard::new(rng.random()));
+    let mut initial_hazards: Vec<_> = match &mut hazards {
+        Some(hazards) => hazards.observe(&game).expect("Map hazards don't fit the game").collect(),
+        None => vec![],
+    };
+    if let Some(royale) = &mut royale {
+        // The safe area starts out as the whole board, the hazards of the map included
+        let mut unshrunk = game.clone();
+        unshrunk.board.hazards.clear();
+        initial_hazards.extend(royale.observe(&unshrunk).expect("Royale settings don't fit the game"));
+    }
+    let mut board = B::from_wire_game(game, &snake_id_map).expect("Failed to create cell board");
+    for pos in initial_hazards {
+        board.set_hazard(board.native_from_position(pos));
+    }
     let mut turn = 0;
+    let mut deaths = vec![];
 
     // Game loop
     while !board.is_over() && turn < config.max_turns {
@@ -178,7 +266,9 @@ pub fn run_game(
                     .any(|(sid, moves)| sid == snake_id && moves.into_iter().next().is_some());
 
                 if has_moves {
-                    let mv = agents[i].choose_move(&board, snake_id);
+                    // The shrinking of royale games, so agents can shrink the boards they
+                    // look ahead on
+                    let mv = agents[i].choose_move_with_hazards(&board, snake_id, royale);
                     Some((snake_id, [mv]))
                 } else {
                     None
@@ -192,17 +282,35 @@ pub fn run_game(
         }
 
         // Simulate the turn
-        let next_board_opt: Option<CellBoard4Snakes11x11> = board
-            .simulate_with_moves(&Instr, &moves)
-            .next()
-            .map(|(_, b)| b);
+        let next_board_opt = board
+            .simulate_with_moves_and_events(&Instruments, &moves)
+            .next();
 
-        if let Some(next_board) = next_board_opt {
+        if let Some((_, next_board, events)) = next_board_opt {
             board = next_board;
+            deaths.extend(events.into_iter().filter_map(|event| match event {
+                TurnEvent::Died { sid, cause } => Some((sid.as_usize(), cause)),
+                _ => None,
+            }));
         } else {
             break;
         }
 
+        // Shrink the board of royale games and grow the hazards of hazard maps
+        if let Some(hazards) = &mut hazards {
+            for pos in hazards.inc_turn() {
+                board.set_hazard(board.native_from_position(pos));
+            }
+        }
+        if let Some(royale) = &mut royale {
+            for pos in royale.inc_turn() {
+                board.set_hazard(board.native_from_position(pos));
+            }
+        }
+        if config.spawn_food && !config.constrictor {
+            config.map.place_food(&mut board, settings.as_ref(), &mut rng);
+        }
+
         turn += 1;
     }
 
@@ -213,26 +321,33 @@ pub fn run_game(
         winner: winner.map(|w| w.0 as usize),
         turns: turn,
         num_snakes: config.num_snakes,
+        deaths,
     }
 }
 
 /// Run multiple games and collect results
-pub fn run_tournament(
-    agents: &[&dyn Agent],
+pub fn run_tournament<B, const N_SNAKES: usize>(
+    agents: &[&dyn Agent<B>],
     config: &GameConfig,
     num_games: usize,
-) -> Vec<GameResult> {
+) -> Vec<GameResult>
+where
+    B: SearchableGame + FromWireGame + EventSimulableGame<Instruments, N_SNAKES>,
+{
     (0..num_games)
         .map(|_| run_game(agents, config))
         .collect()
 }
 
 /// Run multiple games in parallel
-pub fn run_tournament_parallel(
-    agents: &[&dyn Agent],
+pub fn run_tournament_parallel<B, const N_SNAKES: usize>(
+    agents: &[&dyn Agent<B>],
     config: &GameConfig,
     num_games: usize,
-) -> Vec<GameResult> {
+) -> Vec<GameResult>
+where
+    B: SearchableGame + FromWireGame + EventSimulableGame<Instruments, N_SNAKES>,
+{
     use rayon::prelude::*;
 
     (0..num_games)
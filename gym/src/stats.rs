@@ -1,3 +1,6 @@
+use std::collections::HashMap;
+
+use battlesnake_game_types::types::DeathCause;
 use serde::{Deserialize, Serialize};
 
 /// Result of a single game
@@ -9,6 +12,8 @@ pub struct GameResult {
     pub turns: u32,
     /// Number of snakes in the game
     pub num_snakes: usize,
+    /// Index of every agent that was eliminated and why, in the order they died
+    pub deaths: Vec<(usize, DeathCause)>,
 }
 
 /// Aggregated statistics for an agent
@@ -20,6 +25,8 @@ pub struct AgentStats {
     pub draws: u32,
     pub total_games: u32,
     pub total_turns: u64,
+    /// How often the agent was eliminated by each cause
+    pub deaths: HashMap<DeathCause, u32>,
 }
 
 impl AgentStats {
@@ -45,6 +52,17 @@ impl AgentStats {
             self.total_turns as f64 / self.total_games as f64
         }
     }
+
+    /// The causes of death, most common first, e.g. `wall: 3, starvation: 1`
+    pub fn deaths_summary(&self) -> String {
+        let mut deaths: Vec<_> = self.deaths.iter().collect();
+        deaths.sort_by_key(|(cause, count)| (std::cmp::Reverse(**count), cause.to_string()));
+        deaths
+            .iter()
+            .map(|(cause, count)| format!("{cause}: {count}"))
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
 }
 
 /// Tournament statistics
@@ -75,6 +93,11 @@ impl TournamentStats {
             total_turns += result.turns as u64;
             min_length = min_length.min(result.turns);
             max_length = max_length.max(result.turns);
+            for (agent, cause) in &result.deaths {
+                if let Some(stats) = agent_stats.get_mut(*agent) {
+                    *stats.deaths.entry(*cause).or_default() += 1;
+                }
+            }
 
             match result.winner {
                 Some(winner_idx) if winner_idx < agent_stats.len() => {
@@ -142,6 +165,8 @@ impl TournamentStats {
             win_rate: String,
             #[tabled(rename = "Avg Length")]
             avg_length: String,
+            #[tabled(rename = "Deaths")]
+            deaths: String,
         }
 
         let rows: Vec<Row> = self
@@ -154,6 +179,7 @@ impl TournamentStats {
                 draws: s.draws,
                 win_rate: format!("{:.1}%", s.win_rate() * 100.0),
                 avg_length: format!("{:.1}", s.avg_game_length()),
+                deaths: s.deaths_summary(),
             })
             .collect();
 
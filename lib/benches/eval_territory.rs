@@ -0,0 +1,81 @@
+use battlesnake_game_types::compact_representation::standard::CellBoard4Snakes11x11;
+use battlesnake_game_types::types::{HeadGettableGame, SnakeId, build_snake_id_map};
+use battlesnake_game_types::wire_representation::Game as DEGame;
+use criterion::{BenchmarkId, Criterion, criterion_group, criterion_main};
+use lib::Evaluator;
+use lib::eval::territory::{flood_fill, voronoi};
+use lib::eval::{DefaultEvaluator, WeightedEvaluator};
+use std::hint::black_box;
+
+fn boards() -> [(&'static str, CellBoard4Snakes11x11); 3] {
+    let board = |fixture: &str| {
+        let g: DEGame = serde_json::from_str(fixture).expect("the json literal is valid");
+        let snake_id_mapping = build_snake_id_map(&g);
+        g.as_cell_board(&snake_id_mapping).unwrap()
+    };
+    [
+        (
+            "start_of_game",
+            board(include_str!(
+                "../../battlesnake-game-types/fixtures/start_of_game.json"
+            )),
+        ),
+        (
+            "4_snake_game",
+            board(include_str!(
+                "../../battlesnake-game-types/fixtures/4_snake_game.json"
+            )),
+        ),
+        (
+            "late_stage",
+            board(include_str!(
+                "../../battlesnake-game-types/fixtures/late_stage.json"
+            )),
+        ),
+    ]
+}
+
+/// Benchmark the multi-source BFS computing the territory of every snake
+fn bench_voronoi(c: &mut Criterion) {
+    let mut group = c.benchmark_group("voronoi");
+    for (name, board) in boards() {
+        group.bench_with_input(BenchmarkId::from_parameter(name), &board, |b, board| {
+            b.iter(|| black_box(voronoi(black_box(board))))
+        });
+    }
+    group.finish();
+}
+
+/// Benchmark counting the cells reachable from the head of a single snake
+fn bench_flood_fill(c: &mut Criterion) {
+    let mut group = c.benchmark_group("flood_fill");
+    for (name, board) in boards() {
+        let head = board.get_head_as_position(&SnakeId(0));
+        group.bench_with_input(BenchmarkId::from_parameter(name), &board, |b, board| {
+            b.iter(|| black_box(flood_fill(black_box(board), head)))
+        });
+    }
+    group.finish();
+}
+
+/// Compare the evaluators, the weighted one includes the territory feature
+fn bench_evaluators(c: &mut Criterion) {
+    let mut group = c.benchmark_group("evaluate");
+    let evaluators: [(&str, &dyn Evaluator<CellBoard4Snakes11x11>); 2] = [
+        ("default", &DefaultEvaluator),
+        ("weighted", &WeightedEvaluator::default()),
+    ];
+    for (name, board) in boards() {
+        for (evaluator_name, evaluator) in evaluators {
+            group.bench_with_input(
+                BenchmarkId::new(evaluator_name, name),
+                &board,
+                |b, board| b.iter(|| black_box(evaluator.evaluate(black_box(board), &SnakeId(0)))),
+            );
+        }
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_voronoi, bench_flood_fill, bench_evaluators);
+criterion_main!(benches);
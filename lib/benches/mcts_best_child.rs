@@ -2,13 +2,18 @@ use battlesnake_game_types::compact_representation::standard::CellBoard4Snakes11
 use battlesnake_game_types::types::{YouDeterminableGame, build_snake_id_map};
 use battlesnake_game_types::wire_representation::Game as DEGame;
 use criterion::{BenchmarkId, Criterion, criterion_group, criterion_main};
+use lib::MctsConfig;
+use lib::eval::DefaultEvaluator;
 use lib::mcts::Node;
 use std::hint::black_box;
 use std::sync::Arc;
 
 /// Helper function to create a node with expanded children
-fn create_node_with_children(compact: CellBoard4Snakes11x11, num_expansions: usize) -> Arc<Node> {
-    let you = compact.you_id().clone();
+fn create_node_with_children(
+    compact: CellBoard4Snakes11x11,
+    num_expansions: usize,
+) -> Arc<Node<CellBoard4Snakes11x11, 4>> {
+    let you = *compact.you_id();
     let node = Arc::new(Node::new_root(compact));
 
     // Expand the node to create children
@@ -21,7 +26,8 @@ fn create_node_with_children(compact: CellBoard4Snakes11x11, num_expansions: usi
 
     // Do some rollouts to populate win/visit statistics
     for _ in 0..10 {
-        node.clone().rollout(&you);
+        node.clone()
+            .rollout(&MctsConfig::default(), &DefaultEvaluator);
     }
 
     node
@@ -39,7 +45,7 @@ fn bench_best_child_start_of_game(c: &mut Criterion) {
 
     c.bench_function("best_child_start_of_game", |b| {
         b.iter(|| {
-            black_box(node.best_child(black_box(1.414)));
+            black_box(node.best_child(node.board().you_id(), black_box(1.414)));
         })
     });
 }
@@ -56,7 +62,7 @@ fn bench_best_child_late_stage(c: &mut Criterion) {
 
     c.bench_function("best_child_late_stage", |b| {
         b.iter(|| {
-            black_box(node.best_child(black_box(1.414)));
+            black_box(node.best_child(node.board().you_id(), black_box(1.414)));
         })
     });
 }
@@ -73,7 +79,7 @@ fn bench_best_child_cornered(c: &mut Criterion) {
 
     c.bench_function("best_child_cornered", |b| {
         b.iter(|| {
-            black_box(node.best_child(black_box(1.414)));
+            black_box(node.best_child(node.board().you_id(), black_box(1.414)));
         })
     });
 }
@@ -90,7 +96,7 @@ fn bench_best_child_4_snakes(c: &mut Criterion) {
 
     c.bench_function("best_child_4_snakes", |b| {
         b.iter(|| {
-            black_box(node.best_child(black_box(1.414)));
+            black_box(node.best_child(node.board().you_id(), black_box(1.414)));
         })
     });
 }
@@ -106,14 +112,14 @@ fn bench_best_child_varying_children(c: &mut Criterion) {
     let mut group = c.benchmark_group("best_child_varying_children");
 
     for num_children in [1, 2, 4, 8, 16].iter() {
-        let node = create_node_with_children(compact.clone(), *num_children);
+        let node = create_node_with_children(compact, *num_children);
 
         group.bench_with_input(
             BenchmarkId::from_parameter(num_children),
             num_children,
             |b, _| {
                 b.iter(|| {
-                    black_box(node.best_child(black_box(1.414)));
+                    black_box(node.best_child(node.board().you_id(), black_box(1.414)));
                 });
             },
         );
@@ -139,7 +145,7 @@ fn bench_best_child_varying_c(c: &mut Criterion) {
             exploration_constant,
             |b, &c_val| {
                 b.iter(|| {
-                    black_box(node.best_child(black_box(c_val)));
+                    black_box(node.best_child(node.board().you_id(), black_box(c_val)));
                 });
             },
         );
@@ -184,7 +190,7 @@ fn bench_best_child_scenarios(c: &mut Criterion) {
 
                 group.bench_with_input(BenchmarkId::from_parameter(name), &node, |b, node| {
                     b.iter(|| {
-                        black_box(node.best_child(black_box(1.414)));
+                        black_box(node.best_child(node.board().you_id(), black_box(1.414)));
                     });
                 });
             }
@@ -204,7 +210,7 @@ fn bench_best_child_repeated_calls(c: &mut Criterion) {
     let mut group = c.benchmark_group("best_child_repeated_calls");
 
     for num_calls in [10, 50, 100, 200].iter() {
-        let node = create_node_with_children(compact.clone(), 4);
+        let node = create_node_with_children(compact, 4);
 
         group.bench_with_input(
             BenchmarkId::from_parameter(num_calls),
@@ -212,7 +218,7 @@ fn bench_best_child_repeated_calls(c: &mut Criterion) {
             |b, &num_calls| {
                 b.iter(|| {
                     for _ in 0..num_calls {
-                        black_box(node.best_child(black_box(1.414)));
+                        black_box(node.best_child(node.board().you_id(), black_box(1.414)));
                     }
                 });
             },
@@ -1,8 +1,11 @@
 use battlesnake_game_types::compact_representation::standard::CellBoard4Snakes11x11;
-use battlesnake_game_types::types::{YouDeterminableGame, build_snake_id_map};
+use battlesnake_game_types::types::build_snake_id_map;
 use battlesnake_game_types::wire_representation::Game as DEGame;
-use criterion::{BenchmarkId, Criterion, black_box, criterion_group, criterion_main};
+use criterion::{BenchmarkId, Criterion, criterion_group, criterion_main};
+use lib::MctsConfig;
+use lib::eval::DefaultEvaluator;
 use lib::mcts::Node;
+use std::hint::black_box;
 use std::sync::Arc;
 
 /// Benchmark the rollout function with different game states
@@ -12,12 +15,14 @@ fn bench_rollout_start_of_game(c: &mut Criterion) {
     let g = g.expect("the json literal is valid");
     let snake_id_mapping = build_snake_id_map(&g);
     let compact: CellBoard4Snakes11x11 = g.as_cell_board(&snake_id_mapping).unwrap();
-    let you = compact.you_id().clone();
     let node = Arc::new(Node::new_root(compact));
 
     c.bench_function("rollout_start_of_game", |b| {
         b.iter(|| {
-            black_box(node.clone().rollout(black_box(&you)));
+            black_box(
+                node.clone()
+                    .rollout(&MctsConfig::default(), &DefaultEvaluator),
+            );
         })
     });
 }
@@ -29,12 +34,14 @@ fn bench_rollout_late_stage(c: &mut Criterion) {
     let g = g.expect("the json literal is valid");
     let snake_id_mapping = build_snake_id_map(&g);
     let compact: CellBoard4Snakes11x11 = g.as_cell_board(&snake_id_mapping).unwrap();
-    let you = compact.you_id().clone();
     let node = Arc::new(Node::new_root(compact));
 
     c.bench_function("rollout_late_stage", |b| {
         b.iter(|| {
-            black_box(node.clone().rollout(black_box(&you)));
+            black_box(
+                node.clone()
+                    .rollout(&MctsConfig::default(), &DefaultEvaluator),
+            );
         })
     });
 }
@@ -46,12 +53,14 @@ fn bench_rollout_cornered(c: &mut Criterion) {
     let g = g.expect("the json literal is valid");
     let snake_id_mapping = build_snake_id_map(&g);
     let compact: CellBoard4Snakes11x11 = g.as_cell_board(&snake_id_mapping).unwrap();
-    let you = compact.you_id().clone();
     let node = Arc::new(Node::new_root(compact));
 
     c.bench_function("rollout_cornered", |b| {
         b.iter(|| {
-            black_box(node.clone().rollout(black_box(&you)));
+            black_box(
+                node.clone()
+                    .rollout(&MctsConfig::default(), &DefaultEvaluator),
+            );
         })
     });
 }
@@ -63,12 +72,14 @@ fn bench_rollout_4_snakes(c: &mut Criterion) {
     let g = g.expect("the json literal is valid");
     let snake_id_mapping = build_snake_id_map(&g);
     let compact: CellBoard4Snakes11x11 = g.as_cell_board(&snake_id_mapping).unwrap();
-    let you = compact.you_id().clone();
     let node = Arc::new(Node::new_root(compact));
 
     c.bench_function("rollout_4_snakes", |b| {
         b.iter(|| {
-            black_box(node.clone().rollout(black_box(&you)));
+            black_box(
+                node.clone()
+                    .rollout(&MctsConfig::default(), &DefaultEvaluator),
+            );
         })
     });
 }
@@ -80,7 +91,6 @@ fn bench_rollout_multiple_runs(c: &mut Criterion) {
     let g = g.expect("the json literal is valid");
     let snake_id_mapping = build_snake_id_map(&g);
     let compact: CellBoard4Snakes11x11 = g.as_cell_board(&snake_id_mapping).unwrap();
-    let you = compact.you_id().clone();
 
     let mut group = c.benchmark_group("rollout_multiple_runs");
 
@@ -89,10 +99,13 @@ fn bench_rollout_multiple_runs(c: &mut Criterion) {
             BenchmarkId::from_parameter(num_rollouts),
             num_rollouts,
             |b, &num_rollouts| {
-                let node = Arc::new(Node::new_root(compact.clone()));
+                let node = Arc::new(Node::new_root(compact));
                 b.iter(|| {
                     for _ in 0..num_rollouts {
-                        black_box(node.clone().rollout(black_box(&you)));
+                        black_box(
+                            node.clone()
+                                .rollout(&MctsConfig::default(), &DefaultEvaluator),
+                        );
                     }
                 });
             },
@@ -134,12 +147,14 @@ fn bench_rollout_scenarios(c: &mut Criterion) {
             let snake_id_mapping = build_snake_id_map(&g);
             if let Ok(compact) = g.as_cell_board(&snake_id_mapping) {
                 let compact: CellBoard4Snakes11x11 = compact;
-                let you = compact.you_id().clone();
                 let node = Arc::new(Node::new_root(compact));
 
                 group.bench_with_input(BenchmarkId::from_parameter(name), &node, |b, node| {
                     b.iter(|| {
-                        black_box(node.clone().rollout(black_box(&you)));
+                        black_box(
+                            node.clone()
+                                .rollout(&MctsConfig::default(), &DefaultEvaluator),
+                        );
                     });
                 });
             }
@@ -155,7 +170,6 @@ fn bench_mcts_search_limited(c: &mut Criterion) {
     let g = g.expect("the json literal is valid");
     let snake_id_mapping = build_snake_id_map(&g);
     let compact: CellBoard4Snakes11x11 = g.as_cell_board(&snake_id_mapping).unwrap();
-    let you = compact.you_id().clone();
 
     let mut group = c.benchmark_group("mcts_search_limited");
     group.sample_size(10); // Reduce sample size for longer-running benchmarks
@@ -167,7 +181,10 @@ fn bench_mcts_search_limited(c: &mut Criterion) {
     group.bench_function("50_rollouts", |b| {
         b.iter(|| {
             for _ in 0..50 {
-                black_box(node.clone().rollout(black_box(&you)));
+                black_box(
+                    node.clone()
+                        .rollout(&MctsConfig::default(), &DefaultEvaluator),
+                );
             }
         });
     });
@@ -182,11 +199,10 @@ fn bench_node_creation(c: &mut Criterion) {
     let g = g.expect("the json literal is valid");
     let snake_id_mapping = build_snake_id_map(&g);
     let compact: CellBoard4Snakes11x11 = g.as_cell_board(&snake_id_mapping).unwrap();
-    let you = compact.you_id().clone();
 
     c.bench_function("node_creation", |b| {
         b.iter(|| {
-            black_box(Node::new_root(black_box(compact.clone())));
+            black_box(Node::new_root(black_box(compact)));
         })
     });
 }
@@ -4,52 +4,76 @@ use std::sync::{
 };
 use std::time::Duration;
 
-use battlesnake_game_types::{
-    compact_representation::standard::CellBoard4Snakes11x11,
-    types::{Move, SnakeId},
-};
+use battlesnake_game_types::hazard_algorithms::RoyaleHazard;
+use battlesnake_game_types::types::{FallibleSimulableGame, Move, SnakeId};
 
-use crate::mcts::{mcts_search, Node};
+use crate::config::MctsConfig;
+use crate::eval::{DefaultEvaluator, Evaluator};
+use crate::game::{Instruments, SearchableGame};
+use crate::mcts::{ensemble_best_move, parallel_search, Node, SearchMode};
 
-/// Trait that defines a snake agent's decision-making interface.
-pub trait Agent: Send + Sync {
+/// Trait that defines a snake agent's decision-making interface for boards of type `B`.
+pub trait Agent<B>: Send + Sync {
     /// Returns the name of this agent for display purposes.
     fn name(&self) -> &str;
 
     /// Choose a move given the current board state and the snake ID to play as.
-    fn choose_move(&self, board: &CellBoard4Snakes11x11, you: SnakeId) -> Move;
+    fn choose_move(&self, board: &B, you: SnakeId) -> Move;
+
+    /// Like [`Agent::choose_move`] in a royale game whose board shrinks with `hazards`, as of the
+    /// current board. Agents that don't look ahead ignore the shrinking
+    fn choose_move_with_hazards(
+        &self,
+        board: &B,
+        you: SnakeId,
+        _hazards: Option<RoyaleHazard>,
+    ) -> Move {
+        self.choose_move(board, you)
+    }
 
     /// Optional: Reset any internal state between games.
     fn reset(&mut self) {}
 }
 
-impl Agent for Box<dyn Agent> {
+impl<B> Agent<B> for Box<dyn Agent<B>> {
     fn name(&self) -> &str {
         (**self).name()
     }
 
-    fn choose_move(&self, board: &CellBoard4Snakes11x11, you: SnakeId) -> Move {
+    fn choose_move(&self, board: &B, you: SnakeId) -> Move {
         (**self).choose_move(board, you)
     }
 
+    fn choose_move_with_hazards(
+        &self,
+        board: &B,
+        you: SnakeId,
+        hazards: Option<RoyaleHazard>,
+    ) -> Move {
+        (**self).choose_move_with_hazards(board, you, hazards)
+    }
+
     fn reset(&mut self) {
         (**self).reset()
     }
 }
 
-/// The MCTS-based agent that uses Monte Carlo Tree Search.
-pub struct MctsAgent {
+/// The MCTS-based agent that uses Monte Carlo Tree Search on boards of type `B` with up to
+/// `N_SNAKES` snakes.
+pub struct MctsAgent<B, const N_SNAKES: usize> {
     name: String,
     think_time: Duration,
-    exploration_constant: f32,
+    config: MctsConfig,
+    evaluator: Arc<dyn Evaluator<B>>,
 }
 
-impl MctsAgent {
+impl<B: SearchableGame, const N_SNAKES: usize> MctsAgent<B, N_SNAKES> {
     pub fn new(think_time: Duration) -> Self {
         Self {
             name: "MCTS".to_string(),
             think_time,
-            exploration_constant: 0.0,
+            config: MctsConfig::default(),
+            evaluator: Arc::new(DefaultEvaluator),
         }
     }
 
@@ -57,42 +81,80 @@ impl MctsAgent {
         Self {
             name: name.into(),
             think_time,
-            exploration_constant: 0.0,
+            config: MctsConfig::default(),
+            evaluator: Arc::new(DefaultEvaluator),
         }
     }
+
+    /// Search with the given [`MctsConfig`]
+    pub fn with_config(mut self, config: MctsConfig) -> Self {
+        self.config = config;
+        self
+    }
+
+    /// Score the boards that aren't played out until the end with `evaluator`
+    pub fn with_evaluator(
+        mut self,
+        evaluator: impl Evaluator<B> + 'static,
+    ) -> Self {
+        self.evaluator = Arc::new(evaluator);
+        self
+    }
+
+    /// Use the given [`SearchMode`] to expand the search tree
+    pub fn with_search_mode(mut self, search_mode: SearchMode) -> Self {
+        self.config.search_mode = search_mode;
+        self
+    }
 }
 
-impl Default for MctsAgent {
+impl<B: SearchableGame, const N_SNAKES: usize> Default for MctsAgent<B, N_SNAKES> {
     fn default() -> Self {
         Self::new(Duration::from_millis(100))
     }
 }
 
-impl Agent for MctsAgent {
+impl<B, const N_SNAKES: usize> Agent<B> for MctsAgent<B, N_SNAKES>
+where
+    B: SearchableGame + FallibleSimulableGame<Instruments, N_SNAKES>,
+{
     fn name(&self) -> &str {
         &self.name
     }
 
-    fn choose_move(&self, board: &CellBoard4Snakes11x11, you: SnakeId) -> Move {
-        let root_node = Arc::new(Node::new_root(*board));
+    fn choose_move(&self, board: &B, you: SnakeId) -> Move {
+        self.choose_move_with_hazards(board, you, None)
+    }
+
+    fn choose_move_with_hazards(
+        &self,
+        board: &B,
+        you: SnakeId,
+        hazards: Option<RoyaleHazard>,
+    ) -> Move {
+        let roots: Vec<_> = (0..self.config.trees())
+            .map(|_| Arc::new(Node::new_root_with_config(*board, &self.config)))
+            .collect();
+        for root in &roots {
+            root.set_hazards(hazards);
+        }
         let stop = Arc::new(AtomicBool::new(false));
 
         let stop_clone = Arc::clone(&stop);
-        let root_clone = Arc::clone(&root_node);
+        let roots_clone = roots.clone();
 
+        let config = self.config;
+        let evaluator = Arc::clone(&self.evaluator);
         let search_thread = std::thread::spawn(move || {
-            mcts_search(root_clone, &you, stop_clone);
+            parallel_search(&roots_clone, &you, stop_clone, &config, &*evaluator);
         });
 
         std::thread::sleep(self.think_time);
         stop.store(true, Ordering::Relaxed);
         let _ = search_thread.join();
 
-        if let Some((action, _)) = root_node.best_child(self.exploration_constant) {
-            let moves = action.into_inner();
-            if let Some(mv) = moves[you.0 as usize] {
-                return mv;
-            }
+        if let Some(mv) = ensemble_best_move(&roots, &you, self.config.final_move_policy) {
+            return mv;
         }
 
         Move::Up
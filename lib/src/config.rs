@@ -0,0 +1,180 @@
+//! Tunable parameters of the search, so they can be changed without recompiling
+
+use std::str::FromStr;
+
+use serde::{Deserialize, de::IntoDeserializer};
+
+use crate::mcts::SearchMode;
+use crate::reward::{OpponentModel, RewardKind};
+use crate::selection::FinalMovePolicy;
+
+/// How the moves of the snakes are picked during a rollout
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RolloutPolicy {
+    /// Every snake plays a uniformly random reasonable move
+    #[default]
+    Random,
+    /// Every snake plays the best move of a one-ply heuristic, or a random one with a chance of
+    /// `rollout_epsilon`
+    EpsilonGreedy,
+    /// Random moves for `rollout_cutoff` turns, then the board evaluation scores the survivors
+    Cutoff,
+}
+
+/// How the `threads` of a search share the work
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ParallelMode {
+    /// All threads search the same tree, spread out by virtual loss
+    #[default]
+    Tree,
+    /// Every thread searches its own tree of the same board, the trees vote on the move at the end
+    Root,
+}
+
+/// Parameters of [`crate::mcts::mcts_search`]. Missing fields take their default value when
+/// deserializing, so a config only has to name what it changes
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct MctsConfig {
+    /// The `c` of UCB1 used while descending the tree
+    pub exploration_constant: f32,
+    /// Maximum number of turns simulated in a single rollout
+    pub rollout_depth: u32,
+    pub rollout_policy: RolloutPolicy,
+    /// Chance of a random move with [`RolloutPolicy::EpsilonGreedy`]
+    pub rollout_epsilon: f32,
+    /// Number of turns played before scoring the board with [`RolloutPolicy::Cutoff`]
+    pub rollout_cutoff: u32,
+    pub final_move_policy: FinalMovePolicy,
+    /// The mode new search trees are created with
+    pub search_mode: SearchMode,
+    pub reward_kind: RewardKind,
+    pub opponent_model: OpponentModel,
+    /// Number of threads searching, see [`ParallelMode`]
+    pub threads: usize,
+    pub parallel_mode: ParallelMode,
+    /// Losses added to every node on the path of a running search, so concurrent searches spread
+    /// out over the tree. 0 disables it
+    pub virtual_loss: u32,
+    /// Maximum number of nodes added to the tree in a single search, rollouts still continue
+    /// from the leaves once it is reached
+    pub node_budget: Option<usize>,
+    /// Shrink the board of royale games during rollouts, with the hazards set on the root by
+    /// [`crate::mcts::Node::set_hazards`]
+    pub royale_shrinking: bool,
+    /// Spawn food after every turn with the food settings of the game, in rollouts and on the
+    /// path through the tree, which makes the search open-loop. Off keeps the search free of
+    /// chance apart from the moves
+    pub spawn_food: bool,
+    /// Number of buckets of the transposition table every tree gets, nodes of the same position
+    /// then share their statistics no matter which move order reached them. Every bucket holds
+    /// up to two positions. Positions only tell healths of 10 and more apart by steps of 10, so
+    /// snakes with a few points of health more or less share a node. None searches a pure tree
+    pub transposition_table: Option<usize>,
+}
+
+impl MctsConfig {
+    /// The number of trees a search with this config needs
+    pub fn trees(&self) -> usize {
+        match self.parallel_mode {
+            ParallelMode::Tree => 1,
+            ParallelMode::Root => self.threads.max(1),
+        }
+    }
+}
+
+impl Default for MctsConfig {
+    fn default() -> Self {
+        Self {
+            exploration_constant: 1.4,
+            rollout_depth: 50,
+            rollout_policy: RolloutPolicy::default(),
+            rollout_epsilon: 0.2,
+            rollout_cutoff: 5,
+            final_move_policy: FinalMovePolicy::default(),
+            search_mode: SearchMode::default(),
+            reward_kind: RewardKind::default(),
+            opponent_model: OpponentModel::default(),
+            threads: 1,
+            parallel_mode: ParallelMode::default(),
+            virtual_loss: 1,
+            node_budget: None,
+            royale_shrinking: false,
+            spawn_food: false,
+            transposition_table: None,
+        }
+    }
+}
+
+/// Parses the enums of the config from their snake_case name, e.g. for CLI flags
+macro_rules! impl_from_str_via_serde {
+    ($($ty:ty),*) => {
+        $(
+            impl FromStr for $ty {
+                type Err = serde::de::value::Error;
+
+                fn from_str(s: &str) -> Result<Self, Self::Err> {
+                    Self::deserialize(s.into_deserializer())
+                }
+            }
+        )*
+    };
+}
+
+impl_from_str_via_serde!(
+    RolloutPolicy,
+    FinalMovePolicy,
+    SearchMode,
+    RewardKind,
+    OpponentModel,
+    ParallelMode
+);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde::de::value::{Error, MapDeserializer};
+
+    #[test]
+    fn test_enums_parse_from_snake_case() {
+        assert_eq!(
+            "max_robust".parse::<FinalMovePolicy>().unwrap(),
+            FinalMovePolicy::MaxRobust
+        );
+        assert_eq!(
+            "decoupled".parse::<SearchMode>().unwrap(),
+            SearchMode::Decoupled
+        );
+        assert_eq!(
+            "placement".parse::<RewardKind>().unwrap(),
+            RewardKind::Placement
+        );
+        assert_eq!(
+            "paranoid".parse::<OpponentModel>().unwrap(),
+            OpponentModel::Paranoid
+        );
+        assert_eq!("root".parse::<ParallelMode>().unwrap(), ParallelMode::Root);
+        assert_eq!(
+            "epsilon_greedy".parse::<RolloutPolicy>().unwrap(),
+            RolloutPolicy::EpsilonGreedy
+        );
+        assert!("sideways".parse::<RolloutPolicy>().is_err());
+    }
+
+    #[test]
+    fn test_missing_fields_are_defaulted() {
+        let fields = [("rollout_depth", 20u32), ("threads", 4)];
+        let config =
+            MctsConfig::deserialize(MapDeserializer::<_, Error>::new(fields.into_iter())).unwrap();
+        assert_eq!(
+            config,
+            MctsConfig {
+                rollout_depth: 20,
+                threads: 4,
+                ..MctsConfig::default()
+            }
+        );
+    }
+}
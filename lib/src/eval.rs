@@ -1,71 +1,187 @@
-use battlesnake_game_types::{
-    compact_representation::standard::CellBoard4Snakes11x11,
-    types::{
-        FoodGettableGame, HeadGettableGame, HealthGettableGame, LengthGettableGame,
-        NeighborDeterminableGame, PositionGettableGame, SnakeId,
-    },
-    wire_representation::Position,
-};
-use std::collections::{HashSet, VecDeque};
+//! Board evaluations, used to score boards that aren't played out until the end
 
-/// Manhattan distance between two positions
-fn manhattan_distance(a: &Position, b: &Position) -> i32 {
-    (a.x - b.x).abs() + (a.y - b.y).abs()
+use battlesnake_game_types::{types::SnakeId, wire_representation::Position};
+use serde::Deserialize;
+
+use crate::game::SearchableGame;
+
+pub mod territory;
+
+use territory::{flood_fill, voronoi};
+
+/// Scores a board of type `B` from the perspective of a single snake
+pub trait Evaluator<B>: Send + Sync {
+    /// The value of `board` for `you` within `[0, 1]`, higher is better and a dead snake is
+    /// worth 0. Staying in this range lets the evaluation be used as the reward of a rollout
+    fn evaluate(&self, board: &B, you: &SnakeId) -> f32;
+}
+
+impl<B, E: Evaluator<B> + ?Sized> Evaluator<B> for &E {
+    fn evaluate(&self, board: &B, you: &SnakeId) -> f32 {
+        (**self).evaluate(board, you)
+    }
 }
 
-/// Flood fill to count reachable cells from a starting position
-/// This is critical for survival - we need to know how much space we can access
-fn flood_fill(board: &CellBoard4Snakes11x11, start_pos: Position) -> u32 {
-    let mut visited = HashSet::new();
-    let mut queue = VecDeque::new();
+impl<B, E: Evaluator<B> + ?Sized> Evaluator<B> for Box<E> {
+    fn evaluate(&self, board: &B, you: &SnakeId) -> f32 {
+        (**self).evaluate(board, you)
+    }
+}
 
-    let start_native = board.native_from_position(start_pos);
-    queue.push_back(start_native);
-    visited.insert(start_native);
+impl<B, E: Evaluator<B> + ?Sized> Evaluator<B> for std::sync::Arc<E> {
+    fn evaluate(&self, board: &B, you: &SnakeId) -> f32 {
+        (**self).evaluate(board, you)
+    }
+}
 
-    let mut count = 0;
-    const MAX_ITERATIONS: u32 = 121; // Max cells on 11x11 board
+/// [`evaluate_board_normalized`] as an [`Evaluator`]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct DefaultEvaluator;
 
-    while let Some(pos) = queue.pop_front() {
-        count += 1;
+impl<B: SearchableGame> Evaluator<B> for DefaultEvaluator {
+    fn evaluate(&self, board: &B, you: &SnakeId) -> f32 {
+        evaluate_board_normalized(board, you)
+    }
+}
 
-        // Safety check to prevent infinite loops
-        if count >= MAX_ITERATIONS {
-            break;
+/// A weighted sum of board features, squashed into `[0, 1]` with a logistic function. Every
+/// feature is roughly scaled to `[-1, 1]` so the weights are comparable.
+/// Missing weights take their default value when deserializing
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct WeightedEvaluator {
+    /// Health out of 100
+    pub health: f32,
+    /// Length compared to the longest opponent
+    pub length: f32,
+    /// Number of cells next to the head that aren't off the board
+    pub mobility: f32,
+    /// Share of the board reachable from the head
+    pub space: f32,
+    /// Share of the board reached before every other snake, see [`territory::voronoi`]
+    pub territory: f32,
+    /// Distance to the closest food, only while below `hunger_threshold` health
+    pub food: f32,
+    /// Distance to the center of the board
+    pub center: f32,
+    /// Opponent heads next to ours, negative for the ones at least as long as us
+    pub head_to_head: f32,
+    pub hunger_threshold: u8,
+}
+
+impl Default for WeightedEvaluator {
+    fn default() -> Self {
+        Self {
+            health: 0.5,
+            length: 1.0,
+            mobility: 0.5,
+            space: 1.0,
+            territory: 2.0,
+            food: 1.0,
+            center: 0.2,
+            head_to_head: 1.0,
+            hunger_threshold: 40,
+        }
+    }
+}
+
+impl<B: SearchableGame> Evaluator<B> for WeightedEvaluator {
+    fn evaluate(&self, board: &B, you: &SnakeId) -> f32 {
+        if !board.is_alive(you) {
+            return 0.0;
         }
+        let health = board.get_health_i64(you);
+        let head = board.get_head_as_position(you);
+        let length = board.get_length_i64(you) as i32;
 
-        // Get all valid neighboring positions
-        for neighbor in board.neighbors(&pos) {
-            if !visited.contains(&neighbor) {
-                visited.insert(neighbor);
-                queue.push_back(neighbor);
+        let mut longest_opponent = 0;
+        let mut head_to_head = 0.0;
+        for opponent in board.get_snake_ids() {
+            if opponent == *you {
+                continue;
+            }
+            let opponent_length = board.get_length_i64(&opponent) as i32;
+            longest_opponent = longest_opponent.max(opponent_length);
+            if distance(board, &head, &board.get_head_as_position(&opponent)) == 1 {
+                head_to_head += if opponent_length >= length { -1.0 } else { 0.5 };
             }
         }
+
+        let mobility = board
+            .neighbors(&board.get_head_as_native_position(you))
+            .filter(|pos| !board.position_is_snake_body(pos.clone()))
+            .count() as f32;
+        let cells = (board.get_width() * board.get_height()) as f32;
+        let space = flood_fill(board, head) as f32 / cells;
+        let territory = voronoi(board).share(you);
+        let food = if health < i64::from(self.hunger_threshold) {
+            board
+                .get_all_food_as_positions()
+                .iter()
+                .map(|food| distance(board, &head, food))
+                .min()
+                .map_or(0.0, |distance| -(distance as f32) / 20.0)
+        } else {
+            0.0
+        };
+        // Every cell of a wrapped board is as central as any other
+        let center = if board.is_wrapped() {
+            0.0
+        } else {
+            let middle = Position::new(board.get_width() as i32 / 2, board.get_height() as i32 / 2);
+            -(manhattan_distance(&head, &middle) as f32) / (middle.x + middle.y) as f32
+        };
+
+        let score = self.health * (health as f32 / 100.0)
+            + self.length * ((length - longest_opponent) as f32 / 10.0).clamp(-1.0, 1.0)
+            + self.mobility * (mobility / 4.0)
+            + self.space * space
+            + self.territory * territory
+            + self.food * food
+            + self.center * center
+            + self.head_to_head * head_to_head;
+        1.0 / (1.0 + (-score).exp())
     }
+}
 
-    count
+/// Manhattan distance between two positions
+pub(crate) fn manhattan_distance(a: &Position, b: &Position) -> i32 {
+    (a.x - b.x).abs() + (a.y - b.y).abs()
+}
+
+/// Manhattan distance between two positions of `board`, which on wrapped boards may go across
+/// the edges. The positions may lie one step off the board
+pub(crate) fn distance(board: &impl SearchableGame, a: &Position, b: &Position) -> i32 {
+    if !board.is_wrapped() {
+        return manhattan_distance(a, b);
+    }
+    let width = board.get_width() as i32;
+    let height = board.get_height() as i32;
+    let dx = (a.x - b.x).rem_euclid(width);
+    let dy = (a.y - b.y).rem_euclid(height);
+    dx.min(width - dx) + dy.min(height - dy)
 }
 
 /// Lightweight evaluation function optimized for MCTS (called millions of times)
 /// This version avoids expensive operations like flood fill
-pub fn evaluate_board(cellboard: &CellBoard4Snakes11x11, you: &SnakeId) -> u16 {
+pub fn evaluate_board(cellboard: &impl SearchableGame, you: &SnakeId) -> u16 {
     // Check if we're dead - return worst score
-    if cellboard.get_health(you) == 0 {
+    if !cellboard.is_alive(you) {
         return 0;
     }
 
     let mut score: i32 = 500; // Start with baseline score
 
     // 1. Health consideration (critical when low)
-    let health = cellboard.get_health(you);
+    let health = cellboard.get_health_i64(you) as i32;
     if health < 30 {
-        score -= (30 - health as i32) * 5; // Penalty for low health
+        score -= (30 - health) * 5; // Penalty for low health
     } else {
-        score += (health as i32).min(50) / 10; // Small bonus for good health
+        score += health.min(50) / 10; // Small bonus for good health
     }
 
     // 2. Length advantage (longer is better)
-    let my_length = cellboard.get_length(you) as i32;
+    let my_length = cellboard.get_length_i64(you) as i32;
     score += my_length * 15;
 
     // 3. Immediate mobility (number of valid moves from head) - fast approximation of space
@@ -80,7 +196,7 @@ pub fn evaluate_board(cellboard: &CellBoard4Snakes11x11, you: &SnakeId) -> u16 {
         if !food_positions.is_empty() {
             let min_food_dist = food_positions
                 .iter()
-                .map(|food| manhattan_distance(&head_pos, food))
+                .map(|food| distance(cellboard, &head_pos, food))
                 .min()
                 .unwrap_or(0);
 
@@ -89,36 +205,36 @@ pub fn evaluate_board(cellboard: &CellBoard4Snakes11x11, you: &SnakeId) -> u16 {
         }
     }
 
-    // 5. Center control (middle of board is strategically valuable)
+    // 5. Center control (middle of board is strategically valuable), wrapped boards have none
     let head_pos = cellboard.get_head_as_position(you);
-    let center_dist = (head_pos.x - 5).abs() + (head_pos.y - 5).abs();
-    score -= center_dist;
+    if !cellboard.is_wrapped() {
+        let center = Position::new(
+            cellboard.get_width() as i32 / 2,
+            cellboard.get_height() as i32 / 2,
+        );
+        let center_dist = manhattan_distance(&head_pos, &center);
+        score -= center_dist;
+    }
 
     // 6. Opponent awareness - avoid dangerous head-to-head collisions
-    for opponent_id in 0..4 {
-        let opp_id = SnakeId(opponent_id);
+    for opp_id in cellboard.get_snake_ids() {
         if opp_id == *you {
             continue;
         }
 
-        let opp_health = cellboard.get_health(&opp_id);
-        if opp_health == 0 {
-            continue;
-        }
-
         let opp_head = cellboard.get_head_as_position(&opp_id);
-        let opp_length = cellboard.get_length(&opp_id);
-        let dist_to_opponent = manhattan_distance(&head_pos, &opp_head);
+        let opp_length = cellboard.get_length_i64(&opp_id) as i32;
+        let dist_to_opponent = distance(cellboard, &head_pos, &opp_head);
 
         if dist_to_opponent == 1 {
-            if opp_length >= my_length as u16 {
+            if opp_length >= my_length {
                 score -= 100; // Avoid head-to-head with larger snakes
             } else {
                 score += 30; // Bonus for potential head-to-head win
             }
         }
 
-        if my_length > opp_length as i32 {
+        if my_length > opp_length {
             score += 3; // Bonus for being longer
         }
     }
@@ -127,10 +243,22 @@ pub fn evaluate_board(cellboard: &CellBoard4Snakes11x11, you: &SnakeId) -> u16 {
     score.max(1).min(u16::MAX as i32) as u16
 }
 
+/// [`evaluate_board`] mapped into `[0, 1)`, the baseline score of 500 maps to 0.5
+pub fn evaluate_board_normalized(cellboard: &impl SearchableGame, you: &SnakeId) -> f32 {
+    let score = evaluate_board(cellboard, you) as f32;
+    score / (score + 500.0)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
-    use battlesnake_game_types::{types::build_snake_id_map, wire_representation::Game};
+    use battlesnake_game_types::{
+        compact_representation::{
+            standard::CellBoard4Snakes11x11, wrapped::CellBoard4SnakesSquare11x11,
+        },
+        types::{SnakeIDGettableGame, build_snake_id_map},
+        wire_representation::Game,
+    };
 
     #[test]
     fn test_evaluate_dead_snake() {
@@ -158,6 +286,74 @@ mod tests {
         assert_eq!(manhattan_distance(&p3, &p4), 0);
     }
 
+    #[test]
+    fn test_distance_wraps_around_the_edges() {
+        let game_fixture =
+            include_str!("../../battlesnake-game-types/fixtures/debug_wrapped/debug_game_1.json");
+        let game: Game = serde_json::from_str(game_fixture).expect("valid fixture");
+        let snake_id_map = build_snake_id_map(&game);
+        let wrapped: CellBoard4SnakesSquare11x11 = game
+            .as_wrapped_cell_board(&snake_id_map)
+            .expect("valid board");
+        let mut game = game;
+        game.game.ruleset.name = "standard".to_string();
+        let standard: CellBoard4Snakes11x11 =
+            game.as_cell_board(&snake_id_map).expect("valid board");
+
+        let corner = Position::new(0, 0);
+        let opposite = Position::new(10, 10);
+        assert_eq!(distance(&standard, &corner, &opposite), 20);
+        assert_eq!(distance(&wrapped, &corner, &opposite), 2);
+        assert_eq!(
+            distance(&wrapped, &Position::new(-1, 5), &Position::new(10, 5)),
+            0
+        );
+        assert_eq!(
+            distance(&wrapped, &Position::new(2, 3), &Position::new(5, 7)),
+            7
+        );
+    }
+
+    #[test]
+    fn test_evaluators_stay_in_range_on_wrapped_boards() {
+        let evaluators: [&dyn Evaluator<CellBoard4SnakesSquare11x11>; 2] =
+            [&DefaultEvaluator, &WeightedEvaluator::default()];
+        for fixture in [
+            include_str!("../../battlesnake-game-types/fixtures/debug_wrapped/debug_game_1.json"),
+            include_str!("../../battlesnake-game-types/fixtures/debug_wrapped/debug_game_50.json"),
+            include_str!("../../battlesnake-game-types/fixtures/debug_wrapped/debug_game_150.json"),
+        ] {
+            let game: Game = serde_json::from_str(fixture).expect("valid fixture");
+            let snake_id_map = build_snake_id_map(&game);
+            let board: CellBoard4SnakesSquare11x11 = game
+                .as_wrapped_cell_board(&snake_id_map)
+                .expect("valid board");
+            for sid in board.get_snake_ids() {
+                for evaluator in evaluators {
+                    let value = evaluator.evaluate(&board, &sid);
+                    assert!(value > 0.0 && value < 1.0, "got {value} for {sid:?}");
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_evaluators_stay_in_range() {
+        let game_fixture = include_str!("../../battlesnake-game-types/fixtures/4_snake_game.json");
+        let game: Game = serde_json::from_str(game_fixture).expect("valid fixture");
+        let snake_id_map = build_snake_id_map(&game);
+        let board: CellBoard4Snakes11x11 = game.as_cell_board(&snake_id_map).expect("valid board");
+
+        let evaluators: [&dyn Evaluator<CellBoard4Snakes11x11>; 2] =
+            [&DefaultEvaluator, &WeightedEvaluator::default()];
+        for evaluator in evaluators {
+            for sid in (0..4).map(SnakeId) {
+                let value = evaluator.evaluate(&board, &sid);
+                assert!(value > 0.0 && value < 1.0, "got {value} for {sid:?}");
+            }
+        }
+    }
+
     #[test]
     fn test_evaluate_board_basic() {
         let game_fixture = include_str!("../../battlesnake-game-types/fixtures/start_of_game.json");
@@ -0,0 +1,478 @@
+//! Space control, computed with bitsets of cells instead of hash sets
+//!
+//! On boards of up to 128 cells, cell `y * width + x` is bit `y * width + x` of a `u128`, so a
+//! whole layer of a BFS is expanded with a handful of shifts. On wrapped boards a few more shifts
+//! carry the cells of each edge over to the opposite one. Larger boards fall back to a BFS over
+//! the neighbors the board reports.
+
+use battlesnake_game_types::{types::SnakeId, wire_representation::Position};
+
+use crate::game::SearchableGame;
+
+/// The most snakes any board of `battlesnake_game_types` holds
+const MAX_SNAKES: usize = 16;
+
+/// A set of cells of the board
+type CellSet = u128;
+
+/// The layout of a board that fits into a [`CellSet`]
+struct Grid {
+    width: i32,
+    /// Distance between a cell of the bottom row and the cell above it in the top row
+    top_row: u32,
+    board: CellSet,
+    /// Shifting a cell of the rightmost column right lands in the leftmost column of the next row,
+    /// these masks remove such wrapped cells
+    left_column: CellSet,
+    right_column: CellSet,
+    wrapped: bool,
+}
+
+impl Grid {
+    fn new(width: u32, height: u32, wrapped: bool) -> Option<Self> {
+        let cells = width * height;
+        if cells > CellSet::BITS {
+            return None;
+        }
+        let top_row = cells - width;
+        let width = width as i32;
+        let column_mask =
+            |x: i32| (0..height as i32).fold(0, |mask: CellSet, y| mask | 1 << (y * width + x));
+        Some(Self {
+            width,
+            top_row,
+            board: CellSet::MAX >> (CellSet::BITS - cells),
+            left_column: column_mask(0),
+            right_column: column_mask(width - 1),
+            wrapped,
+        })
+    }
+
+    fn for_board(board: &impl SearchableGame) -> Option<Self> {
+        Self::new(board.get_width(), board.get_height(), board.is_wrapped())
+    }
+
+    fn cell(&self, pos: Position) -> CellSet {
+        1 << (pos.y * self.width + pos.x)
+    }
+
+    /// The cells next to any cell of `cells`
+    fn neighbors(&self, cells: CellSet) -> CellSet {
+        let right = (cells << 1) & !self.left_column;
+        let left = (cells >> 1) & !self.right_column;
+        let up = cells << self.width;
+        let down = cells >> self.width;
+        let mut neighbors = right | left | up | down;
+        if self.wrapped {
+            let width = self.width - 1;
+            neighbors |= (cells & self.right_column) >> width
+                | (cells & self.left_column) << width
+                | cells >> self.top_row
+                | cells << self.top_row;
+        }
+        neighbors & self.board
+    }
+}
+
+/// The number of cells each snake reaches before every other snake, the result of a simultaneous
+/// BFS from all heads
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Territory {
+    sizes: [u32; MAX_SNAKES],
+    cells: u32,
+}
+
+impl Territory {
+    /// Number of cells `sid` owns, including its head. 0 for dead snakes
+    pub fn size(&self, sid: &SnakeId) -> u32 {
+        self.sizes.get(sid.as_usize()).copied().unwrap_or(0)
+    }
+
+    /// The share of the board `sid` owns
+    pub fn share(&self, sid: &SnakeId) -> f32 {
+        self.size(sid) as f32 / self.cells as f32
+    }
+}
+
+/// A living snake as the BFS sees it
+struct Snake {
+    id: usize,
+    head: Position,
+    length: i64,
+}
+
+/// The living snakes of `board`, and for every cell `y * width + x` the turn after which it's no
+/// longer occupied by a body segment: the segment `i` cells behind the head of a snake of length
+/// `l` is free after `l - i` turns
+fn obstacles(board: &impl SearchableGame) -> (Vec<Snake>, Vec<usize>) {
+    let width = board.get_width() as i32;
+    let cells = (board.get_width() * board.get_height()) as usize;
+    let mut free_after = vec![0; cells];
+    let mut snakes = Vec::new();
+    for sid in board.get_snake_ids() {
+        let body = board.get_snake_body_vec(&sid);
+        let length = body.len();
+        for (i, native) in body.into_iter().enumerate() {
+            let pos = board.position_from_native(native);
+            let index = (pos.y * width + pos.x) as usize;
+            free_after[index] = free_after[index].max((length - i).min(cells));
+        }
+        snakes.push(Snake {
+            id: sid.as_usize(),
+            head: board.get_head_as_position(&sid),
+            length: board.get_length_i64(&sid),
+        });
+    }
+    (snakes, free_after)
+}
+
+/// Computes the Voronoi territory of every snake on `board`.
+///
+/// All heads expand one cell per turn at the same time. Body segments block the way until the
+/// tail has moved past them. A cell that several snakes reach in the same turn goes to the
+/// strictly longest of them, like a head-to-head collision, and to nobody if they're equally long.
+pub fn voronoi(board: &impl SearchableGame) -> Territory {
+    match Grid::for_board(board) {
+        Some(grid) => voronoi_bitset(board, &grid),
+        None => voronoi_bfs(board),
+    }
+}
+
+fn voronoi_bitset(board: &impl SearchableGame, grid: &Grid) -> Territory {
+    let (snakes, free_after) = obstacles(board);
+    let mut territory = [0 as CellSet; MAX_SNAKES];
+    let mut lengths = [0; MAX_SNAKES];
+    for snake in &snakes {
+        territory[snake.id] = grid.cell(snake.head);
+        lengths[snake.id] = snake.length;
+    }
+    let snake_count = snakes.iter().map(|snake| snake.id + 1).max().unwrap_or(0);
+
+    // Cells that become free in each turn
+    let last_release = free_after.iter().copied().max().unwrap_or(0);
+    let mut released = vec![0 as CellSet; last_release + 1];
+    let mut free = grid.board;
+    for (index, turn) in free_after.into_iter().enumerate() {
+        if turn > 0 {
+            free &= !(1 << index);
+            released[turn] |= 1 << index;
+        }
+    }
+
+    let mut claimed: CellSet = territory.iter().fold(0, |acc, cells| acc | cells);
+    for turn in 1.. {
+        free |= released.get(turn).copied().unwrap_or(0);
+        let mut reached = [0 as CellSet; MAX_SNAKES];
+        for sid in 0..snake_count {
+            reached[sid] = grid.neighbors(territory[sid]) & free & !claimed;
+        }
+        let any_reached = reached.iter().fold(0, |acc, cells| acc | cells);
+        if any_reached == 0 {
+            if turn >= last_release {
+                break;
+            }
+            continue;
+        }
+
+        for sid in 0..snake_count {
+            // Cells that a snake at least as long reaches in the same turn are lost
+            let lost = (0..snake_count)
+                .filter(|other| *other != sid && lengths[*other] >= lengths[sid])
+                .fold(0, |acc, other| acc | reached[other]);
+            territory[sid] |= reached[sid] & !lost;
+        }
+        claimed |= any_reached;
+    }
+
+    Territory {
+        sizes: std::array::from_fn(|sid| territory[sid].count_ones()),
+        cells: grid.board.count_ones(),
+    }
+}
+
+/// Nobody reached the cell yet
+const UNCLAIMED: u8 = u8::MAX;
+/// Several equally long snakes reached the cell in the same turn
+const CONTESTED: u8 = u8::MAX - 1;
+
+/// [`voronoi`] for boards too large for a [`CellSet`], one BFS layer at a time
+fn voronoi_bfs<B: SearchableGame>(board: &B) -> Territory {
+    let width = board.get_width() as i32;
+    let (snakes, free_after) = obstacles(board);
+    let cells = free_after.len();
+    let index = |pos: Position| (pos.y * width + pos.x) as usize;
+    let neighbors = |cell: usize| {
+        let pos = Position::new(cell as i32 % width, cell as i32 / width);
+        board
+            .neighbors(&board.native_from_position(pos))
+            .map(|native| index(board.position_from_native(native)))
+    };
+
+    let mut owners = vec![UNCLAIMED; cells];
+    let mut sizes = [0; MAX_SNAKES];
+    let mut lengths = [0; MAX_SNAKES];
+    let mut frontier = Vec::new();
+    for snake in &snakes {
+        owners[index(snake.head)] = snake.id as u8;
+        sizes[snake.id] = 1;
+        lengths[snake.id] = snake.length;
+        frontier.push((snake.id, index(snake.head)));
+    }
+
+    let last_release = free_after.iter().copied().max().unwrap_or(0);
+    let mut released = vec![Vec::new(); last_release + 1];
+    for (cell, turn) in free_after.iter().enumerate() {
+        if *turn > 0 {
+            released[*turn].push(cell);
+        }
+    }
+
+    // The snakes that reach each cell in the current turn, as a bitmask
+    let mut reached_by = vec![0u16; cells];
+    let mut reached = Vec::new();
+    for turn in 1.. {
+        let mut reach = |sid: usize, cell: usize| {
+            if owners[cell] == UNCLAIMED && free_after[cell] <= turn {
+                if reached_by[cell] == 0 {
+                    reached.push(cell);
+                }
+                reached_by[cell] |= 1 << sid;
+            }
+        };
+        for (sid, cell) in frontier.drain(..) {
+            neighbors(cell).for_each(|next| reach(sid, next));
+        }
+        // A cell that becomes free now can be entered from any cell that was already owned
+        for cell in released.get(turn).into_iter().flatten() {
+            for next in neighbors(*cell) {
+                if (owners[next] as usize) < MAX_SNAKES {
+                    reach(owners[next] as usize, *cell);
+                }
+            }
+        }
+        if reached.is_empty() {
+            if turn >= last_release {
+                break;
+            }
+            continue;
+        }
+
+        for cell in reached.drain(..) {
+            let mask = std::mem::take(&mut reached_by[cell]);
+            let contenders = || (0..MAX_SNAKES).filter(|sid| mask & (1 << sid) != 0);
+            let longest = contenders().map(|sid| lengths[sid]).max().unwrap_or(0);
+            let mut winners = contenders().filter(|sid| lengths[*sid] == longest);
+            match (winners.next(), winners.next()) {
+                (Some(sid), None) => {
+                    owners[cell] = sid as u8;
+                    sizes[sid] += 1;
+                    frontier.push((sid, cell));
+                }
+                _ => owners[cell] = CONTESTED,
+            }
+        }
+    }
+
+    Territory {
+        sizes,
+        cells: cells as u32,
+    }
+}
+
+/// Counts the cells reachable from `start`, snake bodies block the way
+pub fn flood_fill(board: &impl SearchableGame, start: Position) -> u32 {
+    let Some(grid) = Grid::for_board(board) else {
+        return flood_fill_bfs(board, start);
+    };
+    let mut free = grid.board;
+    for sid in board.get_snake_ids() {
+        for native in board.get_snake_body_iter(&sid) {
+            free &= !grid.cell(board.position_from_native(native));
+        }
+    }
+
+    let mut reached = grid.cell(start);
+    loop {
+        let next = reached | (grid.neighbors(reached) & free);
+        if next == reached {
+            return reached.count_ones();
+        }
+        reached = next;
+    }
+}
+
+/// [`flood_fill`] for boards too large for a [`CellSet`]
+fn flood_fill_bfs<B: SearchableGame>(board: &B, start: Position) -> u32 {
+    let width = board.get_width() as i32;
+    let index = |pos: Position| (pos.y * width + pos.x) as usize;
+    let mut blocked = vec![false; (board.get_width() * board.get_height()) as usize];
+    for sid in board.get_snake_ids() {
+        for native in board.get_snake_body_iter(&sid) {
+            blocked[index(board.position_from_native(native))] = true;
+        }
+    }
+
+    let mut reached = 1;
+    blocked[index(start)] = true;
+    let mut frontier = vec![board.native_from_position(start)];
+    while let Some(cell) = frontier.pop() {
+        for next in board.neighbors(&cell) {
+            let next_index = index(board.position_from_native(next.clone()));
+            if !blocked[next_index] {
+                blocked[next_index] = true;
+                reached += 1;
+                frontier.push(next);
+            }
+        }
+    }
+    reached
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use battlesnake_game_types::{
+        compact_representation::{
+            standard::{CellBoard4Snakes11x11, CellBoard8Snakes25x25},
+            wrapped::CellBoard4SnakesSquare11x11,
+        },
+        types::{HeadGettableGame, SnakeIDGettableGame, build_snake_id_map},
+        wire_representation::Game,
+    };
+
+    const CELLS: u32 = 121;
+
+    fn board_from(fixture: &str) -> CellBoard4Snakes11x11 {
+        let game: Game = serde_json::from_str(fixture).expect("valid fixture");
+        let snake_id_map = build_snake_id_map(&game);
+        game.as_cell_board(&snake_id_map).expect("valid board")
+    }
+
+    #[test]
+    fn test_neighbors_stay_in_their_row() {
+        let grid = Grid::new(11, 11, false).unwrap();
+        let corner = grid.cell(Position::new(10, 0));
+        assert_eq!(
+            grid.neighbors(corner),
+            grid.cell(Position::new(9, 0)) | grid.cell(Position::new(10, 1))
+        );
+        let edge = grid.cell(Position::new(0, 5));
+        assert_eq!(grid.neighbors(edge).count_ones(), 3);
+        assert_eq!(
+            grid.neighbors(grid.cell(Position::new(5, 10))).count_ones(),
+            3
+        );
+        assert!(Grid::new(12, 11, false).is_none());
+    }
+
+    #[test]
+    fn test_wrapped_neighbors_cross_the_edges() {
+        let grid = Grid::new(11, 11, true).unwrap();
+        let corner = grid.cell(Position::new(10, 0));
+        assert_eq!(
+            grid.neighbors(corner),
+            grid.cell(Position::new(9, 0))
+                | grid.cell(Position::new(0, 0))
+                | grid.cell(Position::new(10, 1))
+                | grid.cell(Position::new(10, 10))
+        );
+        let everywhere = grid.cell(Position::new(0, 5)) | grid.cell(Position::new(5, 10));
+        assert_eq!(grid.neighbors(everywhere).count_ones(), 8);
+    }
+
+    #[test]
+    fn test_territories_are_disjoint() {
+        let board = board_from(include_str!(
+            "../../../battlesnake-game-types/fixtures/4_snake_game.json"
+        ));
+        let territory = voronoi(&board);
+
+        let sizes: Vec<_> = (0..4).map(|sid| territory.size(&SnakeId(sid))).collect();
+        assert!(sizes.iter().all(|size| *size > 0), "{sizes:?}");
+        assert!(sizes.iter().sum::<u32>() <= CELLS);
+        assert_eq!(territory.size(&SnakeId(4)), 0);
+    }
+
+    #[test]
+    fn test_equal_snakes_split_the_board() {
+        let board = board_from(include_str!(
+            "../../../battlesnake-game-types/fixtures/start_of_game.json"
+        ));
+        let territory = voronoi(&board);
+        let sizes: Vec<_> = (0..4).map(|sid| territory.size(&SnakeId(sid))).collect();
+        // Every snake starts in its own corner with the same length, so each gets a fair share
+        assert!(sizes.iter().all(|size| *size > 10), "{sizes:?}");
+    }
+
+    #[test]
+    fn test_bfs_matches_bitset() {
+        for fixture in [
+            include_str!("../../../battlesnake-game-types/fixtures/4_snake_game.json"),
+            include_str!("../../../battlesnake-game-types/fixtures/late_stage.json"),
+            include_str!("../../../battlesnake-game-types/fixtures/start_of_game.json"),
+        ] {
+            let board = board_from(fixture);
+            let grid = Grid::new(11, 11, false).unwrap();
+            assert_eq!(voronoi_bitset(&board, &grid), voronoi_bfs(&board));
+            let head = board.get_head_as_position(&SnakeId(0));
+            assert_eq!(flood_fill(&board, head), flood_fill_bfs(&board, head));
+        }
+    }
+
+    #[test]
+    fn test_wrapped_bfs_matches_bitset() {
+        for fixture in [
+            include_str!(
+                "../../../battlesnake-game-types/fixtures/debug_wrapped/debug_game_1.json"
+            ),
+            include_str!(
+                "../../../battlesnake-game-types/fixtures/debug_wrapped/debug_game_50.json"
+            ),
+            include_str!(
+                "../../../battlesnake-game-types/fixtures/debug_wrapped/debug_game_150.json"
+            ),
+        ] {
+            let game: Game = serde_json::from_str(fixture).expect("valid fixture");
+            let snake_id_map = build_snake_id_map(&game);
+            let board: CellBoard4SnakesSquare11x11 = game
+                .as_wrapped_cell_board(&snake_id_map)
+                .expect("valid board");
+            let grid = Grid::for_board(&board).unwrap();
+            assert!(grid.wrapped);
+            assert_eq!(voronoi_bitset(&board, &grid), voronoi_bfs(&board));
+            for sid in board.get_snake_ids() {
+                let head = board.get_head_as_position(&sid);
+                assert_eq!(flood_fill(&board, head), flood_fill_bfs(&board, head));
+            }
+        }
+    }
+
+    #[test]
+    fn test_large_boards_fall_back_to_bfs() {
+        let mut game: Game = serde_json::from_str(include_str!(
+            "../../../battlesnake-game-types/fixtures/4_snake_game.json"
+        ))
+        .expect("valid fixture");
+        game.board.width = 25;
+        game.board.height = 25;
+        let snake_id_map = build_snake_id_map(&game);
+        let board = CellBoard8Snakes25x25::convert_from_game(game, &snake_id_map).unwrap();
+
+        let territory = voronoi(&board);
+        // The snakes are in the corner of the 11x11 fixture, so one of them gets the rest
+        assert!((0..4).any(|sid| territory.size(&SnakeId(sid)) > CELLS));
+        let head = board.get_head_as_position(&SnakeId(0));
+        assert!(flood_fill(&board, head) > CELLS);
+    }
+
+    #[test]
+    fn test_flood_fill_counts_free_cells() {
+        let board = board_from(include_str!(
+            "../../../battlesnake-game-types/fixtures/4_snake_game.json"
+        ));
+        let head = board.get_head_as_position(&SnakeId(0));
+        let reachable = flood_fill(&board, head);
+        assert!(reachable > 1);
+        assert!(reachable < CELLS);
+    }
+}
@@ -0,0 +1,119 @@
+//! The traits a board has to implement to be searched and evaluated
+
+use battlesnake_game_types::compact_representation::{
+    CellNum, ConversionError, dimensions::Dimensions, standard, wrapped,
+};
+use battlesnake_game_types::hazard_algorithms::{ForwardOnlyHazardAlgorithm, RoyaleHazard};
+use battlesnake_game_types::types::{
+    EmptyCellGettableGame, FoodGettableGame, FoodQueryableGame, FoodSettableGame,
+    HazardSettableGame, HeadGettableGame, HealthGettableGame, LengthGettableGame,
+    NeighborDeterminableGame, PositionGettableGame, RandomReasonableMovesGame, ReasonableMovesGame,
+    SimulatorInstruments, SizeDeterminableGame, SnakeBodyGettableGame, SnakeIDGettableGame,
+    SnakeIDMap, SnakeId, StandardFoodPlaceableGame, VictorDeterminableGame, WrapDeterminableGame,
+    YouDeterminableGame, ZobristHashableGame,
+};
+use battlesnake_game_types::wire_representation::Game;
+
+/// Every compact board of `battlesnake_game_types` is a `SearchableGame`, whatever its size and
+/// number of snakes. Simulating a board also needs `FallibleSimulableGame<Instruments, N_SNAKES>`, which
+/// fixes the number of snakes
+pub trait SearchableGame:
+    SnakeIDGettableGame<SnakeIDType = SnakeId>
+    + YouDeterminableGame
+    + ReasonableMovesGame
+    + RandomReasonableMovesGame
+    + VictorDeterminableGame
+    + HealthGettableGame
+    + LengthGettableGame
+    + HeadGettableGame
+    + FoodGettableGame
+    + NeighborDeterminableGame
+    + SnakeBodyGettableGame
+    + PositionGettableGame
+    + SizeDeterminableGame
+    + WrapDeterminableGame
+    + HazardSettableGame
+    + StandardFoodPlaceableGame
+    + FoodSettableGame
+    + FoodQueryableGame
+    + EmptyCellGettableGame
+    + ZobristHashableGame
+    + Copy
+    + PartialEq
+    + Send
+    + Sync
+    + 'static
+{
+}
+
+impl<B> SearchableGame for B where
+    B: SnakeIDGettableGame<SnakeIDType = SnakeId>
+        + YouDeterminableGame
+        + ReasonableMovesGame
+        + RandomReasonableMovesGame
+        + VictorDeterminableGame
+        + HealthGettableGame
+        + LengthGettableGame
+        + HeadGettableGame
+        + FoodGettableGame
+        + NeighborDeterminableGame
+        + SnakeBodyGettableGame
+        + PositionGettableGame
+        + SizeDeterminableGame
+        + WrapDeterminableGame
+        + HazardSettableGame
+        + StandardFoodPlaceableGame
+        + FoodSettableGame
+        + FoodQueryableGame
+        + EmptyCellGettableGame
+        + ZobristHashableGame
+        + Copy
+        + PartialEq
+        + Send
+        + Sync
+        + 'static
+{
+}
+
+/// The simulator instruments of the search, nothing is observed
+#[derive(Debug)]
+pub struct Instruments;
+
+impl SimulatorInstruments for Instruments {
+    fn observe_simulation(&self, _: std::time::Duration) {}
+}
+
+/// A board that can be built from the game of a request
+pub trait FromWireGame: Sized {
+    /// Converts `game`, numbering its snakes with `snake_ids`. Fails if the game doesn't fit the
+    /// board or is played with rules the board doesn't simulate
+    fn from_wire_game(game: Game, snake_ids: &SnakeIDMap) -> Result<Self, ConversionError>;
+}
+
+impl<T: CellNum, D: Dimensions, const BOARD_SIZE: usize, const MAX_SNAKES: usize> FromWireGame
+    for standard::CellBoard<T, D, BOARD_SIZE, MAX_SNAKES>
+{
+    fn from_wire_game(game: Game, snake_ids: &SnakeIDMap) -> Result<Self, ConversionError> {
+        Self::convert_from_game(game, snake_ids)
+    }
+}
+
+impl<T: CellNum, D: Dimensions, const BOARD_SIZE: usize, const MAX_SNAKES: usize> FromWireGame
+    for wrapped::CellBoard<T, D, BOARD_SIZE, MAX_SNAKES>
+{
+    fn from_wire_game(game: Game, snake_ids: &SnakeIDMap) -> Result<Self, ConversionError> {
+        Self::convert_from_game(game, snake_ids)
+    }
+}
+
+/// The shrinking of a royale `game`, observed from its hazards and randomly seeded because the
+/// seed of the game is unknown. None if the game doesn't shrink
+pub fn royale_hazards(game: &Game) -> Option<RoyaleHazard> {
+    if !game.is_royale() {
+        return None;
+    }
+    let mut hazards = RoyaleHazard::new(rand::random());
+    // The hazards are already on the board of the game
+    let _ = hazards.observe(game).ok()?;
+    Some(hazards)
+}
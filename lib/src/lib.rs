@@ -2,8 +2,16 @@
 #![feature(iter_collect_into)]
 
 pub mod agent;
-mod eval;
+pub mod config;
+pub mod eval;
+pub mod game;
 pub mod mcts;
 mod non_pushable_queue;
+pub mod reward;
+pub mod rollout;
+pub mod selection;
+pub mod transposition;
 
 pub use agent::{Agent, MctsAgent};
+pub use config::MctsConfig;
+pub use eval::Evaluator;
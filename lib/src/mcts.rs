@@ -1,20 +1,25 @@
 use std::{
+    borrow::Cow,
     collections::BTreeMap,
     sync::{
         Arc, Mutex, Weak,
-        atomic::{AtomicBool, AtomicU32, Ordering},
+        atomic::{AtomicBool, AtomicU32, AtomicUsize, Ordering},
     },
 };
 
-use battlesnake_game_types::{
-    compact_representation::standard::CellBoard4Snakes11x11,
-    types::{
-        Action, HealthGettableGame, Move, RandomReasonableMovesGame, ReasonableMovesGame,
-        SimulableGame, SimulatorInstruments, SnakeId, VictorDeterminableGame,
-    },
-};
+use battlesnake_game_types::hazard_algorithms::RoyaleHazard;
+use battlesnake_game_types::types::{Action, FallibleSimulableGame, Move, SnakeId};
 
+use rand::Rng;
+use serde::Deserialize;
+
+use crate::config::{MctsConfig, ParallelMode};
+use crate::eval::{Evaluator, distance};
+use crate::game::{Instruments, SearchableGame};
 use crate::non_pushable_queue::NonPushableQueue;
+use crate::reward::{RewardSum, Rewards};
+use crate::selection::{self, FinalMovePolicy};
+use crate::transposition::{TableEntry, TranspositionTable};
 
 /// Iterator that generates all possible combinations of moves for each snake (Cartesian product)
 struct MoveCombinationIterator {
@@ -87,36 +92,229 @@ fn generate_move_combinations(
     MoveCombinationIterator::new(snake_moves)
 }
 
-pub struct Node {
-    parent_node: Weak<Node>,
-    board: CellBoard4Snakes11x11,
-    next_nodes: Mutex<BTreeMap<Action<4>, Arc<Node>>>,
-    possible_moves: NonPushableQueue<Vec<(SnakeId, Move)>>,
-    wins: AtomicU32,
+/// How the children of a node are formed from the moves of the individual snakes
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SearchMode {
+    /// Every combination of moves is its own child, the snakes pick their part of it one after
+    /// another with the UCB1 values of the children, see [`Node::select_joint_child`]
+    #[default]
+    Joint,
+    /// Every snake picks its move with its own UCB1 statistics (decoupled UCT), the joint action
+    /// is only formed when descending
+    Decoupled,
+}
+
+/// Visit count and the rewards of every snake for a single move of a single snake at a node
+struct MoveStats<const N_SNAKES: usize> {
+    rewards: [RewardSum; N_SNAKES],
+    visits: AtomicU32,
+    /// Visits of searches that are currently below this move, counted as losses until they finish
+    virtual_loss: AtomicU32,
+}
+
+impl<const N_SNAKES: usize> Default for MoveStats<N_SNAKES> {
+    fn default() -> Self {
+        Self {
+            rewards: std::array::from_fn(|_| RewardSum::default()),
+            visits: AtomicU32::new(0),
+            virtual_loss: AtomicU32::new(0),
+        }
+    }
+}
+
+impl<const N_SNAKES: usize> MoveStats<N_SNAKES> {
+    fn effective_visits(&self) -> u32 {
+        self.visits.load(Ordering::Relaxed) + self.virtual_loss.load(Ordering::Relaxed)
+    }
+}
+
+/// The statistics gathered for the position of a [`Node`]
+pub struct NodeStats<const N_SNAKES: usize> {
+    /// Per snake and per move statistics, indexed by `SnakeId` and `Move::as_index`
+    move_stats: [[MoveStats<N_SNAKES>; 4]; N_SNAKES],
+    /// Accumulated rewards of every snake, indexed by `SnakeId`
+    rewards: [RewardSum; N_SNAKES],
     visits: AtomicU32,
+    /// Visits of searches that are currently below this node, counted as losses until they finish
+    /// so other threads are steered towards different paths
+    virtual_loss: AtomicU32,
 }
-#[derive(Debug)]
-struct Instr;
-impl SimulatorInstruments for Instr {
-    fn observe_simulation(&self, _: std::time::Duration) {}
+
+impl<const N_SNAKES: usize> Default for NodeStats<N_SNAKES> {
+    fn default() -> Self {
+        Self {
+            move_stats: std::array::from_fn(|_| Default::default()),
+            rewards: std::array::from_fn(|_| RewardSum::default()),
+            visits: AtomicU32::new(0),
+            virtual_loss: AtomicU32::new(0),
+        }
+    }
 }
-impl Node {
-    pub fn new_root(board: CellBoard4Snakes11x11) -> Self {
-        Self::new_child(Weak::new(), board)
+
+impl<const N_SNAKES: usize> TableEntry for NodeStats<N_SNAKES> {
+    fn weight(&self) -> u32 {
+        self.visits.load(Ordering::Relaxed)
     }
-    pub fn new_child(parent: Weak<Node>, board: CellBoard4Snakes11x11) -> Self {
+}
+
+/// The transposition table of a search, see [`MctsConfig::transposition_table`]
+pub type NodeTable<const N_SNAKES: usize> = TranspositionTable<NodeStats<N_SNAKES>>;
+
+/// A node of the search tree over boards of type `B` with up to `N_SNAKES` snakes
+pub struct Node<B, const N_SNAKES: usize> {
+    parent_node: Mutex<Weak<Node<B, N_SNAKES>>>,
+    /// The joint action that led from the parent to this node
+    action: Option<Action<N_SNAKES>>,
+    mode: SearchMode,
+    board: B,
+    snake_moves: Vec<(SnakeId, Vec<Move>)>,
+    next_nodes: Mutex<BTreeMap<Action<N_SNAKES>, Arc<Node<B, N_SNAKES>>>>,
+    possible_moves: NonPushableQueue<Vec<(SnakeId, Move)>>,
+    /// The statistics of the position of this node, shared with the other nodes of the same
+    /// position if the search has a transposition table
+    stats: Arc<NodeStats<N_SNAKES>>,
+    table: Option<Arc<NodeTable<N_SNAKES>>>,
+    /// The royale shrinking as of the board of this node, only used on the root of a search
+    hazards: Mutex<Option<RoyaleHazard>>,
+}
+impl<B, const N_SNAKES: usize> Node<B, N_SNAKES>
+where
+    B: SearchableGame + FallibleSimulableGame<Instruments, N_SNAKES>,
+{
+    pub fn new_root(board: B) -> Self {
+        Self::new_root_with_mode(board, SearchMode::default())
+    }
+    pub fn new_root_with_mode(board: B, mode: SearchMode) -> Self {
+        Self::new_root_with_table(board, mode, None)
+    }
+    /// A root with the [`SearchMode`] of `config` and a new transposition table if the config
+    /// asks for one
+    pub fn new_root_with_config(board: B, config: &MctsConfig) -> Self {
+        let table = config
+            .transposition_table
+            .map(|buckets| Arc::new(NodeTable::new(buckets)));
+        Self::new_root_with_table(board, config.search_mode, table)
+    }
+    /// A root whose tree shares the statistics of every position through `table`
+    pub fn new_root_with_table(
+        board: B,
+        mode: SearchMode,
+        table: Option<Arc<NodeTable<N_SNAKES>>>,
+    ) -> Self {
+        Self::new(Weak::new(), None, mode, table, board)
+    }
+    pub fn new_child(parent: &Arc<Self>, action: Action<N_SNAKES>, board: B) -> Self {
+        Self::new(
+            Arc::downgrade(parent),
+            Some(action),
+            parent.mode,
+            parent.table.clone(),
+            board,
+        )
+    }
+    fn new(
+        parent: Weak<Self>,
+        action: Option<Action<N_SNAKES>>,
+        mode: SearchMode,
+        table: Option<Arc<NodeTable<N_SNAKES>>>,
+        board: B,
+    ) -> Self {
         let snake_moves: Vec<_> = board.reasonable_moves_for_each_snake().collect();
-        let move_combinations = generate_move_combinations(snake_moves);
+        // Decoupled nodes never expand by enumerating joint actions, so don't pay for building them
+        let possible_moves = match mode {
+            SearchMode::Joint => {
+                NonPushableQueue::new_from_iterator(generate_move_combinations(snake_moves.clone()))
+            }
+            SearchMode::Decoupled => NonPushableQueue::new_from_iterator(std::iter::empty()),
+        };
 
         Node {
-            parent_node: parent,
+            parent_node: Mutex::new(parent),
+            action,
+            mode,
             board,
+            snake_moves,
             next_nodes: Mutex::new(BTreeMap::new()),
-            possible_moves: NonPushableQueue::new_from_iterator(move_combinations.into_iter()),
-            wins: AtomicU32::new(0),
-            visits: AtomicU32::new(0),
+            possible_moves,
+            stats: match &table {
+                Some(table) => table.get_or_insert(board.zobrist_hash()),
+                None => Arc::default(),
+            },
+            table,
+            hazards: Mutex::new(None),
         }
     }
+    pub fn mode(&self) -> SearchMode {
+        self.mode
+    }
+    pub fn board(&self) -> &B {
+        &self.board
+    }
+    /// Sets the royale shrinking of the board of this node. Searches from this node shrink the
+    /// boards of their rollouts with it if [`MctsConfig::royale_shrinking`] is set
+    pub fn set_hazards(&self, hazards: Option<RoyaleHazard>) {
+        *self.hazards.lock().unwrap() = hazards;
+    }
+    /// The royale shrinking searches from this node use, if enabled by `config`
+    fn hazards(&self, config: &MctsConfig) -> Option<RoyaleHazard> {
+        let hazards = *self.hazards.lock().unwrap();
+        hazards.filter(|_| config.royale_shrinking)
+    }
+    /// Finds the child that was reached by the moves the snakes actually made and detaches it
+    /// from this node so it can be used as the root of the next search.
+    /// Returns None if no child leads to exactly `board`, e.g. because food spawned. With
+    /// [`MctsConfig::spawn_food`] the boards of the tree have no spawned food, so only the rest
+    /// of the board has to match and the child continues from the food of `board`
+    pub fn take_child_for_board(&self, board: &B, config: &MctsConfig) -> Option<Arc<Self>> {
+        let mut moves = [None; N_SNAKES];
+        for (sid, mv) in moves.iter_mut().enumerate() {
+            let sid = SnakeId(sid as u8);
+            if !self.board.is_alive(&sid) || !board.is_alive(&sid) {
+                continue;
+            }
+            let old_head = self.board.get_head_as_position(&sid);
+            let new_head = board.get_head_as_position(&sid);
+            *mv = Some(
+                Move::all()
+                    .into_iter()
+                    .find(|m| distance(board, &old_head.add_vec(m.to_vector()), &new_head) == 0)?,
+            );
+        }
+
+        let matches = |tree_board: &B| {
+            if config.spawn_food {
+                without_food(tree_board) == without_food(board)
+            } else {
+                tree_board == board
+            }
+        };
+        let mut next_nodes = self.next_nodes.lock().unwrap();
+        let action = next_nodes
+            .iter()
+            .find(|(action, node)| {
+                action
+                    .into_inner()
+                    .iter()
+                    .zip(moves)
+                    .all(|(taken, expected)| expected.is_none() || *taken == expected)
+                    && matches(&node.board)
+            })
+            .map(|(action, _)| *action)?;
+        let child = next_nodes.remove(&action)?;
+        *child.parent_node.lock().unwrap() = Weak::new();
+        if child.board == *board {
+            return Some(child);
+        }
+        // Nothing else holds on to the child once it is detached, the search is done
+        let mut child = Arc::into_inner(child)?;
+        child.board = *board;
+        let child = Arc::new(child);
+        for grandchild in child.next_nodes.lock().unwrap().values() {
+            *grandchild.parent_node.lock().unwrap() = Arc::downgrade(&child);
+        }
+        Some(child)
+    }
     pub fn get_depth(&self) -> u32 {
         self.next_nodes
             .lock()
@@ -126,9 +324,10 @@ impl Node {
             .max()
             .unwrap_or(0)
     }
-    pub fn best_child(&self, c: f32) -> Option<(Action<4>, Arc<Node>)> {
+    /// The child with the highest UCB1 value for `you`
+    pub fn best_child(&self, you: &SnakeId, c: f32) -> Option<(Action<N_SNAKES>, Arc<Self>)> {
         // Cache parent visits to avoid repeated atomic loads during iteration
-        let parent_visits = self.visits.load(Ordering::Relaxed) as f32;
+        let parent_visits = self.effective_visits();
 
         // Collect entries quickly to minimize lock duration
         let children: Vec<_> = {
@@ -140,19 +339,86 @@ impl Node {
 
         // Compute UCB1 scores without holding the lock
         children.into_iter().max_by(|(_, node1), (_, node2)| {
-            Self::ucb1_from_ref(node1, c, parent_visits).total_cmp(&Self::ucb1_from_ref(
+            Self::ucb1_from_ref(node1, you, c, parent_visits).total_cmp(&Self::ucb1_from_ref(
                 node2,
+                you,
                 c,
                 parent_visits,
             ))
         })
     }
+    /// The child of the joint action the snakes pick one after another, `you` first. Every snake
+    /// picks its move by the UCB1 value of the children that agree with the moves picked before
+    /// it, valued with the [`OpponentModel`](crate::reward::OpponentModel) of the config
+    pub fn select_joint_child(
+        &self,
+        you: &SnakeId,
+        config: &MctsConfig,
+    ) -> Option<(Action<N_SNAKES>, Arc<Self>)> {
+        self.select_joint_child_among(you, config, &self.snake_moves)
+    }
+    /// [`Node::select_joint_child`] where the snakes pick from `snake_moves`, the moves of a
+    /// board sampled at this node. None if no child agrees with them
+    pub fn select_joint_child_among(
+        &self,
+        you: &SnakeId,
+        config: &MctsConfig,
+        snake_moves: &[(SnakeId, Vec<Move>)],
+    ) -> Option<(Action<N_SNAKES>, Arc<Self>)> {
+        let mut candidates: Vec<_> = {
+            let lock = self.next_nodes.lock().unwrap();
+            lock.iter()
+                .map(|(action, node)| (*action, node.clone()))
+                .collect()
+        };
+        let (mine, opponents): (Vec<_>, Vec<_>) =
+            snake_moves.iter().partition(|(sid, _)| sid == you);
+        for (sid, legal) in mine.into_iter().chain(opponents) {
+            // Visits and reward sums of every snake over the candidates, per move of `sid`
+            let mut totals: [Option<(u32, Rewards<N_SNAKES>)>; 4] = [None; 4];
+            for (action, node) in &candidates {
+                let Some(mv) = action.into_inner()[sid.as_usize()].filter(|mv| legal.contains(mv))
+                else {
+                    continue;
+                };
+                let visits = node.effective_visits();
+                let (total_visits, sums) =
+                    totals[mv.as_index()].get_or_insert((0, [0.0; N_SNAKES]));
+                *total_visits += visits;
+                for (sum, reward) in sums.iter_mut().zip(&node.stats.rewards) {
+                    *sum += reward.mean(visits) * visits as f32;
+                }
+            }
+            let parent_visits = totals.iter().flatten().map(|(visits, _)| visits).sum();
+            let ucb1 = |mv: &Move| {
+                let Some((visits, sums)) = totals[mv.as_index()] else {
+                    return f32::NEG_INFINITY;
+                };
+                let value = config
+                    .opponent_model
+                    .value(sid, you, |sid| sums[sid.as_usize()] / visits.max(1) as f32);
+                selection::ucb1(value, visits, parent_visits, config.exploration_constant)
+            };
+            let Some(best) = Move::all()
+                .into_iter()
+                .filter(|mv| totals[mv.as_index()].is_some())
+                .max_by(|a, b| ucb1(a).total_cmp(&ucb1(b)))
+            else {
+                // Only the children where `sid` already died on the board of this node are left
+                candidates.retain(|(action, _)| action.into_inner()[sid.as_usize()].is_none());
+                continue;
+            };
+            candidates.retain(|(action, _)| action.into_inner()[sid.as_usize()] == Some(best));
+        }
+        candidates.into_iter().next()
+    }
     pub fn is_fully_expanded(&self) -> bool {
         self.possible_moves.is_empty()
     }
-    pub fn expand(self: Arc<Self>, _you: &SnakeId) {
+    /// Adds the child for the next untried joint action, returns whether a child was added
+    pub fn expand(self: Arc<Self>, _you: &SnakeId) -> bool {
         let Some(moves) = self.possible_moves.pop_front() else {
-            return;
+            return false;
         };
 
         // Convert moves in-place to avoid intermediate allocation
@@ -160,110 +426,586 @@ impl Node {
 
         if let Some((action, next_board)) = self
             .board
-            .simulate_with_moves(&Instr, &moves_for_simulation)
-            .next()
+            .try_simulate_with_moves(&Instruments, &moves_for_simulation)
+            .ok()
+            .and_then(|mut boards| boards.next()?.ok())
         {
-            let node = Self::new_child(Arc::downgrade(&self), next_board);
+            let node = Self::new_child(&self, action, next_board);
             let mut next_nodes_lock = self.next_nodes.lock().unwrap();
             next_nodes_lock.insert(action, Arc::new(node));
+            return true;
         }
+        false
     }
 
-    pub fn ucb1(self: Arc<Self>, c: f32, visits_to_parent: f32) -> f32 {
-        Self::ucb1_from_ref(self, c, visits_to_parent)
+    /// Lets every alive snake pick its own move using only its own statistics at this node.
+    /// Which reward the opponents maximize is determined by the
+    /// [`OpponentModel`](crate::reward::OpponentModel) of the config.
+    pub fn select_decoupled_action(&self, you: &SnakeId, config: &MctsConfig) -> Action<N_SNAKES> {
+        self.select_decoupled_action_among(you, config, &self.snake_moves)
+    }
+    /// [`Node::select_decoupled_action`] where the snakes pick from `snake_moves`, the moves
+    /// of a board sampled at this node. Snakes that are only alive on the board of this node
+    /// still pick one of their moves, snakes that are only alive on the sampled board don't
+    pub fn select_decoupled_action_among(
+        &self,
+        you: &SnakeId,
+        config: &MctsConfig,
+        snake_moves: &[(SnakeId, Vec<Move>)],
+    ) -> Action<N_SNAKES> {
+        let parent_visits = self.effective_visits();
+        let mut moves = [None; N_SNAKES];
+        for (sid, snake_moves) in snake_moves.iter().chain(&self.snake_moves) {
+            if moves[sid.as_usize()].is_some() || !self.board.is_alive(sid) {
+                continue;
+            }
+            let stats = &self.stats.move_stats[sid.as_usize()];
+            let ucb1 = |mv: &Move| {
+                Self::decoupled_ucb1(&stats[mv.as_index()], sid, you, config, parent_visits)
+            };
+            moves[sid.as_usize()] = snake_moves
+                .iter()
+                .copied()
+                .max_by(|a, b| ucb1(a).total_cmp(&ucb1(b)));
+        }
+        Action::new(moves)
+    }
+    fn decoupled_ucb1(
+        stats: &MoveStats<N_SNAKES>,
+        sid: &SnakeId,
+        you: &SnakeId,
+        config: &MctsConfig,
+        parent_visits: u32,
+    ) -> f32 {
+        let visits = stats.effective_visits();
+        let value = config
+            .opponent_model
+            .value(sid, you, |sid| stats.rewards[sid.as_usize()].mean(visits));
+        selection::ucb1(value, visits, parent_visits, config.exploration_constant)
+    }
+    /// Returns the child for the given joint action if it was already simulated
+    pub fn child(&self, action: &Action<N_SNAKES>) -> Option<Arc<Self>> {
+        self.next_nodes.lock().unwrap().get(action).cloned()
+    }
+    /// Returns the child for the given joint action, simulating it if it doesn't exist yet.
+    /// The returned bool is true if the child was newly created. None if the board of this node
+    /// can't be simulated.
+    pub fn child_for_action(
+        self: &Arc<Self>,
+        action: Action<N_SNAKES>,
+    ) -> Option<(Arc<Self>, bool)> {
+        let mut next_nodes = self.next_nodes.lock().unwrap();
+        if let Some(child) = next_nodes.get(&action) {
+            return Some((child.clone(), false));
+        }
+        let moves: Vec<_> = action
+            .into_inner()
+            .into_iter()
+            .enumerate()
+            .filter_map(|(sid, mv)| mv.map(|mv| (SnakeId(sid as u8), [mv])))
+            .collect();
+        let (_, next_board) = self
+            .board
+            .try_simulate_with_moves(&Instruments, &moves)
+            .ok()?
+            .next()?
+            .ok()?;
+        let child = Arc::new(Self::new_child(self, action, next_board));
+        next_nodes.insert(action, child.clone());
+        Some((child, true))
+    }
+    /// The move `you` should play according to the statistics gathered so far, picked with the
+    /// given [`FinalMovePolicy`]
+    pub fn best_move(&self, you: &SnakeId, policy: FinalMovePolicy) -> Option<Move> {
+        match self.mode {
+            SearchMode::Joint => {
+                let children: Vec<_> = {
+                    let lock = self.next_nodes.lock().unwrap();
+                    lock.iter()
+                        .map(|(action, node)| (*action, node.visits(), node.mean_reward(you)))
+                        .collect()
+                };
+                policy
+                    .select(children)
+                    .and_then(|action| action.into_inner()[you.as_usize()])
+            }
+            SearchMode::Decoupled => {
+                let stats = &self.stats.move_stats[you.as_usize()];
+                let (_, moves) = self.snake_moves.iter().find(|(sid, _)| sid == you)?;
+                policy.select(moves.iter().map(|mv| {
+                    let stats = &stats[mv.as_index()];
+                    let visits = stats.visits.load(Ordering::Relaxed);
+                    (*mv, visits, stats.rewards[you.as_usize()].mean(visits))
+                }))
+            }
+        }
+    }
+    /// How often each move of `you` was searched, indexed by [`Move::as_index`]
+    pub fn move_visits(&self, you: &SnakeId) -> [u32; 4] {
+        let mut visits = [0; 4];
+        match self.mode {
+            SearchMode::Joint => {
+                for (action, node) in self.next_nodes.lock().unwrap().iter() {
+                    if let Some(mv) = action.into_inner()[you.as_usize()] {
+                        visits[mv.as_index()] += node.visits();
+                    }
+                }
+            }
+            SearchMode::Decoupled => {
+                for (visits, stats) in visits
+                    .iter_mut()
+                    .zip(&self.stats.move_stats[you.as_usize()])
+                {
+                    *visits = stats.visits.load(Ordering::Relaxed);
+                }
+            }
+        }
+        visits
+    }
+    pub fn visits(&self) -> u32 {
+        self.stats.visits.load(Ordering::Relaxed)
+    }
+    /// Visits including the virtual losses of searches that are still running below this node
+    fn effective_visits(&self) -> u32 {
+        self.visits() + self.stats.virtual_loss.load(Ordering::Relaxed)
+    }
+    /// Adds `virtual_loss` to this node before a search descends into it
+    fn add_virtual_loss(&self, virtual_loss: u32) {
+        self.stats
+            .virtual_loss
+            .fetch_add(virtual_loss, Ordering::AcqRel);
+    }
+    /// Adds `virtual_loss` to `child` and to the moves that lead to it
+    fn descend_with_virtual_loss(&self, child: &Self, virtual_loss: u32) {
+        child.add_virtual_loss(virtual_loss);
+        if let Some(action) = child.action {
+            self.for_each_move_stats(action, |stats| {
+                stats.virtual_loss.fetch_add(virtual_loss, Ordering::AcqRel);
+            });
+        }
     }
-    pub fn ucb1_from_ref(node: impl AsRef<Self>, c: f32, visits_to_parent: f32) -> f32 {
+    /// The average reward of `sid` over all rollouts that went through this node
+    pub fn mean_reward(&self, sid: &SnakeId) -> f32 {
+        self.stats.rewards[sid.as_usize()].mean(self.visits())
+    }
+
+    pub fn ucb1(self: Arc<Self>, you: &SnakeId, c: f32, visits_to_parent: u32) -> f32 {
+        Self::ucb1_from_ref(self, you, c, visits_to_parent)
+    }
+    pub fn ucb1_from_ref(
+        node: impl AsRef<Self>,
+        you: &SnakeId,
+        c: f32,
+        visits_to_parent: u32,
+    ) -> f32 {
         let reference = node.as_ref();
         // Use Relaxed ordering since we only need eventual consistency for UCB1 calculations
-        (reference.wins.load(Ordering::Relaxed) as f32).algebraic_add(
-            c.algebraic_mul(
-                visits_to_parent
-                    .ln()
-                    .sqrt()
-                    .algebraic_div((reference.visits.load(Ordering::Relaxed) + 1) as f32),
-            ),
+        let visits = reference.effective_visits();
+        selection::ucb1(
+            reference.stats.rewards[you.as_usize()].mean(visits),
+            visits,
+            visits_to_parent,
+            c,
         )
     }
-    /// Perform a random rollout with depth limit
-    /// Returns 1 for win, 0 for loss
-    pub fn rollout(self: Arc<Self>, you: &SnakeId) -> u32 {
-        const MAX_ROLLOUT_DEPTH: u32 = 50; // Limit depth to prevent extremely long simulations
+    /// Plays out the board of this node with the [`RolloutPolicy`](crate::config::RolloutPolicy)
+    /// of the config. Returns the reward of every snake according to the
+    /// [`RewardKind`](crate::reward::RewardKind) of the config
+    pub fn rollout(
+        self: Arc<Self>,
+        config: &MctsConfig,
+        evaluator: &dyn Evaluator<B>,
+    ) -> Rewards<N_SNAKES> {
+        config
+            .rollout_policy
+            .rollout(&self.board, config, evaluator, None)
+    }
+    /// Like [`Node::rollout`], but the board shrinks with `hazards` while it is played out.
+    /// `hazards` are those of the root of the search, `depth` the number of turns from the root
+    /// to this node
+    pub fn rollout_with_hazards(
+        self: Arc<Self>,
+        config: &MctsConfig,
+        evaluator: &dyn Evaluator<B>,
+        hazards: RoyaleHazard,
+        depth: u32,
+    ) -> Rewards<N_SNAKES> {
+        rollout_leaf(&self.board, config, evaluator, Some(hazards), depth)
+    }
+    pub fn is_terminal(&self) -> bool {
+        self.board.is_over()
+    }
+    pub fn backpropagate(self: Arc<Self>, rewards: Rewards<N_SNAKES>) {
+        self.backpropagate_with_virtual_loss(rewards, 0)
+    }
+    /// Backpropagates `rewards` and reverts the `virtual_loss` that was added on the way down
+    fn backpropagate_with_virtual_loss(
+        self: Arc<Self>,
+        rewards: Rewards<N_SNAKES>,
+        virtual_loss: u32,
+    ) {
+        self.stats
+            .visits
+            .fetch_add(1, std::sync::atomic::Ordering::AcqRel);
+        self.stats
+            .virtual_loss
+            .fetch_sub(virtual_loss, Ordering::AcqRel);
+        for (sum, reward) in self.stats.rewards.iter().zip(rewards) {
+            sum.add(reward);
+        }
+        let parent = self.parent_node.lock().unwrap().upgrade();
+        if let Some(parent) = parent {
+            if let Some(action) = self.action {
+                parent.record_action(action, &rewards, virtual_loss);
+            }
+            parent.backpropagate_with_virtual_loss(rewards, virtual_loss)
+        }
+    }
+    /// Credits the rewards to the move every snake chose in `action`
+    fn record_action(
+        &self,
+        action: Action<N_SNAKES>,
+        rewards: &Rewards<N_SNAKES>,
+        virtual_loss: u32,
+    ) {
+        self.for_each_move_stats(action, |stats| {
+            stats.visits.fetch_add(1, Ordering::AcqRel);
+            stats.virtual_loss.fetch_sub(virtual_loss, Ordering::AcqRel);
+            for (sum, reward) in stats.rewards.iter().zip(rewards) {
+                sum.add(*reward);
+            }
+        });
+    }
+    /// Calls `f` with the statistics of the move every snake chose in `action`
+    fn for_each_move_stats(
+        &self,
+        action: Action<N_SNAKES>,
+        mut f: impl FnMut(&MoveStats<N_SNAKES>),
+    ) {
+        for (sid, mv) in action.into_inner().into_iter().enumerate() {
+            if let Some(mv) = mv {
+                f(&self.stats.move_stats[sid][mv.as_index()]);
+            }
+        }
+    }
+}
 
-        let mut rng = rand::rng();
-        let mut cur_board = self.board;
-        let mut moves = Vec::with_capacity(4);
-        let mut depth = 0;
+/// Searches the tree below `root_node` until `stop` is set, the kind of search is determined by
+/// the [`SearchMode`] of the root. Every one of the `threads` of the config searches the same tree,
+/// virtual loss keeps them from all following the same path.
+pub fn mcts_search<B, const N_SNAKES: usize>(
+    root_node: Arc<Node<B, N_SNAKES>>,
+    you: &SnakeId,
+    stop: Arc<AtomicBool>,
+    config: &MctsConfig,
+    evaluator: &dyn Evaluator<B>,
+) where
+    B: SearchableGame + FallibleSimulableGame<Instruments, N_SNAKES>,
+{
+    let nodes_added = AtomicUsize::new(0);
+    let search = || match root_node.mode {
+        SearchMode::Joint => {
+            joint_mcts_search(&root_node, you, &stop, config, evaluator, &nodes_added)
+        }
+        SearchMode::Decoupled => {
+            decoupled_mcts_search(&root_node, you, &stop, config, evaluator, &nodes_added)
+        }
+    };
+    std::thread::scope(|scope| {
+        for _ in 1..config.threads {
+            scope.spawn(search);
+        }
+        search();
+    });
+}
 
-        while !cur_board.is_over() && depth < MAX_ROLLOUT_DEPTH {
-            moves.clear();
-            cur_board
-                .random_reasonable_move_for_each_snake(&mut rng)
-                .map(|(sid, mv)| (sid, [mv]))
-                .collect_into(&mut moves);
-            let next_board = cur_board
-                .simulate_with_moves(&Instr, &moves)
-                .next()
-                .unwrap()
-                .1;
-            cur_board = next_board;
-            depth += 1;
+/// Searches `roots` until `stop` is set, using as many trees as [`MctsConfig::trees`] says.
+/// With [`ParallelMode::Tree`] this is just [`mcts_search`] on the single root, with
+/// [`ParallelMode::Root`] every thread searches one of the roots on its own. The rollouts of every
+/// thread use their own randomly seeded RNG, so the trees grow differently.
+pub fn parallel_search<B, const N_SNAKES: usize>(
+    roots: &[Arc<Node<B, N_SNAKES>>],
+    you: &SnakeId,
+    stop: Arc<AtomicBool>,
+    config: &MctsConfig,
+    evaluator: &dyn Evaluator<B>,
+) where
+    B: SearchableGame + FallibleSimulableGame<Instruments, N_SNAKES>,
+{
+    match config.parallel_mode {
+        ParallelMode::Tree => mcts_search(roots[0].clone(), you, stop, config, evaluator),
+        ParallelMode::Root => {
+            let config = MctsConfig {
+                threads: 1,
+                ..*config
+            };
+            std::thread::scope(|scope| {
+                for root in roots {
+                    let stop = stop.clone();
+                    scope.spawn(move || mcts_search(root.clone(), you, stop, &config, evaluator));
+                }
+            });
         }
+    }
+}
 
-        // If we hit max depth without game ending, check if we're still alive
-        if cur_board.get_health(you) == 0 {
-            0 // We died
-        } else if cur_board.is_over() && cur_board.get_winner().is_some_and(|w| w == *you) {
-            1 // We won
-        } else if cur_board.is_over() {
-            0 // We lost
-        } else {
-            // Game not over but we hit depth limit - assume survival is somewhat good
-            // Return 1 if we're still alive at depth limit, 0 otherwise
-            if cur_board.get_health(you) > 0 { 1 } else { 0 }
+/// The move `you` should play after searching `roots`. A single tree picks it with the given
+/// [`FinalMovePolicy`], several trees of a root-parallel search vote with the visits of each move.
+pub fn ensemble_best_move<B, const N_SNAKES: usize>(
+    roots: &[Arc<Node<B, N_SNAKES>>],
+    you: &SnakeId,
+    policy: FinalMovePolicy,
+) -> Option<Move>
+where
+    B: SearchableGame + FallibleSimulableGame<Instruments, N_SNAKES>,
+{
+    if let [root] = roots {
+        return root.best_move(you, policy);
+    }
+    let mut votes = [0u32; 4];
+    for root in roots {
+        for (votes, visits) in votes.iter_mut().zip(root.move_visits(you)) {
+            *votes += visits;
         }
     }
-    pub fn is_terminal(&self) -> bool {
-        self.board.is_over()
+    votes
+        .into_iter()
+        .enumerate()
+        .filter(|(_, votes)| *votes > 0)
+        .max_by_key(|(_, votes)| *votes)
+        .map(|(index, _)| Move::from_index(index))
+}
+
+/// Plays out `board`, which a search reached `depth` turns below its root. The board shrinks
+/// with the `hazards` of the root, wound forward to the turn of the board
+fn rollout_leaf<B, const N_SNAKES: usize>(
+    board: &B,
+    config: &MctsConfig,
+    evaluator: &dyn Evaluator<B>,
+    hazards: Option<RoyaleHazard>,
+    depth: u32,
+) -> Rewards<N_SNAKES>
+where
+    B: SearchableGame + FallibleSimulableGame<Instruments, N_SNAKES>,
+{
+    let hazards = hazards.map(|hazards| {
+        // The seed of the game is unknown, so every rollout samples its own shrinks
+        let mut hazards = hazards.with_seed(rand::random());
+        for _ in 0..depth {
+            hazards.advance();
+        }
+        hazards
+    });
+    config
+        .rollout_policy
+        .rollout(board, config, evaluator, hazards)
+}
+
+/// The board a descent that reached `node` is actually at. Boards of the tree are simulated
+/// without food spawns, so with [`MctsConfig::spawn_food`] the search is open-loop: nodes stand
+/// for the joint actions leading to them and every descent samples the spawns along its path into
+/// `sampled`, so the search doesn't trust a single food layout. Whether the descent is over, the
+/// moves it picks from and the rollout all come from this board
+fn sampled_board<'a, B, const N_SNAKES: usize>(
+    config: &MctsConfig,
+    node: &'a Node<B, N_SNAKES>,
+    sampled: &'a B,
+) -> &'a B
+where
+    B: SearchableGame + FallibleSimulableGame<Instruments, N_SNAKES>,
+{
+    if config.spawn_food {
+        sampled
+    } else {
+        node.board()
     }
-    pub fn backpropagate(self: Arc<Self>, result: u32) {
-        self.visits
-            .fetch_add(1, std::sync::atomic::Ordering::AcqRel);
-        self.wins
-            .fetch_add(result, std::sync::atomic::Ordering::AcqRel);
-        if let Some(parent) = self.parent_node.upgrade() {
-            parent.backpropagate(result)
+}
+
+/// The moves of every snake on the board a descent reached at `node`, see `sampled_board`
+fn legal_moves<'a, B, const N_SNAKES: usize>(
+    config: &MctsConfig,
+    node: &'a Node<B, N_SNAKES>,
+    sampled: &B,
+) -> Cow<'a, [(SnakeId, Vec<Move>)]>
+where
+    B: SearchableGame + FallibleSimulableGame<Instruments, N_SNAKES>,
+{
+    if config.spawn_food {
+        Cow::Owned(sampled.reasonable_moves_for_each_snake().collect())
+    } else {
+        Cow::Borrowed(&node.snake_moves)
+    }
+}
+
+/// `board` without any food, what the boards of an open-loop tree predict about a board
+fn without_food<B: SearchableGame>(board: &B) -> B {
+    let mut board = *board;
+    for food in board.get_all_food_as_native_positions() {
+        board.clear_food(food);
+    }
+    board
+}
+
+/// Samples one of the boards the joint `action` leads to from `board`: the action is simulated
+/// and food spawns on the result. Snakes the action has no move for, because they were already
+/// dead on the board of the tree, play a random reasonable move. None if `board` can't be
+/// simulated, the descent then carries on with the board of the tree
+fn sample_next_board<B, const N_SNAKES: usize>(
+    board: &B,
+    action: Action<N_SNAKES>,
+    rng: &mut impl Rng,
+) -> Option<B>
+where
+    B: SearchableGame + FallibleSimulableGame<Instruments, N_SNAKES>,
+{
+    let planned = action.into_inner();
+    let moves: Vec<_> = board
+        .random_reasonable_move_for_each_snake(rng)
+        .map(|(sid, mv)| (sid, [planned[sid.as_usize()].unwrap_or(mv)]))
+        .collect();
+    let (_, mut next_board) = board
+        .try_simulate_with_moves(&Instruments, &moves)
+        .ok()?
+        .next()?
+        .ok()?;
+    next_board.place_food(rng);
+    Some(next_board)
+}
+
+/// Whether another node may be added to the tree without exceeding the node budget
+fn within_node_budget(config: &MctsConfig, nodes_added: &AtomicUsize) -> bool {
+    config
+        .node_budget
+        .is_none_or(|budget| nodes_added.load(Ordering::Relaxed) < budget)
+}
+
+fn decoupled_mcts_search<B, const N_SNAKES: usize>(
+    root_node: &Arc<Node<B, N_SNAKES>>,
+    you: &SnakeId,
+    stop: &AtomicBool,
+    config: &MctsConfig,
+    evaluator: &dyn Evaluator<B>,
+    nodes_added: &AtomicUsize,
+) where
+    B: SearchableGame + FallibleSimulableGame<Instruments, N_SNAKES>,
+{
+    let virtual_loss = config.virtual_loss;
+    let hazards = root_node.hazards(config);
+    let mut rng = rand::rng();
+    loop {
+        if stop.load(Ordering::Relaxed) {
+            break;
         }
+        let mut node = root_node.clone();
+        let mut depth = 0;
+        // The board this descent actually reached, see `sampled_board`
+        let mut board = *root_node.board();
+        node.add_virtual_loss(virtual_loss);
+
+        loop {
+            let reached = sampled_board(config, &node, &board);
+            if reached.is_over() {
+                break;
+            }
+            let action = node.select_decoupled_action_among(
+                you,
+                config,
+                &legal_moves(config, &node, reached),
+            );
+            let child = if within_node_budget(config, nodes_added) {
+                node.child_for_action(action)
+            } else {
+                node.child(&action).map(|child| (child, false))
+            };
+            let Some((child, created)) = child else {
+                break;
+            };
+            node.descend_with_virtual_loss(&child, virtual_loss);
+            node = child;
+            depth += 1;
+            if config.spawn_food {
+                board = sample_next_board(&board, action, &mut rng).unwrap_or(*node.board());
+            }
+            if created {
+                nodes_added.fetch_add(1, Ordering::Relaxed);
+                break;
+            }
+        }
+
+        let board = sampled_board(config, &node, &board);
+        let rewards = rollout_leaf(board, config, evaluator, hazards, depth);
+
+        node.backpropagate_with_virtual_loss(rewards, virtual_loss);
     }
 }
 
-pub fn mcts_search(root_node: Arc<Node>, you: &SnakeId, stop: Arc<AtomicBool>) {
-    // TODO: We could look here if we can do this in parallel for different sub-trees by sorting and taking the best few
+fn joint_mcts_search<B, const N_SNAKES: usize>(
+    root_node: &Arc<Node<B, N_SNAKES>>,
+    you: &SnakeId,
+    stop: &AtomicBool,
+    config: &MctsConfig,
+    evaluator: &dyn Evaluator<B>,
+    nodes_added: &AtomicUsize,
+) where
+    B: SearchableGame + FallibleSimulableGame<Instruments, N_SNAKES>,
+{
+    let virtual_loss = config.virtual_loss;
+    let hazards = root_node.hazards(config);
+    let mut rng = rand::rng();
     loop {
         if stop.load(Ordering::Relaxed) {
             break;
         }
         let mut node = root_node.clone();
+        let mut depth = 0;
+        // The board this descent actually reached, see `sampled_board`
+        let mut board = *root_node.board();
+        node.add_virtual_loss(virtual_loss);
 
-        while !node.is_terminal() && node.is_fully_expanded() {
-            node = node
-                .best_child(1.4)
-                .expect("This should be none because we checked the variants here under which this would be None").1;
+        while node.is_fully_expanded() {
+            let reached = sampled_board(config, &node, &board);
+            if reached.is_over() {
+                break;
+            }
+            // Another thread may have taken the last joint action and not inserted its child yet,
+            // or no child agrees with the moves of the sampled board
+            let legal = legal_moves(config, &node, reached);
+            let Some((action, child)) = node.select_joint_child_among(you, config, &legal) else {
+                break;
+            };
+            node.descend_with_virtual_loss(&child, virtual_loss);
+            node = child;
+            depth += 1;
+            if config.spawn_food {
+                board = sample_next_board(&board, action, &mut rng).unwrap_or(*node.board());
+            }
         }
 
-        if !node.is_terminal() {
-            node.clone().expand(you);
+        if !sampled_board(config, &node, &board).is_over()
+            && within_node_budget(config, nodes_added)
+            && node.clone().expand(you)
+        {
+            nodes_added.fetch_add(1, Ordering::Relaxed);
         }
 
-        let result = node.clone().rollout(you);
+        let board = sampled_board(config, &node, &board);
+        let rewards = rollout_leaf(board, config, evaluator, hazards, depth);
 
-        node.backpropagate(result);
+        node.backpropagate_with_virtual_loss(rewards, virtual_loss);
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use battlesnake_game_types::{types::build_snake_id_map, wire_representation::Game as DEGame};
+    use crate::config::RolloutPolicy;
+    use crate::eval::{DefaultEvaluator, WeightedEvaluator};
+    use crate::reward::{OpponentModel, RewardKind};
+    use battlesnake_game_types::{
+        compact_representation::standard::CellBoard4Snakes11x11,
+        types::{HeadGettableGame, SimulableGame, SnakeIDGettableGame, build_snake_id_map},
+        wire_representation::Game as DEGame,
+    };
     use std::thread;
     use std::time::{Duration, Instant};
 
@@ -285,7 +1027,13 @@ mod tests {
 
         // Start mcts_search in a separate thread
         let search_thread = thread::spawn(move || {
-            mcts_search(root_clone, &you, stop_clone);
+            mcts_search(
+                root_clone,
+                &you,
+                stop_clone,
+                &MctsConfig::default(),
+                &DefaultEvaluator,
+            );
         });
 
         // Let it run for a bit to ensure it's actually searching
@@ -314,7 +1062,7 @@ mod tests {
         );
 
         // Verify that at least some work was done
-        let visits = root_node.visits.load(Ordering::Acquire);
+        let visits = root_node.stats.visits.load(Ordering::Acquire);
         assert!(
             visits > 0,
             "mcts_search should have performed at least some iterations"
@@ -339,16 +1087,828 @@ mod tests {
         let stop = Arc::new(AtomicBool::new(true)); // Already set to true
 
         // Run mcts_search with stop already set
-        mcts_search(root_node.clone(), &you, stop);
+        mcts_search(
+            root_node.clone(),
+            &you,
+            stop,
+            &MctsConfig::default(),
+            &DefaultEvaluator,
+        );
 
         // Verify no work was done
-        let visits = root_node.visits.load(Ordering::Acquire);
+        let visits = root_node.stats.visits.load(Ordering::Acquire);
         assert_eq!(
             visits, 0,
             "mcts_search should not perform any iterations when stop is already true"
         );
     }
 
+    #[test]
+    fn test_take_child_for_board_reuses_subtree() {
+        let game_fixture = include_str!("../../battlesnake-game-types/fixtures/start_of_game.json");
+        let game: DEGame = serde_json::from_str(game_fixture).expect("valid fixture");
+        let snake_id_map = build_snake_id_map(&game);
+        let board: CellBoard4Snakes11x11 = game.as_cell_board(&snake_id_map).expect("valid board");
+
+        let you = SnakeId(0);
+        let root_node = Arc::new(Node::new_root(board));
+        for _ in 0..200 {
+            let node = root_node.clone();
+            if node.is_fully_expanded() {
+                break;
+            }
+            node.clone().expand(&you);
+        }
+        let (action, child) = root_node.best_child(&you, 0.0).expect("root was expanded");
+        child.clone().backpropagate([1.0; 4]);
+        let next_board = child.board;
+
+        let new_root = root_node
+            .take_child_for_board(&next_board, &MctsConfig::default())
+            .expect("the child for the played moves should be found");
+        assert!(Arc::ptr_eq(&new_root, &child));
+        assert_eq!(new_root.stats.visits.load(Ordering::Acquire), 1);
+        assert!(new_root.parent_node.lock().unwrap().upgrade().is_none());
+        assert!(!root_node.next_nodes.lock().unwrap().contains_key(&action));
+
+        // Backpropagating through the new root must not touch the old one anymore
+        let old_root_visits = root_node.stats.visits.load(Ordering::Acquire);
+        new_root.clone().backpropagate([1.0; 4]);
+        assert_eq!(
+            root_node.stats.visits.load(Ordering::Acquire),
+            old_root_visits
+        );
+    }
+
+    #[test]
+    fn test_take_child_for_board_wraps_around_the_edges() {
+        use battlesnake_game_types::compact_representation::wrapped::CellBoard4SnakesSquare11x11;
+
+        // Snake 3 sits on the right edge, moving right takes it to the left edge
+        let game_fixture =
+            include_str!("../../battlesnake-game-types/fixtures/debug_wrapped/debug_game_2.json");
+        let game: DEGame = serde_json::from_str(game_fixture).expect("valid fixture");
+        let snake_id_map = build_snake_id_map(&game);
+        let board: CellBoard4SnakesSquare11x11 = game
+            .as_wrapped_cell_board(&snake_id_map)
+            .expect("valid board");
+
+        let you = SnakeId(0);
+        let root_node = Arc::new(Node::new_root(board));
+        while !root_node.is_fully_expanded() {
+            root_node.clone().expand(&you);
+        }
+        let (action, child) = root_node
+            .next_nodes
+            .lock()
+            .unwrap()
+            .iter()
+            .find(|(action, _)| action.into_inner()[3] == Some(Move::Right))
+            .map(|(action, child)| (*action, child.clone()))
+            .expect("snake 3 can move right");
+
+        let new_root = root_node
+            .take_child_for_board(&child.board, &MctsConfig::default())
+            .expect("the child for the played moves should be found");
+        assert!(Arc::ptr_eq(&new_root, &child));
+        assert!(!root_node.next_nodes.lock().unwrap().contains_key(&action));
+    }
+
+    #[test]
+    fn test_search_runs_on_wrapped_boards() {
+        use battlesnake_game_types::compact_representation::wrapped::BestCellBoard;
+
+        let you = SnakeId(0);
+        for fixture in [
+            include_str!("../../battlesnake-game-types/fixtures/debug_wrapped/debug_game_1.json"),
+            include_str!("../../battlesnake-game-types/fixtures/debug_wrapped/debug_game_100.json"),
+        ] {
+            let game: DEGame = serde_json::from_str(fixture).expect("valid fixture");
+            let snake_id_map = build_snake_id_map(&game);
+            let Ok(BestCellBoard::MediumExact(board)) =
+                BestCellBoard::from_game(game, &snake_id_map)
+            else {
+                panic!("expected an exactly 11x11 wrapped board");
+            };
+            let root_node = search_briefly(*board);
+            assert!(root_node.visits() > 0);
+            assert!(
+                root_node
+                    .best_move(&you, FinalMovePolicy::default())
+                    .is_some()
+            );
+        }
+    }
+
+    #[test]
+    fn test_take_child_for_board_falls_back_on_mismatch() {
+        use battlesnake_game_types::{
+            types::{HazardSettableGame, PositionGettableGame},
+            wire_representation::Position,
+        };
+
+        let game_fixture = include_str!("../../battlesnake-game-types/fixtures/start_of_game.json");
+        let game: DEGame = serde_json::from_str(game_fixture).expect("valid fixture");
+        let snake_id_map = build_snake_id_map(&game);
+        let board: CellBoard4Snakes11x11 = game.as_cell_board(&snake_id_map).expect("valid board");
+
+        let you = SnakeId(0);
+        let root_node = Arc::new(Node::new_root(board));
+        root_node.clone().expand(&you);
+        let (_, child) = root_node.best_child(&you, 0.0).expect("root was expanded");
+
+        // The same moves were played, but the board changed in a way the tree could not predict
+        let mut next_board = child.board;
+        next_board.set_hazard(next_board.native_from_position(Position::new(0, 0)));
+        assert!(
+            root_node
+                .take_child_for_board(&next_board, &MctsConfig::default())
+                .is_none()
+        );
+    }
+
+    #[test]
+    fn test_take_child_for_board_ignores_spawned_food_when_open_loop() {
+        use battlesnake_game_types::types::{EmptyCellGettableGame, FoodSettableGame};
+
+        let game_fixture = include_str!("../../battlesnake-game-types/fixtures/start_of_game.json");
+        let game: DEGame = serde_json::from_str(game_fixture).expect("valid fixture");
+        let snake_id_map = build_snake_id_map(&game);
+        let board: CellBoard4Snakes11x11 = game.as_cell_board(&snake_id_map).expect("valid board");
+
+        let you = SnakeId(0);
+        let root_node = Arc::new(Node::new_root(board));
+        root_node.clone().expand(&you);
+        let (_, child) = root_node.best_child(&you, 0.0).expect("root was expanded");
+        child.clone().expand(&you);
+        let (_, grandchild) = child.best_child(&you, 0.0).expect("child was expanded");
+        drop(child);
+
+        // The same moves were played and food spawned
+        let mut next_board = root_node
+            .next_nodes
+            .lock()
+            .unwrap()
+            .values()
+            .next()
+            .unwrap()
+            .board;
+        let spawn = next_board
+            .get_empty_cells()
+            .next()
+            .expect("the board has room");
+        next_board.set_food(spawn);
+        assert!(
+            root_node
+                .take_child_for_board(&next_board, &MctsConfig::default())
+                .is_none()
+        );
+
+        let config = MctsConfig {
+            spawn_food: true,
+            ..MctsConfig::default()
+        };
+        let new_root = root_node
+            .take_child_for_board(&next_board, &config)
+            .expect("only the food differs");
+        assert!(new_root.board == next_board);
+        assert!(new_root.parent_node.lock().unwrap().upgrade().is_none());
+
+        // The subtree still backpropagates in to the new root
+        grandchild.backpropagate([1.0; 4]);
+        assert_eq!(new_root.visits(), 1);
+    }
+
+    #[test]
+    fn test_best_move_uses_final_move_policy() {
+        let game_fixture = include_str!("../../battlesnake-game-types/fixtures/start_of_game.json");
+        let game: DEGame = serde_json::from_str(game_fixture).expect("valid fixture");
+        let snake_id_map = build_snake_id_map(&game);
+        let board: CellBoard4Snakes11x11 = game.as_cell_board(&snake_id_map).expect("valid board");
+
+        let you = SnakeId(0);
+        let root_node = Arc::new(Node::new_root(board));
+        while !root_node.is_fully_expanded() {
+            root_node.clone().expand(&you);
+        }
+        let children: Vec<_> = root_node
+            .next_nodes
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|(action, node)| (action.into_inner()[you.as_usize()], node.clone()))
+            .collect();
+        // Hand-built statistics: the first child is visited the most, the second has the best mean
+        // and the third is close to both
+        let robust = children
+            .iter()
+            .find(|(mv, _)| mv.is_some())
+            .expect("you have moves");
+        let max = children
+            .iter()
+            .find(|(mv, _)| mv.is_some() && *mv != robust.0)
+            .expect("you have more than one move");
+        let max_robust = children
+            .iter()
+            .find(|(mv, _)| mv.is_some() && *mv != robust.0 && *mv != max.0)
+            .expect("you have more than two moves");
+        for ((_, node), (visits, wins)) in
+            [robust, max, max_robust]
+                .into_iter()
+                .zip([(30, 12), (10, 9), (25, 20)])
+        {
+            node.stats.visits.store(visits, Ordering::Release);
+            for _ in 0..wins {
+                node.stats.rewards[you.as_usize()].add(1.0);
+            }
+        }
+        root_node.stats.visits.store(65, Ordering::Release);
+
+        assert_eq!(
+            root_node.best_move(&you, FinalMovePolicy::RobustChild),
+            robust.0
+        );
+        assert_eq!(root_node.best_move(&you, FinalMovePolicy::MaxChild), max.0);
+        assert_eq!(
+            root_node.best_move(&you, FinalMovePolicy::MaxRobust),
+            max_robust.0
+        );
+        // Selection during the search uses the mean, so without exploration the best mean wins
+        let ucb1 = |node: &Arc<Node<_, 4>>| Node::ucb1_from_ref(node, &you, 0.0, 65);
+        assert!(ucb1(&max.1) > ucb1(&max_robust.1));
+        assert!(ucb1(&max_robust.1) > ucb1(&robust.1));
+    }
+
+    #[test]
+    fn test_decoupled_search_keeps_per_snake_statistics() {
+        let game_fixture = include_str!("../../battlesnake-game-types/fixtures/4_snake_game.json");
+        let game: DEGame = serde_json::from_str(game_fixture).expect("valid fixture");
+        let snake_id_map = build_snake_id_map(&game);
+        let board: CellBoard4Snakes11x11 = game.as_cell_board(&snake_id_map).expect("valid board");
+
+        let you = SnakeId(0);
+        let root_node = Arc::new(Node::new_root_with_mode(board, SearchMode::Decoupled));
+        assert!(root_node.possible_moves.is_empty());
+
+        let stop = Arc::new(AtomicBool::new(false));
+        let stop_clone = Arc::clone(&stop);
+        let root_clone = Arc::clone(&root_node);
+        let search_thread = thread::spawn(move || {
+            mcts_search(
+                root_clone,
+                &you,
+                stop_clone,
+                &MctsConfig::default(),
+                &DefaultEvaluator,
+            );
+        });
+        thread::sleep(Duration::from_millis(100));
+        stop.store(true, Ordering::Relaxed);
+        search_thread.join().unwrap();
+
+        let root_visits = root_node.stats.visits.load(Ordering::Acquire);
+        assert!(root_visits > 0);
+        for (sid, moves) in &root_node.snake_moves {
+            let visits: u32 = moves
+                .iter()
+                .map(|mv| {
+                    root_node.stats.move_stats[sid.as_usize()][mv.as_index()]
+                        .visits
+                        .load(Ordering::Acquire)
+                })
+                .sum();
+            assert_eq!(
+                visits, root_visits,
+                "every iteration picks one move per snake"
+            );
+        }
+
+        let (_, my_moves) = root_node
+            .snake_moves
+            .iter()
+            .find(|(sid, _)| *sid == you)
+            .unwrap();
+        let best_move = root_node
+            .best_move(&you, FinalMovePolicy::default())
+            .expect("a move was searched");
+        assert!(my_moves.contains(&best_move));
+    }
+
+    #[test]
+    fn test_open_loop_search_keeps_food_spawns_out_of_the_tree() {
+        let game_fixture = include_str!("../../battlesnake-game-types/fixtures/4_snake_game.json");
+        let game: DEGame = serde_json::from_str(game_fixture).expect("valid fixture");
+        let snake_id_map = build_snake_id_map(&game);
+        let board: CellBoard4Snakes11x11 = game.as_cell_board(&snake_id_map).expect("valid board");
+
+        let you = SnakeId(0);
+        let root_node = Arc::new(Node::new_root_with_mode(board, SearchMode::Decoupled));
+        let stop = Arc::new(AtomicBool::new(false));
+        let stop_clone = Arc::clone(&stop);
+        let root_clone = Arc::clone(&root_node);
+        let search_thread = thread::spawn(move || {
+            let config = MctsConfig {
+                spawn_food: true,
+                ..MctsConfig::default()
+            };
+            mcts_search(root_clone, &you, stop_clone, &config, &DefaultEvaluator);
+        });
+        thread::sleep(Duration::from_millis(100));
+        stop.store(true, Ordering::Relaxed);
+        search_thread.join().unwrap();
+
+        assert!(root_node.stats.visits.load(Ordering::Acquire) > 0);
+        let next_nodes = root_node.next_nodes.lock().unwrap();
+        assert!(!next_nodes.is_empty());
+        let mut rng = rand::rng();
+        for (action, child) in next_nodes.iter() {
+            let moves: Vec<_> = action
+                .into_inner()
+                .into_iter()
+                .enumerate()
+                .filter_map(|(sid, mv)| mv.map(|mv| (SnakeId(sid as u8), [mv])))
+                .collect();
+            let (_, expected) = board
+                .simulate_with_moves(&Instruments, &moves)
+                .next()
+                .unwrap();
+            assert!(
+                child.board == expected,
+                "no food spawned on the tree boards"
+            );
+
+            // Sampled boards follow the same moves, only the food differs
+            let sampled = sample_next_board(&board, *action, &mut rng).unwrap();
+            for sid in board.get_snake_ids() {
+                assert_eq!(
+                    sampled.get_head_as_position(&sid),
+                    expected.get_head_as_position(&sid)
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_parallel_search_reverts_virtual_loss() {
+        let game_fixture = include_str!("../../battlesnake-game-types/fixtures/4_snake_game.json");
+        let game: DEGame = serde_json::from_str(game_fixture).expect("valid fixture");
+        let snake_id_map = build_snake_id_map(&game);
+        let board: CellBoard4Snakes11x11 = game.as_cell_board(&snake_id_map).expect("valid board");
+
+        fn assert_no_virtual_loss(node: &Node<CellBoard4Snakes11x11, 4>) {
+            assert_eq!(node.stats.virtual_loss.load(Ordering::Acquire), 0);
+            for stats in node.stats.move_stats.iter().flatten() {
+                assert_eq!(stats.virtual_loss.load(Ordering::Acquire), 0);
+            }
+            for child in node.next_nodes.lock().unwrap().values() {
+                assert_no_virtual_loss(child);
+            }
+        }
+
+        let config = MctsConfig {
+            threads: 4,
+            virtual_loss: 3,
+            ..MctsConfig::default()
+        };
+        for mode in [SearchMode::Joint, SearchMode::Decoupled] {
+            let root_node = Arc::new(Node::new_root_with_mode(board, mode));
+            let stop = Arc::new(AtomicBool::new(false));
+            let stop_clone = Arc::clone(&stop);
+            let root_clone = Arc::clone(&root_node);
+            let search_thread = thread::spawn(move || {
+                mcts_search(
+                    root_clone,
+                    &SnakeId(0),
+                    stop_clone,
+                    &config,
+                    &DefaultEvaluator,
+                );
+            });
+            thread::sleep(Duration::from_millis(100));
+            stop.store(true, Ordering::Relaxed);
+            search_thread.join().unwrap();
+
+            assert!(root_node.visits() > 0);
+            assert_no_virtual_loss(&root_node);
+        }
+    }
+
+    #[test]
+    fn test_root_parallel_trees_vote_on_move() {
+        let game_fixture = include_str!("../../battlesnake-game-types/fixtures/4_snake_game.json");
+        let game: DEGame = serde_json::from_str(game_fixture).expect("valid fixture");
+        let snake_id_map = build_snake_id_map(&game);
+        let board: CellBoard4Snakes11x11 = game.as_cell_board(&snake_id_map).expect("valid board");
+        let you = SnakeId(0);
+
+        let config = MctsConfig {
+            threads: 3,
+            parallel_mode: ParallelMode::Root,
+            ..MctsConfig::default()
+        };
+        let roots: Vec<_> = (0..config.trees())
+            .map(|_| Arc::new(Node::new_root(board)))
+            .collect();
+        assert_eq!(roots.len(), 3);
+
+        let stop = Arc::new(AtomicBool::new(false));
+        let stop_clone = Arc::clone(&stop);
+        let roots_clone = roots.clone();
+        let search_thread = thread::spawn(move || {
+            parallel_search(&roots_clone, &you, stop_clone, &config, &DefaultEvaluator);
+        });
+        thread::sleep(Duration::from_millis(100));
+        stop.store(true, Ordering::Relaxed);
+        search_thread.join().unwrap();
+
+        let mut votes = [0; 4];
+        for root in &roots {
+            assert!(root.visits() > 0, "every tree is searched");
+            for (votes, visits) in votes.iter_mut().zip(root.move_visits(&you)) {
+                *votes += visits;
+            }
+        }
+        let most_votes = *votes.iter().max().unwrap();
+        let chosen = ensemble_best_move(&roots, &you, FinalMovePolicy::default())
+            .expect("a move was searched");
+        assert_eq!(votes[chosen.as_index()], most_votes);
+    }
+
+    #[test]
+    fn test_select_decoupled_action_tries_unvisited_moves_first() {
+        let game_fixture = include_str!("../../battlesnake-game-types/fixtures/start_of_game.json");
+        let game: DEGame = serde_json::from_str(game_fixture).expect("valid fixture");
+        let snake_id_map = build_snake_id_map(&game);
+        let board: CellBoard4Snakes11x11 = game.as_cell_board(&snake_id_map).expect("valid board");
+
+        let you = SnakeId(0);
+        let node = Arc::new(Node::new_root_with_mode(board, SearchMode::Decoupled));
+        let (_, my_moves) = node
+            .snake_moves
+            .iter()
+            .find(|(sid, _)| *sid == you)
+            .unwrap()
+            .clone();
+
+        let mut seen = Vec::new();
+        for _ in 0..my_moves.len() {
+            let action = node.select_decoupled_action(&you, &MctsConfig::default());
+            let mv = action.into_inner()[you.as_usize()].expect("you are alive");
+            assert!(
+                !seen.contains(&mv),
+                "{mv} was selected twice before trying every move"
+            );
+            seen.push(mv);
+            node.stats.visits.fetch_add(1, Ordering::AcqRel);
+            node.record_action(action, &[1.0; 4], 0);
+        }
+
+        let (child, created) = node
+            .child_for_action(node.select_decoupled_action(&you, &MctsConfig::default()))
+            .unwrap();
+        assert!(created);
+        assert_eq!(child.mode(), SearchMode::Decoupled);
+    }
+
+    #[test]
+    fn test_opponents_follow_opponent_model() {
+        let game_fixture = include_str!("../../battlesnake-game-types/fixtures/start_of_game.json");
+        let game: DEGame = serde_json::from_str(game_fixture).expect("valid fixture");
+        let snake_id_map = build_snake_id_map(&game);
+        let board: CellBoard4Snakes11x11 = game.as_cell_board(&snake_id_map).expect("valid board");
+
+        let you = SnakeId(0);
+        let opponent = SnakeId(1);
+        let build = || {
+            let node = Node::new_root_with_mode(board, SearchMode::Decoupled);
+            let (_, moves) = node
+                .snake_moves
+                .iter()
+                .find(|(sid, _)| *sid == opponent)
+                .expect("the opponent is alive")
+                .clone();
+            // The first move is good for both snakes, every other move is bad for both
+            for (i, mv) in moves.iter().enumerate() {
+                let mut rewards = [0.0; 4];
+                if i == 0 {
+                    rewards[you.as_usize()] = 1.0;
+                    rewards[opponent.as_usize()] = 1.0;
+                }
+                node.stats.visits.fetch_add(1, Ordering::AcqRel);
+                let mut action = [None; 4];
+                action[opponent.as_usize()] = Some(*mv);
+                node.record_action(Action::new(action), &rewards, 0);
+            }
+            (node, moves)
+        };
+
+        let (node, moves) = build();
+        let config = |opponent_model| MctsConfig {
+            exploration_constant: 0.0,
+            opponent_model,
+            ..MctsConfig::default()
+        };
+        let action = node.select_decoupled_action(&you, &config(OpponentModel::MaxN));
+        assert_eq!(action.into_inner()[opponent.as_usize()], Some(moves[0]));
+
+        let action = node.select_decoupled_action(&you, &config(OpponentModel::Paranoid));
+        assert_ne!(action.into_inner()[opponent.as_usize()], Some(moves[0]));
+    }
+
+    #[test]
+    fn test_joint_selection_follows_opponent_model() {
+        let game_fixture = include_str!(
+            "../../battlesnake-game-types/fixtures/e80b70e7-a916-40ca-82d2-ad76e074efe1_0.json"
+        );
+        let game: DEGame = serde_json::from_str(game_fixture).expect("valid fixture");
+        let snake_id_map = build_snake_id_map(&game);
+        let board: CellBoard4Snakes11x11 = game.as_cell_board(&snake_id_map).expect("valid board");
+
+        let you = SnakeId(0);
+        let opponent = SnakeId(1);
+        let root_node = Arc::new(Node::new_root(board));
+        while !root_node.is_fully_expanded() {
+            root_node.clone().expand(&you);
+        }
+        let moves_of = |sid: SnakeId| {
+            let (_, moves) = root_node
+                .snake_moves
+                .iter()
+                .find(|(id, _)| *id == sid)
+                .expect("both snakes are alive")
+                .clone();
+            moves
+        };
+        let (mine, theirs) = (moves_of(you), moves_of(opponent));
+        // Your second move is the best on average. After it, the opponent's first move is the best
+        // for the opponent and its second move the worst for you
+        let children: Vec<_> = root_node
+            .next_nodes
+            .lock()
+            .unwrap()
+            .clone()
+            .into_iter()
+            .collect();
+        for (action, child) in children {
+            let action = action.into_inner();
+            let rewards = match (action[you.as_usize()], action[opponent.as_usize()]) {
+                (mv, _) if mv == Some(mine[0]) => [0.5, 0.5, 0.0, 0.0],
+                (mv, theirs_mv) if mv == Some(mine[1]) => {
+                    if theirs_mv == Some(theirs[0]) {
+                        [0.9, 1.0, 0.0, 0.0]
+                    } else if theirs_mv == Some(theirs[1]) {
+                        [0.6, 0.0, 0.0, 0.0]
+                    } else {
+                        [0.9, 0.0, 0.0, 0.0]
+                    }
+                }
+                _ => [0.0; 4],
+            };
+            child.backpropagate(rewards);
+        }
+
+        let config = |opponent_model| MctsConfig {
+            exploration_constant: 0.0,
+            opponent_model,
+            ..MctsConfig::default()
+        };
+        let picked = |opponent_model| {
+            let (action, _) = root_node
+                .select_joint_child(&you, &config(opponent_model))
+                .expect("root was expanded");
+            let action = action.into_inner();
+            (action[you.as_usize()], action[opponent.as_usize()])
+        };
+        assert_eq!(
+            picked(OpponentModel::MaxN),
+            (Some(mine[1]), Some(theirs[0]))
+        );
+        assert_eq!(
+            picked(OpponentModel::Paranoid),
+            (Some(mine[1]), Some(theirs[1]))
+        );
+    }
+
+    #[test]
+    fn test_selection_keeps_to_the_moves_of_the_sampled_board() {
+        let game_fixture = include_str!("../../battlesnake-game-types/fixtures/start_of_game.json");
+        let game: DEGame = serde_json::from_str(game_fixture).expect("valid fixture");
+        let snake_id_map = build_snake_id_map(&game);
+        let board: CellBoard4Snakes11x11 = game.as_cell_board(&snake_id_map).expect("valid board");
+
+        let you = SnakeId(0);
+        let root_node = Arc::new(Node::new_root(board));
+        while !root_node.is_fully_expanded() {
+            root_node.clone().expand(&you);
+        }
+        // On the sampled board only the last move of every snake is still possible
+        let legal: Vec<_> = root_node
+            .snake_moves
+            .iter()
+            .map(|(sid, moves)| (*sid, vec![*moves.last().unwrap()]))
+            .collect();
+        let expected: Vec<_> = legal.iter().map(|(sid, moves)| (*sid, moves[0])).collect();
+        let config = MctsConfig::default();
+
+        let (action, _) = root_node
+            .select_joint_child_among(&you, &config, &legal)
+            .expect("the child of the legal moves exists");
+        for (sid, mv) in &expected {
+            assert_eq!(action.into_inner()[sid.as_usize()], Some(*mv));
+        }
+
+        let decoupled = Node::new_root_with_mode(board, SearchMode::Decoupled);
+        let action = decoupled.select_decoupled_action_among(&you, &config, &legal);
+        for (sid, mv) in &expected {
+            assert_eq!(action.into_inner()[sid.as_usize()], Some(*mv));
+        }
+
+        // A snake that died on the sampled board picks nothing the tree doesn't need
+        let (action, _) = root_node
+            .select_joint_child_among(&you, &config, &legal[1..])
+            .expect("children for every move of you exist");
+        for (sid, mv) in &expected[1..] {
+            assert_eq!(action.into_inner()[sid.as_usize()], Some(*mv));
+        }
+    }
+
+    #[test]
+    fn test_backpropagate_keeps_rewards_per_snake() {
+        let game_fixture = include_str!("../../battlesnake-game-types/fixtures/4_snake_game.json");
+        let game: DEGame = serde_json::from_str(game_fixture).expect("valid fixture");
+        let snake_id_map = build_snake_id_map(&game);
+        let board: CellBoard4Snakes11x11 = game.as_cell_board(&snake_id_map).expect("valid board");
+
+        let root_node = Arc::new(Node::new_root(board));
+        root_node.clone().expand(&SnakeId(0));
+        let (_, child) = root_node
+            .best_child(&SnakeId(0), 0.0)
+            .expect("root was expanded");
+
+        child.clone().backpropagate([0.0, 1.0, 0.5, 0.25]);
+        for node in [&root_node, &child] {
+            assert_eq!(node.mean_reward(&SnakeId(0)), 0.0);
+            assert_eq!(node.mean_reward(&SnakeId(1)), 1.0);
+            assert_eq!(node.mean_reward(&SnakeId(2)), 0.5);
+            assert_eq!(node.mean_reward(&SnakeId(3)), 0.25);
+        }
+
+        let config = MctsConfig {
+            reward_kind: RewardKind::Placement,
+            ..MctsConfig::default()
+        };
+        let rewards = child.clone().rollout(&config, &DefaultEvaluator);
+        assert!(rewards.iter().all(|r| (0.0..=1.0).contains(r)));
+    }
+
+    #[test]
+    fn test_transpositions_share_statistics() {
+        let game_fixture = include_str!("../../battlesnake-game-types/fixtures/4_snake_game.json");
+        let game: DEGame = serde_json::from_str(game_fixture).expect("valid fixture");
+        let snake_id_map = build_snake_id_map(&game);
+        let board: CellBoard4Snakes11x11 = game.as_cell_board(&snake_id_map).expect("valid board");
+
+        // Two trees that reach the same positions, e.g. by different move orders
+        let table = Arc::new(NodeTable::new(1 << 10));
+        let first = Arc::new(Node::new_root_with_table(
+            board,
+            SearchMode::Joint,
+            Some(table.clone()),
+        ));
+        let second = Arc::new(Node::new_root_with_table(
+            board,
+            SearchMode::Joint,
+            Some(table.clone()),
+        ));
+        first.clone().expand(&SnakeId(0));
+        second.clone().expand(&SnakeId(0));
+        let (_, child) = first
+            .best_child(&SnakeId(0), 0.0)
+            .expect("root was expanded");
+        let (_, same_child) = second
+            .best_child(&SnakeId(0), 0.0)
+            .expect("root was expanded");
+        assert!(!Arc::ptr_eq(&child, &same_child));
+
+        child.clone().backpropagate([0.0, 1.0, 0.5, 0.25]);
+        for node in [&first, &second, &child, &same_child] {
+            assert_eq!(node.visits(), 1);
+            assert_eq!(node.mean_reward(&SnakeId(1)), 1.0);
+        }
+        // Without a table every tree keeps its own statistics
+        let pure = Arc::new(Node::new_root(board));
+        assert_eq!(pure.visits(), 0);
+
+        // The table stays within its buckets no matter how long the search runs
+        let config = MctsConfig {
+            transposition_table: Some(4),
+            ..MctsConfig::default()
+        };
+        let root_node = Arc::new(Node::new_root_with_config(board, &config));
+        let table = root_node
+            .table
+            .clone()
+            .expect("the config asks for a table");
+        let stop = Arc::new(AtomicBool::new(false));
+        let stop_clone = Arc::clone(&stop);
+        let root_clone = Arc::clone(&root_node);
+        let search_thread = thread::spawn(move || {
+            mcts_search(
+                root_clone,
+                &SnakeId(0),
+                stop_clone,
+                &config,
+                &DefaultEvaluator,
+            );
+        });
+        thread::sleep(Duration::from_millis(100));
+        stop.store(true, Ordering::Relaxed);
+        search_thread.join().expect("search finishes");
+        assert!(root_node.visits() > 0);
+        assert!(table.len() <= 8);
+    }
+
+    /// Searches `board` for a moment with every snake scored by the weighted evaluation
+    fn search_briefly<B, const N_SNAKES: usize>(board: B) -> Arc<Node<B, N_SNAKES>>
+    where
+        B: SearchableGame + FallibleSimulableGame<Instruments, N_SNAKES>,
+    {
+        let root_node = Arc::new(Node::new_root(board));
+        let stop = Arc::new(AtomicBool::new(false));
+        let stop_clone = Arc::clone(&stop);
+        let root_clone = Arc::clone(&root_node);
+        let search_thread = thread::spawn(move || {
+            let config = MctsConfig {
+                rollout_policy: RolloutPolicy::Cutoff,
+                ..MctsConfig::default()
+            };
+            mcts_search(
+                root_clone,
+                &SnakeId(0),
+                stop_clone,
+                &config,
+                &WeightedEvaluator::default(),
+            );
+        });
+        // Children are only visited once the root is fully expanded, which takes a while with four
+        // snakes on a large board in debug builds
+        thread::sleep(Duration::from_millis(50));
+        while root_node
+            .best_move(&SnakeId(0), FinalMovePolicy::default())
+            .is_none()
+        {
+            thread::sleep(Duration::from_millis(10));
+        }
+        stop.store(true, Ordering::Relaxed);
+        search_thread.join().unwrap();
+        root_node
+    }
+
+    #[test]
+    fn test_search_runs_on_other_board_sizes() {
+        use battlesnake_game_types::compact_representation::standard::{
+            CellBoard4Snakes7x7, CellBoard8Snakes15x15, CellBoard8Snakes25x25,
+        };
+
+        let you = SnakeId(0);
+        let game_fixture = include_str!("../../battlesnake-game-types/fixtures/7x7board.json");
+        let game: DEGame = serde_json::from_str(game_fixture).expect("valid fixture");
+        let snake_id_map = build_snake_id_map(&game);
+        let board = CellBoard4Snakes7x7::convert_from_game(game, &snake_id_map).expect("fits 7x7");
+        let root_node = search_briefly(board);
+        assert!(root_node.visits() > 0);
+        assert!(
+            root_node
+                .best_move(&you, FinalMovePolicy::default())
+                .is_some()
+        );
+
+        let game_fixture = include_str!("../../battlesnake-game-types/fixtures/4_snake_game.json");
+        let mut game: DEGame = serde_json::from_str(game_fixture).expect("valid fixture");
+        let snake_id_map = build_snake_id_map(&game);
+        let board = CellBoard8Snakes15x15::convert_from_game(game.clone(), &snake_id_map)
+            .expect("fits 15x15");
+        let root_node = search_briefly(board);
+        assert!(root_node.visits() > 0);
+        assert!(
+            root_node
+                .best_move(&you, FinalMovePolicy::default())
+                .is_some()
+        );
+
+        game.board.width = 19;
+        game.board.height = 23;
+        let board =
+            CellBoard8Snakes25x25::convert_from_game(game, &snake_id_map).expect("fits 25x25");
+        let root_node = search_briefly(board);
+        assert!(root_node.visits() > 0);
+        assert!(
+            root_node
+                .best_move(&you, FinalMovePolicy::default())
+                .is_some()
+        );
+    }
+
     #[test]
     fn test_move_combination_iterator() {
         // Test empty input - should yield one empty combination
@@ -0,0 +1,204 @@
+//! Rewards of rollouts, kept separately for every snake so each snake can act in its own interest
+
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use battlesnake_game_types::types::SnakeId;
+use serde::Deserialize;
+
+use crate::eval::Evaluator;
+use crate::game::SearchableGame;
+
+/// The reward of every snake for a single rollout, indexed by `SnakeId` and within `[0, 1]`
+pub type Rewards<const N_SNAKES: usize> = [f32; N_SNAKES];
+
+/// How the final board of a rollout is turned into [`Rewards`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RewardKind {
+    /// 1 for the winner and for every snake that is still alive when the rollout is cut off, 0
+    /// for everyone else
+    #[default]
+    WinLoss,
+    /// Snakes are ranked by how long they survived, 1 for the last one standing and 0 for the
+    /// first one to die
+    Placement,
+    /// Like [`RewardKind::WinLoss`], but snakes that are still alive when the rollout is cut off
+    /// are scored with the board evaluation
+    Evaluation,
+}
+
+/// Whose interest the opponents act in when they pick their moves during the search
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OpponentModel {
+    /// Every snake maximizes its own reward (max^n)
+    #[default]
+    MaxN,
+    /// Every opponent minimizes the reward of `you`
+    Paranoid,
+}
+
+impl OpponentModel {
+    /// The value `sid` maximizes when picking its move, given the mean reward of every snake
+    pub fn value(self, sid: &SnakeId, you: &SnakeId, mean: impl Fn(&SnakeId) -> f32) -> f32 {
+        match self {
+            OpponentModel::Paranoid if sid != you => 1.0 - mean(you),
+            _ => mean(sid),
+        }
+    }
+}
+
+/// Tracks when the snakes of a rollout died so they can be ranked at the end
+pub struct RolloutTracker<const N_SNAKES: usize> {
+    /// The depth at which each snake died, `None` while it's alive
+    deaths: [Option<u32>; N_SNAKES],
+    /// Snakes that were alive when the rollout started
+    participants: [bool; N_SNAKES],
+}
+
+impl<const N_SNAKES: usize> RolloutTracker<N_SNAKES> {
+    pub fn new(board: &impl SearchableGame) -> Self {
+        let participants = std::array::from_fn(|sid| board.is_alive(&SnakeId(sid as u8)));
+        Self {
+            deaths: [None; N_SNAKES],
+            participants,
+        }
+    }
+
+    /// Records the snakes that died on `board`, which was reached after `depth` turns
+    pub fn observe(&mut self, board: &impl SearchableGame, depth: u32) {
+        for sid in 0..N_SNAKES {
+            if self.participants[sid]
+                && self.deaths[sid].is_none()
+                && !board.is_alive(&SnakeId(sid as u8))
+            {
+                self.deaths[sid] = Some(depth);
+            }
+        }
+    }
+
+    /// The rewards for the final board of the rollout, `evaluator` scores the survivors with
+    /// [`RewardKind::Evaluation`]
+    pub fn rewards<B: SearchableGame>(
+        &self,
+        board: &B,
+        kind: RewardKind,
+        evaluator: &dyn Evaluator<B>,
+    ) -> Rewards<N_SNAKES> {
+        let winner = board.get_winner();
+        let is_over = board.is_over();
+        std::array::from_fn(|sid| {
+            let snake = SnakeId(sid as u8);
+            if !self.participants[sid] {
+                return 0.0;
+            }
+            match kind {
+                RewardKind::Placement => self.placement(sid),
+                _ if is_over => f32::from(u8::from(winner == Some(snake))),
+                _ if !board.is_alive(&snake) => 0.0,
+                RewardKind::WinLoss => 1.0,
+                RewardKind::Evaluation => evaluator.evaluate(board, &snake),
+            }
+        })
+    }
+
+    /// The share of the other participants `sid` outlived, ties count half
+    fn placement(&self, sid: usize) -> f32 {
+        let others = self.participants.iter().filter(|p| **p).count() - 1;
+        if others == 0 {
+            return f32::from(u8::from(self.deaths[sid].is_none()));
+        }
+        let survived = self.deaths[sid].unwrap_or(u32::MAX);
+        let outlived: f32 = (0..N_SNAKES)
+            .filter(|other| *other != sid && self.participants[*other])
+            .map(|other| {
+                let other_survived = self.deaths[other].unwrap_or(u32::MAX);
+                match survived.cmp(&other_survived) {
+                    std::cmp::Ordering::Greater => 1.0,
+                    std::cmp::Ordering::Equal => 0.5,
+                    std::cmp::Ordering::Less => 0.0,
+                }
+            })
+            .sum();
+        outlived / others as f32
+    }
+}
+
+/// Rewards are summed in fixed point so they can be updated atomically
+const FIXED_POINT_SCALE: f32 = 65536.0;
+
+/// The sum of the rewards of a single snake over many rollouts
+#[derive(Default)]
+pub struct RewardSum(AtomicU64);
+
+impl RewardSum {
+    pub fn add(&self, reward: f32) {
+        let fixed = (reward.clamp(0.0, 1.0) * FIXED_POINT_SCALE) as u64;
+        self.0.fetch_add(fixed, Ordering::AcqRel);
+    }
+
+    /// The average reward over `visits` rollouts, 0 if there were none yet
+    pub fn mean(&self, visits: u32) -> f32 {
+        if visits == 0 {
+            return 0.0;
+        }
+        let sum = self.0.load(Ordering::Relaxed) as f64 / FIXED_POINT_SCALE as f64;
+        (sum / visits as f64) as f32
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::eval::DefaultEvaluator;
+    use battlesnake_game_types::{
+        compact_representation::standard::CellBoard4Snakes11x11,
+        types::{VictorDeterminableGame, build_snake_id_map},
+        wire_representation::Game,
+    };
+
+    fn four_snake_board() -> CellBoard4Snakes11x11 {
+        let game_fixture = include_str!("../../battlesnake-game-types/fixtures/4_snake_game.json");
+        let game: Game = serde_json::from_str(game_fixture).expect("valid fixture");
+        let snake_id_map = build_snake_id_map(&game);
+        game.as_cell_board(&snake_id_map).expect("valid board")
+    }
+
+    #[test]
+    fn test_reward_sum_mean() {
+        let sum = RewardSum::default();
+        assert_eq!(sum.mean(0), 0.0);
+        sum.add(1.0);
+        sum.add(0.5);
+        sum.add(0.0);
+        assert!((sum.mean(3) - 0.5).abs() < 1e-4);
+    }
+
+    #[test]
+    fn test_placement_ranks_by_survival() {
+        let board = four_snake_board();
+        let mut tracker = RolloutTracker::<4>::new(&board);
+        assert_eq!(tracker.participants, [true; 4]);
+        tracker.deaths = [Some(3), Some(5), Some(5), None];
+
+        let rewards = tracker.rewards(&board, RewardKind::Placement, &DefaultEvaluator);
+        assert_eq!(rewards[0], 0.0);
+        assert!((rewards[1] - 0.5).abs() < 1e-6);
+        assert!((rewards[2] - 0.5).abs() < 1e-6);
+        assert_eq!(rewards[3], 1.0);
+    }
+
+    #[test]
+    fn test_win_loss_rewards_survivors_when_cut_off() {
+        let board = four_snake_board();
+        let tracker = RolloutTracker::<4>::new(&board);
+        assert!(!board.is_over());
+        assert_eq!(
+            tracker.rewards(&board, RewardKind::WinLoss, &DefaultEvaluator),
+            [1.0; 4]
+        );
+
+        let rewards = tracker.rewards(&board, RewardKind::Evaluation, &DefaultEvaluator);
+        assert!(rewards.iter().all(|r| *r > 0.0 && *r < 1.0));
+    }
+}
@@ -0,0 +1,395 @@
+//! Policies that play out a board from a leaf of the search tree to estimate the rewards
+
+use battlesnake_game_types::{
+    hazard_algorithms::RoyaleHazard,
+    types::{FallibleSimulableGame, Move, SnakeId},
+    wire_representation::Position,
+};
+use rand::{Rng, seq::IndexedRandom};
+
+use crate::config::{MctsConfig, RolloutPolicy};
+use crate::eval::{Evaluator, distance};
+use crate::game::{Instruments, SearchableGame};
+use crate::reward::{RewardKind, Rewards, RolloutTracker};
+
+/// The random events of the rules a board is played out with, besides the moves of the snakes
+#[derive(Debug, Clone, Copy, Default)]
+pub struct RolloutRules {
+    /// The board shrinks like a royale game
+    pub hazards: Option<RoyaleHazard>,
+    /// Food spawns after every turn with the food settings of the board
+    pub spawn_food: bool,
+}
+
+/// Plays out a board until it is over or the strategy stops and scores the final board, boards
+/// that aren't over are scored with `evaluator` where needed
+pub trait RolloutStrategy {
+    fn rollout<B, const N_SNAKES: usize>(
+        &self,
+        board: &B,
+        reward_kind: RewardKind,
+        evaluator: &dyn Evaluator<B>,
+        rules: RolloutRules,
+    ) -> Rewards<N_SNAKES>
+    where
+        B: SearchableGame + FallibleSimulableGame<Instruments, N_SNAKES>;
+}
+
+/// Every snake plays a uniformly random reasonable move for up to `depth` turns
+pub struct RandomRollout {
+    pub depth: u32,
+}
+
+/// Every snake plays the best move of a cheap one-ply heuristic, or with a chance of `epsilon` a
+/// random reasonable move, for up to `depth` turns
+pub struct EpsilonGreedyRollout {
+    pub depth: u32,
+    pub epsilon: f32,
+}
+
+/// Plays random moves for `plies` turns and scores the snakes that are still alive with the board
+/// evaluation instead of playing on, see [`RewardKind::Evaluation`]
+pub struct CutoffRollout {
+    pub plies: u32,
+}
+
+impl RolloutStrategy for RandomRollout {
+    fn rollout<B, const N_SNAKES: usize>(
+        &self,
+        board: &B,
+        reward_kind: RewardKind,
+        evaluator: &dyn Evaluator<B>,
+        rules: RolloutRules,
+    ) -> Rewards<N_SNAKES>
+    where
+        B: SearchableGame + FallibleSimulableGame<Instruments, N_SNAKES>,
+    {
+        let (board, tracker) = play_out(board, self.depth, rules, random_moves);
+        tracker.rewards(&board, reward_kind, evaluator)
+    }
+}
+
+impl RolloutStrategy for EpsilonGreedyRollout {
+    fn rollout<B, const N_SNAKES: usize>(
+        &self,
+        board: &B,
+        reward_kind: RewardKind,
+        evaluator: &dyn Evaluator<B>,
+        rules: RolloutRules,
+    ) -> Rewards<N_SNAKES>
+    where
+        B: SearchableGame + FallibleSimulableGame<Instruments, N_SNAKES>,
+    {
+        let (board, tracker) = play_out(board, self.depth, rules, |board, rng, moves| {
+            epsilon_greedy_moves(board, self.epsilon, rng, moves)
+        });
+        tracker.rewards(&board, reward_kind, evaluator)
+    }
+}
+
+impl RolloutStrategy for CutoffRollout {
+    fn rollout<B, const N_SNAKES: usize>(
+        &self,
+        board: &B,
+        reward_kind: RewardKind,
+        evaluator: &dyn Evaluator<B>,
+        rules: RolloutRules,
+    ) -> Rewards<N_SNAKES>
+    where
+        B: SearchableGame + FallibleSimulableGame<Instruments, N_SNAKES>,
+    {
+        let (board, tracker) = play_out(board, self.plies, rules, random_moves);
+        match reward_kind {
+            // The ranking of the snakes that died during the playout is still meaningful
+            RewardKind::Placement => tracker.rewards(&board, reward_kind, evaluator),
+            _ => tracker.rewards(&board, RewardKind::Evaluation, evaluator),
+        }
+    }
+}
+
+impl RolloutPolicy {
+    /// Plays out `board` with the strategy of this policy and the parameters of `config`, the
+    /// board shrinks with `hazards`
+    pub fn rollout<B, const N_SNAKES: usize>(
+        &self,
+        board: &B,
+        config: &MctsConfig,
+        evaluator: &dyn Evaluator<B>,
+        hazards: Option<RoyaleHazard>,
+    ) -> Rewards<N_SNAKES>
+    where
+        B: SearchableGame + FallibleSimulableGame<Instruments, N_SNAKES>,
+    {
+        let rules = RolloutRules {
+            hazards,
+            spawn_food: config.spawn_food,
+        };
+        match self {
+            RolloutPolicy::Random => RandomRollout {
+                depth: config.rollout_depth,
+            }
+            .rollout(board, config.reward_kind, evaluator, rules),
+            RolloutPolicy::EpsilonGreedy => EpsilonGreedyRollout {
+                depth: config.rollout_depth,
+                epsilon: config.rollout_epsilon,
+            }
+            .rollout(board, config.reward_kind, evaluator, rules),
+            RolloutPolicy::Cutoff => CutoffRollout {
+                plies: config.rollout_cutoff,
+            }
+            .rollout(board, config.reward_kind, evaluator, rules),
+        }
+    }
+}
+
+type Moves = Vec<(SnakeId, [Move; 1])>;
+
+/// Simulates up to `depth` turns with the moves `choose_moves` puts into the buffer it's given.
+/// The hazards of `rules` are added to the board first and advanced every turn, so the board
+/// shrinks like a royale game would
+fn play_out<B, const N_SNAKES: usize>(
+    board: &B,
+    depth: u32,
+    rules: RolloutRules,
+    mut choose_moves: impl FnMut(&B, &mut rand::rngs::ThreadRng, &mut Moves),
+) -> (B, RolloutTracker<N_SNAKES>)
+where
+    B: SearchableGame + FallibleSimulableGame<Instruments, N_SNAKES>,
+{
+    let mut rng = rand::rng();
+    let mut cur_board = *board;
+    let mut hazards = rules.hazards;
+    if let Some(hazards) = &hazards {
+        add_hazards(&mut cur_board, hazards);
+    }
+    let mut moves = Vec::with_capacity(N_SNAKES);
+    let mut tracker = RolloutTracker::new(&cur_board);
+
+    for ply in 1..=depth {
+        if cur_board.is_over() {
+            break;
+        }
+        moves.clear();
+        choose_moves(&cur_board, &mut rng, &mut moves);
+        // A board that can't be simulated ends the rollout instead of the search thread
+        let Some((_, next_board)) = cur_board
+            .try_simulate_with_moves(&Instruments, &moves)
+            .ok()
+            .and_then(|mut boards| boards.next()?.ok())
+        else {
+            break;
+        };
+        cur_board = next_board;
+        if let Some(hazards) = &mut hazards
+            && hazards.advance()
+        {
+            add_hazards(&mut cur_board, hazards);
+        }
+        if rules.spawn_food {
+            cur_board.place_food(&mut rng);
+        }
+        tracker.observe(&cur_board, ply);
+    }
+
+    (cur_board, tracker)
+}
+
+/// Marks every hazard of `hazards` on `board`
+fn add_hazards(board: &mut impl SearchableGame, hazards: &RoyaleHazard) {
+    for pos in hazards.hazards() {
+        board.set_hazard(board.native_from_position(pos));
+    }
+}
+
+fn random_moves(board: &impl SearchableGame, rng: &mut impl Rng, moves: &mut Moves) {
+    board
+        .random_reasonable_move_for_each_snake(rng)
+        .map(|(sid, mv)| (sid, [mv]))
+        .collect_into(moves);
+}
+
+fn epsilon_greedy_moves(
+    board: &impl SearchableGame,
+    epsilon: f32,
+    rng: &mut impl Rng,
+    moves: &mut Moves,
+) {
+    let food = board.get_all_food_as_positions();
+    for (sid, reasonable_moves) in board.reasonable_moves_for_each_snake() {
+        let mv = if rng.random::<f32>() < epsilon {
+            reasonable_moves.choose(rng).copied()
+        } else {
+            // Break ties randomly so equally good moves are all played
+            let best = reasonable_moves
+                .iter()
+                .map(|mv| heuristic_move_score(board, &sid, *mv, &food))
+                .max();
+            let best_moves: Vec<_> = reasonable_moves
+                .iter()
+                .filter(|mv| Some(heuristic_move_score(board, &sid, **mv, &food)) == best)
+                .collect();
+            best_moves.choose(rng).map(|mv| **mv)
+        };
+        if let Some(mv) = mv {
+            moves.push((sid, [mv]));
+        }
+    }
+}
+
+/// Health below which a snake heads for food
+const HUNGRY_HEALTH: i64 = 40;
+
+/// Scores `mv` of `sid` by looking a single move ahead: moving next to the head of a snake at
+/// least as long risks losing a head-to-head, and hungry snakes move towards food
+fn heuristic_move_score(
+    board: &impl SearchableGame,
+    sid: &SnakeId,
+    mv: Move,
+    food: &[Position],
+) -> i32 {
+    let head = board.get_head_as_position(sid).add_vec(mv.to_vector());
+    let length = board.get_length_i64(sid);
+    let mut score = 0;
+
+    for other in board.get_snake_ids() {
+        if other == *sid {
+            continue;
+        }
+        let other_head = board.get_head_as_position(&other);
+        if distance(board, &head, &other_head) == 1 {
+            if board.get_length_i64(&other) >= length {
+                score -= 100;
+            } else {
+                score += 10;
+            }
+        }
+    }
+
+    if board.get_health_i64(sid) < HUNGRY_HEALTH {
+        let food_distance = food.iter().map(|food| distance(board, &head, food)).min();
+        if let Some(food_distance) = food_distance {
+            score -= food_distance * 5;
+        }
+    }
+
+    score
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::eval::DefaultEvaluator;
+    use crate::game::royale_hazards;
+    use battlesnake_game_types::{
+        compact_representation::standard::CellBoard4Snakes11x11,
+        types::{
+            FoodGettableGame, HazardQueryableGame, HazardSettableGame, PositionGettableGame,
+            ReasonableMovesGame, VictorDeterminableGame, build_snake_id_map,
+        },
+        wire_representation::Game,
+    };
+
+    fn four_snake_board() -> CellBoard4Snakes11x11 {
+        let game_fixture = include_str!("../../battlesnake-game-types/fixtures/4_snake_game.json");
+        let game: Game = serde_json::from_str(game_fixture).expect("valid fixture");
+        let snake_id_map = build_snake_id_map(&game);
+        game.as_cell_board(&snake_id_map).expect("valid board")
+    }
+
+    #[test]
+    fn test_cutoff_rollout_scores_with_evaluation() {
+        let board = four_snake_board();
+        let rewards: Rewards<4> = CutoffRollout { plies: 0 }.rollout(
+            &board,
+            RewardKind::WinLoss,
+            &DefaultEvaluator,
+            RolloutRules::default(),
+        );
+        assert!(rewards.iter().all(|r| *r > 0.0 && *r < 1.0));
+    }
+
+    #[test]
+    fn test_every_policy_returns_rewards_in_range() {
+        let board = four_snake_board();
+        for policy in [
+            RolloutPolicy::Random,
+            RolloutPolicy::EpsilonGreedy,
+            RolloutPolicy::Cutoff,
+        ] {
+            let rewards: Rewards<4> =
+                policy.rollout(&board, &MctsConfig::default(), &DefaultEvaluator, None);
+            assert!(
+                rewards.iter().all(|r| (0.0..=1.0).contains(r)),
+                "{policy:?} returned {rewards:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_greedy_moves_pick_the_best_heuristic_move() {
+        let board = four_snake_board();
+        let mut rng = rand::rng();
+        let mut moves = Vec::new();
+        epsilon_greedy_moves(&board, 0.0, &mut rng, &mut moves);
+
+        for (sid, [mv]) in &moves {
+            let (_, reasonable_moves) = board
+                .reasonable_moves_for_each_snake()
+                .find(|(other, _)| other == sid)
+                .unwrap();
+            let food = board.get_all_food_as_positions();
+            let best = reasonable_moves
+                .iter()
+                .map(|mv| heuristic_move_score(&board, sid, *mv, &food))
+                .max()
+                .unwrap();
+            assert_eq!(heuristic_move_score(&board, sid, *mv, &food), best);
+        }
+    }
+
+    #[test]
+    fn test_play_out_shrinks_with_hazards() {
+        let game_fixture = include_str!("../../battlesnake-game-types/fixtures/another_crash.json");
+        let game: Game = serde_json::from_str(game_fixture).expect("valid fixture");
+        let hazards = royale_hazards(&game).expect("a royale game");
+        let snake_id_map = build_snake_id_map(&game);
+        let mut board: CellBoard4Snakes11x11 = game.as_cell_board(&snake_id_map).unwrap();
+        for pos in hazards.hazards() {
+            board.clear_hazard(board.native_from_position(pos));
+        }
+
+        let (board, _) = play_out::<_, 4>(
+            &board,
+            30,
+            RolloutRules {
+                hazards: Some(hazards),
+                spawn_food: false,
+            },
+            random_moves,
+        );
+        // The board shrinks on turn 75, unless the game is over before
+        assert!(
+            hazards
+                .hazards()
+                .all(|pos| board.is_hazard(&board.native_from_position(pos)))
+        );
+        let hazard_count = (0..11)
+            .flat_map(|x| (0..11).map(move |y| Position { x, y }))
+            .filter(|pos| board.is_hazard(&board.native_from_position(*pos)))
+            .count();
+        assert!(hazard_count > hazards.hazards().count() || board.is_over());
+    }
+
+    #[test]
+    fn test_play_out_keeps_the_minimum_food() {
+        let board = four_snake_board();
+        let rules = RolloutRules {
+            hazards: None,
+            spawn_food: true,
+        };
+        for _ in 0..20 {
+            let (board, _) = play_out::<_, 4>(&board, 50, rules, random_moves);
+            assert!(!board.get_all_food_as_positions().is_empty());
+        }
+    }
+}
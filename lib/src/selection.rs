@@ -0,0 +1,132 @@
+//! Selection policies used while descending the tree and for picking the move that is played
+
+use serde::Deserialize;
+
+/// UCB1 value of a child with the given mean reward, `mean + c * sqrt(ln N / n)`.
+/// Children that were never visited are always tried first.
+pub fn ucb1(mean: f32, visits: u32, parent_visits: u32, c: f32) -> f32 {
+    if visits == 0 {
+        return f32::INFINITY;
+    }
+    // ln(0) would be -inf, a parent always has at least as many visits as its children
+    let parent_visits = parent_visits.max(1) as f32;
+    mean.algebraic_add(c.algebraic_mul(parent_visits.ln().algebraic_div(visits as f32).sqrt()))
+}
+
+/// How the move that is actually played is picked once the search is done
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FinalMovePolicy {
+    /// The most visited child
+    #[default]
+    RobustChild,
+    /// The child with the highest mean reward
+    MaxChild,
+    /// The child that has both the most visits and the highest mean reward. If no child has
+    /// both, the one with the best minimum of its visits and mean relative to those maxima
+    MaxRobust,
+}
+
+impl FinalMovePolicy {
+    /// Picks one of the `candidates`, given as `(item, visits, mean reward)`.
+    /// Candidates that were never visited are ignored.
+    pub fn select<T>(self, candidates: impl IntoIterator<Item = (T, u32, f32)>) -> Option<T> {
+        let candidates: Vec<_> = candidates
+            .into_iter()
+            .filter(|(_, visits, _)| *visits > 0)
+            .collect();
+        let most_visited = || {
+            candidates
+                .iter()
+                .enumerate()
+                .max_by_key(|(_, (_, visits, _))| *visits)
+                .map(|(i, _)| i)
+        };
+        let highest_mean = || {
+            candidates
+                .iter()
+                .enumerate()
+                .max_by(|(_, (_, _, a)), (_, (_, _, b))| a.total_cmp(b))
+                .map(|(i, _)| i)
+        };
+        let index = match self {
+            FinalMovePolicy::RobustChild => most_visited(),
+            FinalMovePolicy::MaxChild => highest_mean(),
+            FinalMovePolicy::MaxRobust => {
+                // A child that has both maxima scores 1 here, otherwise the one that is closest
+                // to both is chosen
+                let (_, max_visits, _) = candidates[most_visited()?];
+                let (_, _, max_mean) = candidates[highest_mean()?];
+                let score = |visits: u32, mean: f32| {
+                    let visits = visits as f32 / max_visits as f32;
+                    let mean = if max_mean > 0.0 { mean / max_mean } else { 1.0 };
+                    visits.min(mean)
+                };
+                candidates
+                    .iter()
+                    .enumerate()
+                    .max_by(|(_, (_, va, ma)), (_, (_, vb, mb))| {
+                        score(*va, *ma).total_cmp(&score(*vb, *mb)).then(va.cmp(vb))
+                    })
+                    .map(|(i, _)| i)
+            }
+        }?;
+        candidates.into_iter().nth(index).map(|(item, _, _)| item)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ucb1_prefers_unvisited_children() {
+        assert_eq!(ucb1(0.0, 0, 10, 1.4), f32::INFINITY);
+        assert!(ucb1(1.0, 1, 10, 1.4).is_finite());
+    }
+
+    #[test]
+    fn test_ucb1_uses_mean_and_exploration_term() {
+        // Without exploration only the mean counts
+        assert_eq!(ucb1(0.75, 4, 16, 0.0), 0.75);
+        let expected = 0.5 + 2.0 * (100f32.ln() / 25.0).sqrt();
+        assert!((ucb1(0.5, 25, 100, 2.0) - expected).abs() < 1e-5);
+        // Less visited children get a larger exploration bonus
+        assert!(ucb1(0.5, 5, 100, 1.4) > ucb1(0.5, 50, 100, 1.4));
+    }
+
+    #[test]
+    fn test_robust_child_picks_most_visited() {
+        let children = [("a", 10, 0.9), ("b", 30, 0.5), ("c", 20, 0.6)];
+        assert_eq!(FinalMovePolicy::RobustChild.select(children), Some("b"));
+    }
+
+    #[test]
+    fn test_max_child_picks_highest_mean() {
+        let children = [("a", 10, 0.9), ("b", 30, 0.5), ("c", 20, 0.6)];
+        assert_eq!(FinalMovePolicy::MaxChild.select(children), Some("a"));
+    }
+
+    #[test]
+    fn test_max_child_ignores_unvisited_children() {
+        let children = [("a", 0, 1.0), ("b", 3, 0.2)];
+        assert_eq!(FinalMovePolicy::MaxChild.select(children), Some("b"));
+        let unvisited = [("a", 0, 0.0)];
+        assert_eq!(FinalMovePolicy::RobustChild.select(unvisited), None);
+    }
+
+    #[test]
+    fn test_max_robust_picks_child_that_is_both() {
+        let children = [("a", 10, 0.4), ("b", 30, 0.8), ("c", 20, 0.6)];
+        assert_eq!(FinalMovePolicy::MaxRobust.select(children), Some("b"));
+    }
+
+    #[test]
+    fn test_max_robust_balances_visits_and_mean() {
+        // a: min(10/30, 1.0) = 0.33, b: min(1.0, 0.4/0.9) = 0.44, c: min(25/30, 0.8/0.9) = 0.83
+        let children = [("a", 10, 0.9), ("b", 30, 0.4), ("c", 25, 0.8)];
+        assert_eq!(FinalMovePolicy::MaxRobust.select(children), Some("c"));
+        assert_eq!(FinalMovePolicy::RobustChild.select(children), Some("b"));
+        assert_eq!(FinalMovePolicy::MaxChild.select(children), Some("a"));
+    }
+}
@@ -0,0 +1,126 @@
+//! A bounded table from the hash of a board to the statistics of the position, so nodes of the
+//! search tree that reach the same position by different move orders share what they learned
+
+use std::sync::{Arc, Mutex};
+
+/// Entries of a [`TranspositionTable`], the weight decides which entry of a full bucket is
+/// replaced
+pub trait TableEntry: Default {
+    /// How much was invested into this entry, e.g. its visits
+    fn weight(&self) -> u32;
+}
+
+/// Number of entries that can live in a single bucket
+const BUCKET_SIZE: usize = 2;
+
+type Bucket<S> = [Option<(u64, Arc<S>)>; BUCKET_SIZE];
+
+/// A fixed number of buckets that each hold up to two entries, so the memory of the table is
+/// bounded no matter how many positions are searched. A new position replaces an entry no node
+/// uses anymore, or the lighter one of its bucket. Nodes that still hold a replaced entry keep
+/// it, they just don't share it anymore
+pub struct TranspositionTable<S> {
+    buckets: Vec<Mutex<Bucket<S>>>,
+}
+
+impl<S: TableEntry> TranspositionTable<S> {
+    /// A table with `buckets` buckets, at least one
+    pub fn new(buckets: usize) -> Self {
+        Self {
+            buckets: (0..buckets.max(1))
+                .map(|_| Mutex::new(Default::default()))
+                .collect(),
+        }
+    }
+
+    /// The entry of the position with the given hash, a new one if the position isn't in the
+    /// table
+    pub fn get_or_insert(&self, hash: u64) -> Arc<S> {
+        let mut bucket = self.buckets[(hash % self.buckets.len() as u64) as usize]
+            .lock()
+            .unwrap();
+        if let Some((_, entry)) = bucket.iter().flatten().find(|(key, _)| *key == hash) {
+            return entry.clone();
+        }
+        let slot = bucket
+            .iter_mut()
+            .min_by_key(|slot| match slot {
+                None => (0, 0),
+                // Only the table holds it, so no node can profit from it anymore
+                Some((_, entry)) if Arc::strong_count(entry) == 1 => (0, 0),
+                Some((_, entry)) => (1, entry.weight()),
+            })
+            .expect("buckets are never empty");
+        let entry = Arc::new(S::default());
+        *slot = Some((hash, entry.clone()));
+        entry
+    }
+
+    /// The number of positions in the table
+    pub fn len(&self) -> usize {
+        self.buckets
+            .iter()
+            .map(|bucket| bucket.lock().unwrap().iter().flatten().count())
+            .sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    #[derive(Default)]
+    struct Visits(AtomicU32);
+
+    impl TableEntry for Visits {
+        fn weight(&self) -> u32 {
+            self.0.load(Ordering::Relaxed)
+        }
+    }
+
+    #[test]
+    fn test_same_hash_shares_the_entry() {
+        let table = TranspositionTable::<Visits>::new(16);
+        let a = table.get_or_insert(42);
+        a.0.fetch_add(3, Ordering::Relaxed);
+        let b = table.get_or_insert(42);
+        assert!(Arc::ptr_eq(&a, &b));
+        assert_eq!(b.weight(), 3);
+        assert!(!Arc::ptr_eq(&a, &table.get_or_insert(43)));
+    }
+
+    #[test]
+    fn test_full_bucket_replaces_the_lightest_entry() {
+        let table = TranspositionTable::<Visits>::new(1);
+        let heavy = table.get_or_insert(1);
+        heavy.0.store(10, Ordering::Relaxed);
+        let light = table.get_or_insert(2);
+        light.0.store(1, Ordering::Relaxed);
+
+        let new = table.get_or_insert(3);
+        assert_eq!(table.len(), 2);
+        assert!(Arc::ptr_eq(&table.get_or_insert(1), &heavy));
+        assert!(Arc::ptr_eq(&table.get_or_insert(3), &new));
+        // The replaced entry is still usable, it just isn't found anymore
+        assert_eq!(light.weight(), 1);
+        assert!(!Arc::ptr_eq(&table.get_or_insert(2), &light));
+    }
+
+    #[test]
+    fn test_unused_entries_are_replaced_first() {
+        let table = TranspositionTable::<Visits>::new(1);
+        let heavy = table.get_or_insert(1);
+        heavy.0.store(10, Ordering::Relaxed);
+        drop(heavy);
+        let light = table.get_or_insert(2);
+        light.0.store(1, Ordering::Relaxed);
+
+        table.get_or_insert(3);
+        assert!(Arc::ptr_eq(&table.get_or_insert(2), &light));
+    }
+}
@@ -1,59 +1,225 @@
 use axum::response::Response;
 use axum::routing::{get, post};
 use axum::{Json, Router};
-use battlesnake_game_types::compact_representation::standard::CellBoard4Snakes11x11;
+use battlesnake_game_types::compact_representation::{standard, wrapped};
+use battlesnake_game_types::hazard_algorithms::RoyaleHazard;
 use battlesnake_game_types::types::{
-    build_snake_id_map, Move, SnakeIDGettableGame, SnakeIDMap, YouDeterminableGame,
+    build_snake_id_map, FallibleSimulableGame, Move, SnakeIDGettableGame, SnakeIDMap,
 };
 use battlesnake_game_types::wire_representation::Game;
-use lib::mcts::{mcts_search, Node};
+use lib::eval::DefaultEvaluator;
+use lib::game::{royale_hazards, Instruments, SearchableGame};
+use lib::mcts::{ensemble_best_move, parallel_search, Node};
+use lib::MctsConfig;
 use serde_json::{json, Value};
+use std::any::Any;
 use std::collections::BTreeMap;
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::{Arc, Mutex, OnceLock};
 use std::time::Duration;
-use tracing::info;
+use tracing::{error, info};
 
 pub static GAME_STATES: OnceLock<Mutex<BTreeMap<String, SnakeIDMap>>> = OnceLock::new();
+/// The search trees of the last move for each game, so rollouts can be reused on the next turn.
+/// Each game stores a `Vec<Arc<Node<B, N>>>` of the board type it was dispatched to
+pub static GAME_TREES: OnceLock<Mutex<BTreeMap<String, Box<dyn Any + Send>>>> = OnceLock::new();
+pub static MCTS_CONFIG: OnceLock<MctsConfig> = OnceLock::new();
 pub const PING: u64 = 60;
 pub const TIME_TO_MOVE: u64 = 500 - 2 * PING;
 #[global_allocator]
 static GLOBAL: mimalloc::MiMalloc = mimalloc::MiMalloc;
 
-pub fn decode_state(text: String) -> color_eyre::Result<CellBoard4Snakes11x11> {
+/// The smallest board representation a game fits in, for the rules it is played with
+pub enum BestCellBoard {
+    Standard(standard::BestCellBoard),
+    Wrapped(wrapped::BestCellBoard),
+}
+
+/// Converts the game into the smallest board representation it fits in, along with the shrinking
+/// of royale games
+pub fn decode_state(
+    text: String,
+) -> color_eyre::Result<(String, BestCellBoard, Option<RoyaleHazard>)> {
     let game: Game = serde_json::from_str(&text)?;
-    let binding = GAME_STATES.get().unwrap().lock().unwrap();
-    let snake_id_map = binding.get(&game.game.id).unwrap();
-    Ok(game.as_cell_board(snake_id_map).unwrap())
+    // Games we didn't see start, e.g. because the server restarted, number their snakes now
+    let snake_id_map = GAME_STATES
+        .get()
+        .and_then(|states| states.lock().unwrap().get(&game.game.id).cloned())
+        .unwrap_or_else(|| build_snake_id_map(&game));
+    let game_id = game.game.id.clone();
+    let hazards = royale_hazards(&game);
+    let board = if game.is_wrapped() {
+        wrapped::BestCellBoard::from_game(game, &snake_id_map).map(BestCellBoard::Wrapped)
+    } else {
+        standard::BestCellBoard::from_game(game, &snake_id_map).map(BestCellBoard::Standard)
+    }?;
+    Ok((game_id, board, hazards))
+}
+
+/// Loads the search config from the TOML file at `MCTS_CONFIG` if it is set. Single fields can be
+/// overridden with `MCTS_<FIELD>` environment variables, e.g. `MCTS_THREADS=4`
+fn load_mcts_config() -> color_eyre::Result<MctsConfig> {
+    let file = match std::env::var("MCTS_CONFIG") {
+        Ok(path) => Some(std::fs::read_to_string(path)?),
+        Err(_) => None,
+    };
+    mcts_config_from(file.as_deref(), std::env::vars())
+}
+
+/// The config of the TOML document `file` with the `MCTS_<FIELD>` overrides of `vars` applied
+fn mcts_config_from(
+    file: Option<&str>,
+    vars: impl IntoIterator<Item = (String, String)>,
+) -> color_eyre::Result<MctsConfig> {
+    let mut table = match file {
+        Some(file) => file.parse::<toml::Table>()?,
+        None => toml::Table::new(),
+    };
+    for (key, value) in vars {
+        let Some(field) = key.strip_prefix("MCTS_").filter(|field| *field != "CONFIG") else {
+            continue;
+        };
+        table.insert(field.to_lowercase(), parse_override(value));
+    }
+    Ok(table.try_into()?)
+}
+
+/// Parses an override as a TOML value like `4` or `1.4`. Anything else is taken as a string,
+/// e.g. `max_robust`
+fn parse_override(value: String) -> toml::Value {
+    format!("v = {value}")
+        .parse::<toml::Table>()
+        .ok()
+        .and_then(|mut table| table.remove("v"))
+        .unwrap_or(toml::Value::String(value))
 }
+
+/// Continues the search from the subtrees of the previous turn if the moves that were actually
+/// played lead to this board, otherwise starts from fresh roots. The roots shrink with `hazards`
+fn roots_for_turn<B, const N_SNAKES: usize>(
+    game_id: &str,
+    board: B,
+    hazards: Option<RoyaleHazard>,
+) -> Vec<Arc<Node<B, N_SNAKES>>>
+where
+    B: SearchableGame + FallibleSimulableGame<Instruments, N_SNAKES>,
+{
+    let config = MCTS_CONFIG.get_or_init(MctsConfig::default);
+    let previous_roots = GAME_TREES
+        .get_or_init(|| Mutex::new(BTreeMap::new()))
+        .lock()
+        .unwrap()
+        .remove(game_id)
+        .and_then(|trees| trees.downcast::<Vec<Arc<Node<B, N_SNAKES>>>>().ok())
+        .map(|roots| *roots)
+        .unwrap_or_default();
+    let reused_roots: Option<Vec<_>> = previous_roots
+        .iter()
+        .map(|root| root.take_child_for_board(&board, config))
+        .collect();
+    let roots = match reused_roots.filter(|roots| roots.len() == config.trees()) {
+        Some(roots) => {
+            info!("Reusing search trees of the previous turn");
+            roots
+        }
+        None => (0..config.trees())
+            .map(|_| Arc::new(Node::new_root_with_config(board, config)))
+            .collect(),
+    };
+    for root in &roots {
+        root.set_hazards(hazards);
+    }
+    roots
+}
+
 async fn get_move(body: String) -> Json<Value> {
-    let start = std::time::Instant::now();
     info!("Got move request: {}", body);
-    let board = decode_state(body).unwrap();
+    let (game_id, board, hazards) = match decode_state(body) {
+        Ok(state) => state,
+        Err(e) => {
+            error!("Could not decode the game, moving blindly: {e}");
+            return Json(json!({"move": Move::Down.to_string().to_lowercase()}));
+        }
+    };
+    let chosen_move = match board {
+        BestCellBoard::Standard(board) => match board {
+            standard::BestCellBoard::Tiny(board) => search(game_id, *board, hazards).await,
+            standard::BestCellBoard::SmallExact(board) => search(game_id, *board, hazards).await,
+            standard::BestCellBoard::Standard(board) => search(game_id, *board, hazards).await,
+            standard::BestCellBoard::MediumExact(board) => search(game_id, *board, hazards).await,
+            standard::BestCellBoard::LargestU8(board) => search(game_id, *board, hazards).await,
+            standard::BestCellBoard::LargeExact(board) => search(game_id, *board, hazards).await,
+            standard::BestCellBoard::ArcadeMaze(board) => search(game_id, *board, hazards).await,
+            standard::BestCellBoard::ArcadeMaze8Snake(board) => {
+                search(game_id, *board, hazards).await
+            }
+            standard::BestCellBoard::Large(board) => search(game_id, *board, hazards).await,
+            standard::BestCellBoard::Silly(board) => search(game_id, *board, hazards).await,
+        },
+        BestCellBoard::Wrapped(board) => match board {
+            wrapped::BestCellBoard::Tiny(board) => search(game_id, *board, hazards).await,
+            wrapped::BestCellBoard::SmallExact(board) => search(game_id, *board, hazards).await,
+            wrapped::BestCellBoard::Standard(board) => search(game_id, *board, hazards).await,
+            wrapped::BestCellBoard::MediumExact(board) => search(game_id, *board, hazards).await,
+            wrapped::BestCellBoard::LargestU8(board) => search(game_id, *board, hazards).await,
+            wrapped::BestCellBoard::LargeExact(board) => search(game_id, *board, hazards).await,
+            wrapped::BestCellBoard::ArcadeMaze(board) => search(game_id, *board, hazards).await,
+            wrapped::BestCellBoard::ArcadeMaze8Snake(board) => {
+                search(game_id, *board, hazards).await
+            }
+            wrapped::BestCellBoard::Large(board) => search(game_id, *board, hazards).await,
+            wrapped::BestCellBoard::Silly(board) => search(game_id, *board, hazards).await,
+        },
+    };
+    let lowercase_move = chosen_move.to_string().to_lowercase();
+    Json(json!({"move": lowercase_move}))
+}
+
+/// Searches `board` until it's time to answer and stores the trees for the next turn
+async fn search<B, const N_SNAKES: usize>(
+    game_id: String,
+    board: B,
+    hazards: Option<RoyaleHazard>,
+) -> Move
+where
+    B: SearchableGame + FallibleSimulableGame<Instruments, N_SNAKES>,
+{
+    let start = std::time::Instant::now();
     let you = *board.you_id();
-    let root_node = Arc::new(Node::new_root(board));
-    let root_node_clone = root_node.clone();
+    let config = *MCTS_CONFIG.get_or_init(MctsConfig::default);
+    let roots = roots_for_turn(&game_id, board, hazards);
+    let roots_clone = roots.clone();
     let stop_bool = Arc::new(AtomicBool::new(false));
     let stop_bool_ref = stop_bool.clone();
-    let _task = tokio::task::spawn_blocking(move || {
-        mcts_search(root_node_clone, &you, stop_bool_ref);
+    let task = tokio::task::spawn_blocking(move || {
+        parallel_search(
+            &roots_clone,
+            &you,
+            stop_bool_ref,
+            &config,
+            &DefaultEvaluator,
+        );
     });
     tokio::time::sleep(Duration::from_millis(TIME_TO_MOVE)).await;
     stop_bool.store(true, Ordering::Relaxed);
-    let chosen_move = root_node
-        .best_child(0.0)
-        .map(|c| c.0.own_move())
-        .unwrap_or_else(|| {
+    // Make sure the search is done before the tree gets stored for the next turn
+    let _ = task.await;
+    let chosen_move =
+        ensemble_best_move(&roots, &you, config.final_move_policy).unwrap_or_else(|| {
             info!("Could not get move in game!");
             Move::Down
         });
     info!(
         "Got move {chosen_move} in {:?} with depth {}",
         start.elapsed(),
-        root_node.get_depth()
+        roots[0].get_depth()
     );
-    let lowercase_move = chosen_move.to_string().to_lowercase();
-    Json(json!({"move": lowercase_move}))
+    GAME_TREES
+        .get_or_init(|| Mutex::new(BTreeMap::new()))
+        .lock()
+        .unwrap()
+        .insert(game_id, Box::new(roots));
+    chosen_move
 }
 
 async fn info() -> Json<Value> {
@@ -73,6 +239,9 @@ async fn end(body: String) -> Response {
     } else {
         info!("We lost the game {}", game_state.game.id);
     }
+    if let Some(trees) = GAME_TREES.get() {
+        trees.lock().unwrap().remove(&game_state.game.id);
+    }
 
     Response::default()
 }
@@ -106,6 +275,10 @@ async fn main() -> color_eyre::Result<()> {
 
     tracing_subscriber::fmt().init();
 
+    let config = load_mcts_config()?;
+    info!("Searching with {config:?}");
+    MCTS_CONFIG.get_or_init(|| config);
+
     info!("Starting battle-snake server...");
     let app = Router::new()
         .route("/", get(info))
@@ -121,3 +294,51 @@ async fn main() -> color_eyre::Result<()> {
     axum::serve(listener, app).await?;
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use lib::mcts::SearchMode;
+
+    fn vars(vars: &[(&str, &str)]) -> Vec<(String, String)> {
+        vars.iter()
+            .map(|(key, value)| (key.to_string(), value.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn test_env_overrides_load() {
+        let config = mcts_config_from(
+            None,
+            vars(&[
+                ("MCTS_THREADS", "4"),
+                ("MCTS_SEARCH_MODE", "decoupled"),
+                ("MCTS_EXPLORATION_CONSTANT", "0.5"),
+                ("MCTS_SPAWN_FOOD", "true"),
+                ("PORT", "8000"),
+            ]),
+        )
+        .unwrap();
+        assert_eq!(
+            config,
+            MctsConfig {
+                threads: 4,
+                search_mode: SearchMode::Decoupled,
+                exploration_constant: 0.5,
+                spawn_food: true,
+                ..MctsConfig::default()
+            }
+        );
+    }
+
+    #[test]
+    fn test_env_overrides_the_file() {
+        let config = mcts_config_from(
+            Some("threads = 2\nrollout_depth = 20\n"),
+            vars(&[("MCTS_THREADS", "8")]),
+        )
+        .unwrap();
+        assert_eq!(config.threads, 8);
+        assert_eq!(config.rollout_depth, 20);
+    }
+}